@@ -0,0 +1,92 @@
+use std::{
+    fs,
+    io::{ErrorKind, Write},
+    path::Path,
+};
+
+use chrono::Utc;
+use grindless_core::util::keyring::{generate_private_key_pem, SigningKey};
+use jsonwebtoken::Algorithm;
+
+pub static GENERATE_KEY_USAGE: &str =
+    "Usage: grindless-core-api generate-key [RS256|ES256|EdDSA] [--out <keys_dir>] [--stdout]";
+
+/// # Run Command
+///
+/// Runs the subcommand in `args` (without the binary name) if there is one. Returns `false`
+/// when there is nothing to run and the server should start.
+pub fn run_command(args: &[String]) -> bool {
+    match args.first().map(|a| a.as_str()) {
+        Some("generate-key") => {
+            if let Err(e) = generate_key_command(&args[1..]) {
+                eprintln!("Error generating key: {}", e);
+                eprintln!("{}", GENERATE_KEY_USAGE);
+                std::process::exit(1);
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// # Generate Key Command
+///
+/// Writes a new token signing key to `<keys_dir>/<kid>.pem`, the kid being the current UTC
+/// time so the new key sorts last and signs after a restart. With `--stdout` the PEM is
+/// printed instead, to paste into `TOKEN_SIGNING_KEY_PEM`.
+fn generate_key_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut algorithm = Algorithm::RS256;
+    let mut keys_dir = "keys".to_string();
+    let mut to_stdout = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "RS256" => algorithm = Algorithm::RS256,
+            "ES256" => algorithm = Algorithm::ES256,
+            "EdDSA" => algorithm = Algorithm::EdDSA,
+            "--out" => match args.next() {
+                Some(dir) => keys_dir = dir.clone(),
+                None => return Err("--out needs a directory".into()),
+            },
+            "--stdout" => to_stdout = true,
+            other => return Err(format!("unknown argument {}", other).into()),
+        }
+    }
+
+    let kid = Utc::now().format("%Y-%m-%dT%H%M%S").to_string();
+    let key_pem = generate_private_key_pem(algorithm)?;
+
+    // Make sure the server will be able to load it
+    SigningKey::from_private_pem(&kid, key_pem.as_bytes())?;
+
+    if to_stdout {
+        print!("{}", key_pem);
+        return Ok(());
+    }
+
+    let dir = Path::new(&keys_dir);
+    fs::create_dir_all(dir)?;
+
+    // create_new refuses to overwrite a key, and the key is never readable by others
+    let path = dir.join(format!("{}.pem", kid));
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = match options.open(&path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            return Err(format!("{} already exists", path.display()).into())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    file.write_all(key_pem.as_bytes())?;
+
+    println!("Token signing key generated: {} ({:?})", path.display(), algorithm);
+    Ok(())
+}
@@ -1,62 +1,411 @@
-use crate::{state::APIStateWrapper, util::get_claims_from_header};
-use actix_web::{HttpRequest, Responder, Result};
+use crate::{
+    export::{export_account, export_response},
+    extractors::{AuthenticatedAccount, RestorableAccount},
+    state::APIStateWrapper,
+};
+use actix_web::{web, Either, HttpResponse, Responder, Result};
+use chrono::{Duration, Utc};
 use grindless_core::{
-    entities::account_ops::AccountQueryCore,
-    response::response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk},
+    entities::{
+        account::ProfileField,
+        account_ops::{AccountMutationCore, AccountProfilePatch},
+        api_key::SCOPE_ACCOUNTS_READ,
+        session_ops::SessionMutationCore,
+    },
+    feature_flags::enabled_features,
+    permissions::permissions_for,
+    response::{
+        errors::Errors,
+        response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk, ResponseObject, ResponseObjectError},
+    },
+    Timestamp,
 };
-use serde_json::json;
+use log::error;
+use reqwest::Url;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Map, Value};
+
+pub static MAX_NAME_LENGTH: usize = 64;
+pub static MAX_AVATAR_URL_LENGTH: usize = 2048;
+pub static MAX_PREFERENCES: usize = 64;
+pub static MAX_PREFERENCE_KEY_LENGTH: usize = 64;
+pub static MAX_PREFERENCES_BYTES: usize = 8192;
+
+pub async fn get_me_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<impl Responder> {
+    authenticated.require_scope(SCOPE_ACCOUNTS_READ)?;
 
-pub async fn get_me_controller(req: HttpRequest, state: APIStateWrapper) -> Result<impl Responder> {
-    let claims = match get_claims_from_header(
-        req.headers().get("Authorization"),
-        state.pems.tokens_public.clone(),
-    ) {
-        Ok(c) => c,
-        Err(e) => {
-            return Ok(e);
+    // a feature the client doesn't hear about stays hidden, so this is not worth failing for
+    let features = match enabled_features(&state.databases.postgres_conn, &authenticated.account).await {
+        Ok(f) => f,
+        Err(err) => {
+            error!("Error getting features of account {} {:?}", authenticated.account.id, err);
+            vec![]
         }
     };
 
-    let account_and_billing = match AccountQueryCore::get_account_by_id_with_billing(
-        &state.databases.postgres_conn,
-        claims.sub,
-    )
-    .await
-    {
-        Ok(a) => a,
-        Err(_) => {
-            return Ok(build_err(ResponseBuilderError {
-                message: "Error getting account".to_string(),
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Account found".to_string()),
+        data: Some(json!({
+            "permissions": permissions_for(&authenticated.account.flags),
+            "features": features,
+            "account": authenticated.account,
+            "billing": authenticated.billing
+        })),
+    }))
+}
+
+/// # Export Me Controller
+///
+/// Downloads everything stored for the account as a zip of JSON and CSV files.
+pub async fn export_me_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<Either<HttpResponse, web::Json<ResponseObject<()>>>> {
+    authenticated.require_session()?;
+
+    match export_account(&state.databases.postgres_conn, &authenticated.account, Some(&authenticated.billing)).await {
+        Ok(archive) => Ok(Either::Left(export_response(authenticated.account.id, archive))),
+        Err(err) => {
+            error!("Error exporting account {} {:?}", authenticated.account.id, err);
+            Ok(Either::Right(build_err(ResponseBuilderError {
+                message: "Error exporting account".to_string(),
+                errors: vec![],
+            })))
+        }
+    }
+}
+
+/// Tells a field sent as `null` apart from one that was left out
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub struct PatchMeBody {
+    pub name: Option<String>,
+    /// `null` removes the avatar
+    #[serde(default, deserialize_with = "nullable")]
+    pub avatar: Option<Option<String>>,
+    /// Merged into the stored preferences, a `null` value removes the key
+    pub preferences: Option<Map<String, Value>>,
+}
+
+fn field_error(field: &str, message: String) -> ResponseObjectError {
+    ResponseObjectError {
+        error_id: Errors::UnprocessableEntity,
+        message: Some(message),
+        field: Some(field.to_string()),
+    }
+}
+
+pub(crate) fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(format!("Name must be between 1 and {} characters", MAX_NAME_LENGTH));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("Name can't contain control characters".to_string());
+    }
+
+    Ok(name.to_string())
+}
+
+fn validate_avatar(avatar: &str) -> Result<String, String> {
+    if avatar.len() > MAX_AVATAR_URL_LENGTH {
+        return Err(format!("Avatar URL can't be longer than {} characters", MAX_AVATAR_URL_LENGTH));
+    }
+
+    match Url::parse(avatar) {
+        Ok(url) if url.scheme() == "https" && url.host().is_some() => Ok(url.to_string()),
+        _ => Err("Avatar must be an https URL".to_string()),
+    }
+}
+
+fn merge_preferences(current: &Value, changes: &Map<String, Value>) -> Result<Value, String> {
+    let mut merged = current.as_object().cloned().unwrap_or_default();
+    for (key, value) in changes {
+        if key.is_empty() || key.chars().count() > MAX_PREFERENCE_KEY_LENGTH {
+            return Err(format!("Preference keys must be between 1 and {} characters", MAX_PREFERENCE_KEY_LENGTH));
+        }
+
+        match value {
+            Value::Null => merged.remove(key),
+            v => merged.insert(key.clone(), v.clone()),
+        };
+    }
+
+    if merged.len() > MAX_PREFERENCES {
+        return Err(format!("Can't store more than {} preferences", MAX_PREFERENCES));
+    }
+
+    let merged = Value::Object(merged);
+    if merged.to_string().len() > MAX_PREFERENCES_BYTES {
+        return Err(format!("Preferences can't be larger than {} bytes", MAX_PREFERENCES_BYTES));
+    }
+
+    Ok(merged)
+}
+
+/// # Patch Me Controller
+///
+/// Changes only the fields sent. Names and avatars set here are locked, so the next login
+/// doesn't put the provider's back. Every invalid field is reported, each with its `field`.
+pub async fn patch_me_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    data: web::Json<PatchMeBody>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let account = &authenticated.account;
+    let mut patch = AccountProfilePatch::default();
+    let mut locked_fields = account.locked_fields.clone();
+    let mut errors = vec![];
+
+    if let Some(name) = &data.name {
+        match validate_name(name) {
+            Ok(n) => {
+                patch.name = Some(n);
+                locked_fields.push(ProfileField::Name);
+            }
+            Err(e) => errors.push(field_error("name", e)),
+        }
+    }
+
+    if let Some(avatar) = &data.avatar {
+        match avatar.as_deref().map(validate_avatar).transpose() {
+            Ok(a) => {
+                patch.avatar = Some(a);
+                locked_fields.push(ProfileField::Avatar);
+            }
+            Err(e) => errors.push(field_error("avatar", e)),
+        }
+    }
+
+    if let Some(preferences) = &data.preferences {
+        match merge_preferences(&account.preferences, preferences) {
+            Ok(p) => patch.preferences = Some(p),
+            Err(e) => errors.push(field_error("preferences", e)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Ok(build_err(ResponseBuilderError {
+            message: "Invalid fields".to_string(),
+            errors,
+        }));
+    }
+
+    if patch.is_empty() {
+        return Ok(build_err(ResponseBuilderError {
+            message: "Nothing to update".to_string(),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::BadRequest,
+                message: None,
+                field: None,
+            }],
+        }));
+    }
+
+    locked_fields.sort();
+    locked_fields.dedup();
+    if locked_fields != account.locked_fields {
+        patch.locked_fields = Some(locked_fields);
+    }
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    match AccountMutationCore::patch_account_profile(&state.databases.postgres_conn, account.id, patch, now_unix).await {
+        Ok(account) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Account updated".to_string()),
+            data: Some(json!({ "account": account })),
+        })),
+        Err(err) => {
+            error!("Error updating account {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error updating account".to_string(),
                 errors: vec![],
             }))
         }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct LockedFieldsForm {
+    /// Comma separated, e.g. `name,avatar`, empty unlocks everything
+    pub fields: Option<String>,
+}
+
+fn parse_profile_field(field: &str) -> Option<ProfileField> {
+    match field {
+        "name" => Some(ProfileField::Name),
+        "email" => Some(ProfileField::Email),
+        "avatar" => Some(ProfileField::Avatar),
+        _ => None,
+    }
+}
+
+/// # Update Locked Fields Controller
+///
+/// Replaces the profile fields that logins leave alone instead of copying them from the
+/// identity provider.
+pub async fn update_locked_fields_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    data: web::Form<LockedFieldsForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let mut locked_fields = vec![];
+    for field in data.fields.as_deref().unwrap_or_default().split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        match parse_profile_field(field) {
+            Some(f) if !locked_fields.contains(&f) => locked_fields.push(f),
+            Some(_) => (),
+            None => {
+                return Ok(build_err(ResponseBuilderError {
+                    message: format!("Unknown profile field {}", field),
+                    errors: vec![ResponseObjectError {
+                        error_id: Errors::BadRequest,
+                        message: Some("fields can be name, email and avatar".to_string()),
+                        field: None,
+                    }],
+                }))
+            }
+        }
+    }
+    locked_fields.sort();
+
+    let patch = AccountProfilePatch {
+        locked_fields: Some(locked_fields),
+        ..Default::default()
     };
 
-    let (account, billing)  = match account_and_billing {
-        Some(a) => a,
-        None => {
-            return Ok(build_err(ResponseBuilderError {
-                message: "Account not found".to_string(),
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    match AccountMutationCore::patch_account_profile(&state.databases.postgres_conn, authenticated.account.id, patch, now_unix).await {
+        Ok(account) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Locked fields updated".to_string()),
+            data: Some(json!({ "locked_fields": account.locked_fields })),
+        })),
+        Err(err) => {
+            error!("Error updating locked fields {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error updating locked fields".to_string(),
                 errors: vec![],
             }))
         }
-    };
+    }
+}
+
+pub static MAX_DELETION_REASON_LENGTH: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct DeleteAccountForm {
+    pub reason: Option<String>,
+}
+
+/// # Delete Me Controller
+///
+/// Schedules the account for deletion and logs it out everywhere. It can be restored by
+/// logging in again until the grace period ends, then its personal data is purged.
+pub async fn delete_me_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    data: web::Form<DeleteAccountForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
 
-    let billing = match billing {
-        Some(b) => b,
-        None => {
+    let reason = data.reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    if reason.is_some_and(|r| r.chars().count() > MAX_DELETION_REASON_LENGTH) {
+        return Ok(build_err(ResponseBuilderError {
+            message: format!("Reason can't be longer than {} characters", MAX_DELETION_REASON_LENGTH),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::BadRequest,
+                message: None,
+                field: None,
+            }],
+        }));
+    }
+
+    let now = Utc::now();
+    let now_unix = now.timestamp() as Timestamp;
+    match AccountMutationCore::request_account_deletion(
+        &state.databases.postgres_conn,
+        authenticated.account.id,
+        reason.map(str::to_string),
+        now_unix,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => {
             return Ok(build_err(ResponseBuilderError {
-                message: "Billing not found".to_string(),
-                errors: vec![],
+                message: "Account is already scheduled for deletion".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::Conflict,
+                    message: None,
+                    field: None,
+                }],
             }))
         }
-    };
+        Err(err) => {
+            error!("Error requesting account deletion {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error deleting account".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    if let Err(err) = SessionMutationCore::revoke_all_sessions(&state.databases.postgres_conn, authenticated.account.id, now_unix).await {
+        error!("Error revoking sessions of deleted account {:?}", err);
+    }
 
+    let purge_at = now + Duration::days(state.env.account_deletion_grace_days);
     Ok(build_ok(ResponseBuilderOk {
-        message: Some("Account found".to_string()),
-        data: Some(json!({
-            "account": account,
-            "billing": billing
-        })),
+        message: Some("Account scheduled for deletion".to_string()),
+        data: Some(json!({ "purge_at": purge_at.timestamp() })),
     }))
 }
+
+/// # Restore Me Controller
+///
+/// Cancels the scheduled deletion of the account.
+pub async fn restore_me_controller(
+    state: APIStateWrapper,
+    restorable: RestorableAccount,
+) -> Result<impl Responder> {
+    let authenticated = restorable.0;
+    authenticated.require_session()?;
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    match AccountMutationCore::restore_account(&state.databases.postgres_conn, authenticated.account.id, now_unix).await {
+        Ok(true) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Account restored".to_string()),
+            data: Some(json!({ "account_id": authenticated.account.id })),
+        })),
+        Ok(false) => Ok(build_err(ResponseBuilderError {
+            message: "Account is not scheduled for deletion".to_string(),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::Conflict,
+                message: None,
+                field: None,
+            }],
+        })),
+        Err(err) => {
+            error!("Error restoring account {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error restoring account".to_string(),
+                errors: vec![],
+            }))
+        }
+    }
+}
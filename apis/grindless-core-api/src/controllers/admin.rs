@@ -0,0 +1,690 @@
+use crate::{
+    controllers::ledger::LEDGER_ENTRIES_SHOWN,
+    export::{export_account, export_response},
+    extractors::AuthenticatedAccount,
+    state::APIStateWrapper,
+};
+use actix_web::{web, Either, HttpResponse, Responder, Result};
+use chrono::Utc;
+use grindless_core::{
+    entities::{
+        account::{self, AccountFlags},
+        account_ops::{AccountMutationCore, AccountQueryCore, AccountSearch, AccountSort},
+        admin_action,
+        admin_action_ops::{AdminActionMutationCore, AdminActionQueryCore},
+        ledger_entry::LedgerReason,
+        ledger_entry_ops::{CreditMovement, LedgerEntryQueryCore},
+        provider_identity_ops::ProviderIdentityQueryCore,
+        session_ops::SessionMutationCore,
+    },
+    permissions::Permission,
+    response::{
+        errors::Errors,
+        response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk, ResponseObject, ResponseObjectError},
+    },
+    util::snowflake::new_id,
+    Timestamp, ID,
+};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+pub static DEFAULT_ACCOUNTS_PER_PAGE: u64 = 25;
+pub static MAX_ACCOUNTS_PER_PAGE: u64 = 100;
+pub static MAX_ADMIN_REASON_LENGTH: usize = 500;
+pub static ADMIN_ACTIONS_SHOWN: u64 = 100;
+pub static MAX_CREDIT_ADJUSTMENT: i64 = 1_000_000_000;
+
+fn not_found() -> ResponseBuilderError {
+    ResponseBuilderError {
+        message: "Account not found".to_string(),
+        errors: vec![ResponseObjectError {
+            error_id: Errors::NotFound,
+            message: None,
+            field: None,
+        }],
+    }
+}
+
+fn invalid_field(field: &str, message: String) -> ResponseBuilderError {
+    ResponseBuilderError {
+        message: "Invalid fields".to_string(),
+        errors: vec![ResponseObjectError {
+            error_id: Errors::UnprocessableEntity,
+            message: Some(message),
+            field: Some(field.to_string()),
+        }],
+    }
+}
+
+/// Staff can't change their own account from here, so nobody locks themselves out or
+/// hands themselves credits
+fn not_self(authenticated: &AuthenticatedAccount, account_id: ID) -> Result<(), ResponseBuilderError> {
+    match authenticated.account.id == account_id {
+        true => Err(ResponseBuilderError {
+            message: "Staff can't use the admin API on their own account".to_string(),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::Forbidden,
+                message: None,
+                field: None,
+            }],
+        }),
+        false => Ok(()),
+    }
+}
+
+fn optional_reason(reason: &Option<String>) -> Result<Option<String>, ResponseBuilderError> {
+    match reason.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        Some(r) if r.chars().count() > MAX_ADMIN_REASON_LENGTH => Err(invalid_field(
+            "reason",
+            format!("Reason can't be longer than {} characters", MAX_ADMIN_REASON_LENGTH),
+        )),
+        r => Ok(r.map(str::to_string)),
+    }
+}
+
+fn required_reason(reason: &Option<String>) -> Result<String, ResponseBuilderError> {
+    optional_reason(reason)?.ok_or_else(|| invalid_field("reason", "A reason is required".to_string()))
+}
+
+async fn get_account(state: &APIStateWrapper, account_id: ID) -> Result<account::Model, ResponseBuilderError> {
+    match AccountQueryCore::get_account_by_id(&state.databases.postgres_conn, account_id).await {
+        Ok(Some(a)) => Ok(a),
+        Ok(None) => Err(not_found()),
+        Err(err) => {
+            error!("Error getting account {:?}", err);
+            Err(ResponseBuilderError {
+                message: "Error getting account".to_string(),
+                errors: vec![],
+            })
+        }
+    }
+}
+
+/// Adds the action to the audit log. The action already happened, so a failure here is
+/// only logged.
+async fn record_admin_action(
+    state: &APIStateWrapper,
+    authenticated: &AuthenticatedAccount,
+    target_account_id: ID,
+    action: &str,
+    reason: Option<String>,
+    details: Value,
+) {
+    info!("Account {} did {} on account {}", authenticated.account.id, action, target_account_id);
+
+    if let Err(err) = AdminActionMutationCore::create_admin_action(
+        &state.databases.postgres_conn,
+        admin_action::Model {
+            id: new_id(),
+            admin_account_id: authenticated.account.id,
+            target_account_id: Some(target_account_id),
+            action: action.to_string(),
+            reason,
+            details,
+            created_at: Utc::now().timestamp() as Timestamp,
+        },
+    )
+    .await
+    {
+        error!("Error recording admin action {} on account {} {:?}", action, target_account_id, err);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SearchAccountsQuery {
+    pub query: Option<String>,
+    /// `id`, `email`, `name`, `created_at` or `updated_at`
+    pub sort: Option<String>,
+    /// `asc` or `desc`
+    pub order: Option<String>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+/// # Search Accounts Controller
+///
+/// Finds accounts by part of their email or name, their id, or the id a provider knows
+/// them by. Newest first unless sorted otherwise.
+pub async fn search_accounts_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    query: web::Query<SearchAccountsQuery>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::ViewAccounts)?;
+
+    let sort = match query.sort.as_deref().map(AccountSort::from_string) {
+        None => AccountSort::CreatedAt,
+        Some(Some(s)) => s,
+        Some(None) => {
+            return Ok(build_err(invalid_field(
+                "sort",
+                "Sort by id, email, name, created_at or updated_at".to_string(),
+            )))
+        }
+    };
+
+    let descending = match query.order.as_deref() {
+        None | Some("desc") => true,
+        Some("asc") => false,
+        Some(_) => return Ok(build_err(invalid_field("order", "Order is asc or desc".to_string()))),
+    };
+
+    let per_page = query.per_page.unwrap_or(DEFAULT_ACCOUNTS_PER_PAGE);
+    if per_page == 0 || per_page > MAX_ACCOUNTS_PER_PAGE {
+        return Ok(build_err(invalid_field(
+            "per_page",
+            format!("Between 1 and {} accounts per page", MAX_ACCOUNTS_PER_PAGE),
+        )));
+    }
+
+    let search = AccountSearch {
+        query: query.query.clone(),
+        sort,
+        descending,
+        page: query.page.unwrap_or(0),
+        per_page,
+    };
+
+    match AccountQueryCore::search_accounts(&state.databases.postgres_conn, &search).await {
+        Ok((accounts, total)) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Accounts found".to_string()),
+            data: Some(json!({
+                "accounts": accounts,
+                "total": total,
+                "page": search.page,
+                "per_page": search.per_page,
+            })),
+        })),
+        Err(err) => {
+            error!("Error searching accounts {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error searching accounts".to_string(),
+                errors: vec![],
+            }))
+        }
+    }
+}
+
+/// # Get Account Controller
+///
+/// The account with its billing and linked identities.
+pub async fn get_account_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::ViewAccounts)?;
+
+    let (account, billing) = match AccountQueryCore::get_account_by_id_with_billing(&state.databases.postgres_conn, path.into_inner()).await {
+        Ok(Some(a)) => a,
+        Ok(None) => return Ok(build_err(not_found())),
+        Err(err) => {
+            error!("Error getting account {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting account".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    let identities = match ProviderIdentityQueryCore::get_provider_identities_by_account_id(&state.databases.postgres_conn, account.id).await {
+        Ok(i) => i,
+        Err(err) => {
+            error!("Error getting identities {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting account".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Account found".to_string()),
+        data: Some(json!({
+            "account": account,
+            "billing": billing,
+            "identities": identities,
+        })),
+    }))
+}
+
+/// # Get Account Actions Controller
+///
+/// What staff did to the account, newest first.
+pub async fn get_account_actions_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::ViewAccounts)?;
+
+    match AdminActionQueryCore::get_admin_actions_by_target_account_id(&state.databases.postgres_conn, path.into_inner(), ADMIN_ACTIONS_SHOWN).await {
+        Ok(actions) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Admin actions found".to_string()),
+            data: Some(json!({ "actions": actions })),
+        })),
+        Err(err) => {
+            error!("Error getting admin actions {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error getting admin actions".to_string(),
+                errors: vec![],
+            }))
+        }
+    }
+}
+
+/// # Get Account Ledger Controller
+///
+/// The latest changes to the account's credits, and whether the whole ledger adds up to
+/// the balance.
+pub async fn get_account_ledger_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::ViewAccounts)?;
+
+    let db = &state.databases.postgres_conn;
+    let billing = match AccountQueryCore::get_account_by_id_with_billing(db, path.into_inner()).await {
+        Ok(Some((_, Some(b)))) => b,
+        Ok(_) => return Ok(build_err(not_found())),
+        Err(err) => {
+            error!("Error getting account {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting account".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    let ledger = match LedgerEntryQueryCore::get_ledger_entries_by_billing_id(db, billing.id, Some(LEDGER_ENTRIES_SHOWN)).await {
+        Ok(entries) => LedgerEntryQueryCore::get_ledger_total(db, billing.id).await.map(|total| (entries, total)),
+        Err(err) => Err(err),
+    };
+
+    match ledger {
+        Ok((entries, ledger_total)) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Ledger found".to_string()),
+            data: Some(json!({
+                "billing": billing,
+                "entries": entries,
+                "ledger_total": ledger_total,
+                "reconciled": ledger_total == billing.credits,
+            })),
+        })),
+        Err(err) => {
+            error!("Error getting ledger {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error getting ledger".to_string(),
+                errors: vec![],
+            }))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SetFlagsForm {
+    /// Comma separated, e.g. `beta,verified`, empty removes every flag
+    pub flags: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// # Set Account Flags Controller
+///
+/// Replaces the flags of the account, which is how roles are given and taken away.
+pub async fn set_account_flags_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+    data: web::Form<SetFlagsForm>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::ManageAccountFlags)?;
+    let account_id = path.into_inner();
+    if let Err(err) = not_self(&authenticated, account_id) {
+        return Ok(build_err(err));
+    }
+
+    let reason = match optional_reason(&data.reason) {
+        Ok(r) => r,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    let mut flags: Vec<AccountFlags> = vec![];
+    for name in data.flags.as_deref().unwrap_or_default().split(',').map(str::trim).filter(|f| !f.is_empty()) {
+        match serde_json::from_value::<AccountFlags>(Value::String(name.to_string())) {
+            Ok(f) if !flags.contains(&f) => flags.push(f),
+            Ok(_) => (),
+            Err(_) => return Ok(build_err(invalid_field("flags", format!("Unknown flag {}", name)))),
+        }
+    }
+    flags.sort();
+
+    let account = match get_account(&state, account_id).await {
+        Ok(a) => a,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    let updated = match AccountMutationCore::set_account_flags(&state.databases.postgres_conn, account.id, flags, now_unix).await {
+        Ok(a) => a,
+        Err(err) => {
+            error!("Error setting account flags {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error setting flags".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    record_admin_action(
+        &state,
+        &authenticated,
+        account.id,
+        "set_flags",
+        reason,
+        json!({ "from": account.flags, "to": updated.flags }),
+    )
+    .await;
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Flags updated".to_string()),
+        data: Some(json!({ "account": updated })),
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct AdminReasonForm {
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SuspendForm {
+    pub reason: Option<String>,
+    /// Unix timestamp the suspension lifts at, left out to suspend until lifted by hand
+    pub until: Option<Timestamp>,
+}
+
+/// # Suspend Account Controller
+///
+/// Blocks the account and logs it out everywhere, keeping all its data.
+pub async fn suspend_account_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+    data: web::Form<SuspendForm>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::SuspendAccounts)?;
+    let account_id = path.into_inner();
+    if let Err(err) = not_self(&authenticated, account_id) {
+        return Ok(build_err(err));
+    }
+
+    let reason = match required_reason(&data.reason) {
+        Ok(r) => r,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    if data.until.is_some_and(|until| until <= now_unix) {
+        return Ok(build_err(invalid_field("until", "The end of the suspension must be in the future".to_string())));
+    }
+
+    let account = match get_account(&state, account_id).await {
+        Ok(a) => a,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    match AccountMutationCore::suspend_account(
+        &state.databases.postgres_conn,
+        account.id,
+        reason.clone(),
+        authenticated.account.id,
+        data.until,
+        now_unix,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Account is already suspended".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::Conflict,
+                    message: None,
+                    field: None,
+                }],
+            }))
+        }
+        Err(err) => {
+            error!("Error suspending account {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error suspending account".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    if let Err(err) = SessionMutationCore::revoke_all_sessions(&state.databases.postgres_conn, account.id, now_unix).await {
+        error!("Error revoking sessions of suspended account {:?}", err);
+    }
+
+    record_admin_action(&state, &authenticated, account.id, "suspend", Some(reason), json!({ "until": data.until })).await;
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Account suspended".to_string()),
+        data: Some(json!({ "account_id": account.id })),
+    }))
+}
+
+/// # Restore Account Controller
+///
+/// Lifts a suspension and cancels a scheduled deletion, whichever the account has.
+pub async fn restore_account_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+    data: web::Form<AdminReasonForm>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::RestoreAccounts)?;
+    let account_id = path.into_inner();
+    if let Err(err) = not_self(&authenticated, account_id) {
+        return Ok(build_err(err));
+    }
+
+    let reason = match optional_reason(&data.reason) {
+        Ok(r) => r,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    let account = match get_account(&state, account_id).await {
+        Ok(a) => a,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    let db = &state.databases.postgres_conn;
+    let restored = async {
+        Ok::<_, sea_orm::DbErr>((
+            AccountMutationCore::lift_account_suspension(db, account.id, now_unix).await?,
+            AccountMutationCore::restore_account(db, account.id, now_unix).await?,
+        ))
+    }
+    .await;
+
+    let (unsuspended, undeleted) = match restored {
+        Ok(r) => r,
+        Err(err) => {
+            error!("Error restoring account {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error restoring account".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    if !unsuspended && !undeleted {
+        return Ok(build_err(ResponseBuilderError {
+            message: "Account is neither suspended nor scheduled for deletion".to_string(),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::Conflict,
+                message: None,
+                field: None,
+            }],
+        }));
+    }
+
+    record_admin_action(
+        &state,
+        &authenticated,
+        account.id,
+        "restore",
+        reason,
+        json!({ "lifted_suspension": unsuspended, "cancelled_deletion": undeleted }),
+    )
+    .await;
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Account restored".to_string()),
+        data: Some(json!({
+            "account_id": account.id,
+            "lifted_suspension": unsuspended,
+            "cancelled_deletion": undeleted,
+        })),
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct AdjustCreditsForm {
+    /// Added to the balance, negative to take credits away
+    pub amount: Option<i64>,
+    pub reason: Option<String>,
+}
+
+/// # Adjust Credits Controller
+///
+/// Grants or takes away credits. A reason is always required, and the balance can't go
+/// below zero.
+pub async fn adjust_credits_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+    data: web::Form<AdjustCreditsForm>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::GrantCredits)?;
+    let account_id = path.into_inner();
+    if let Err(err) = not_self(&authenticated, account_id) {
+        return Ok(build_err(err));
+    }
+
+    let amount = match data.amount {
+        Some(a) if a != 0 && (-MAX_CREDIT_ADJUSTMENT..=MAX_CREDIT_ADJUSTMENT).contains(&a) => a,
+        _ => {
+            return Ok(build_err(invalid_field(
+                "amount",
+                format!("Amount can't be zero or more than {} either way", MAX_CREDIT_ADJUSTMENT),
+            )))
+        }
+    };
+
+    let reason = match required_reason(&data.reason) {
+        Ok(r) => Some(r),
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    let account = match get_account(&state, account_id).await {
+        Ok(a) => a,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    let movement = CreditMovement {
+        reason: LedgerReason::AdminAdjustment,
+        account_id: Some(authenticated.account.id),
+        service: None,
+        request_id: Some(new_id()),
+    };
+    let billing = match AccountMutationCore::adjust_account_credits(
+        &state.databases.postgres_conn,
+        account.id,
+        amount,
+        &movement,
+        now_unix,
+    )
+    .await
+    {
+        Ok(Some(b)) => b,
+        Ok(None) => {
+            return Ok(build_err(invalid_field(
+                "amount",
+                "The balance can't go below zero".to_string(),
+            )))
+        }
+        Err(err) => {
+            error!("Error adjusting credits {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error adjusting credits".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    record_admin_action(
+        &state,
+        &authenticated,
+        account.id,
+        "adjust_credits",
+        reason,
+        json!({ "amount": amount, "balance": billing.credits, "request_id": movement.request_id }),
+    )
+    .await;
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Credits adjusted".to_string()),
+        data: Some(json!({ "billing": billing })),
+    }))
+}
+
+/// # Export Account Controller
+///
+/// The admin side of `export_me_controller`, for answering data-access requests about any
+/// account, including ones scheduled for deletion.
+pub async fn export_account_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<Either<HttpResponse, web::Json<ResponseObject<()>>>> {
+    authenticated.require(Permission::ExportAccountData)?;
+    let account_id = path.into_inner();
+
+    let (account, billing) = match AccountQueryCore::get_account_by_id_with_billing(&state.databases.postgres_conn, account_id).await {
+        Ok(Some(a)) => a,
+        Ok(None) => return Ok(Either::Right(build_err(not_found()))),
+        Err(err) => {
+            error!("Error getting account {:?}", err);
+            return Ok(Either::Right(build_err(ResponseBuilderError {
+                message: "Error getting account".to_string(),
+                errors: vec![],
+            })));
+        }
+    };
+
+    match export_account(&state.databases.postgres_conn, &account, billing.as_ref()).await {
+        Ok(archive) => {
+            record_admin_action(&state, &authenticated, account.id, "export", None, json!({})).await;
+            Ok(Either::Left(export_response(account.id, archive)))
+        }
+        Err(err) => {
+            error!("Error exporting account {} {:?}", account.id, err);
+            Ok(Either::Right(build_err(ResponseBuilderError {
+                message: "Error exporting account".to_string(),
+                errors: vec![],
+            })))
+        }
+    }
+}
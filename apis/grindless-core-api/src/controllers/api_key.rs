@@ -0,0 +1,295 @@
+use crate::{
+    extractors::AuthenticatedAccount,
+    state::APIStateWrapper,
+    tokens::{API_KEY_PREFIX_RANDOM_LEN, API_KEY_SECRET_LEN},
+};
+use actix_web::{web, Responder, Result};
+use chrono::{Duration, Utc};
+use grindless_core::{
+    entities::{
+        api_key::{self, API_KEY_PREFIX, API_KEY_SCOPES},
+        api_key_ops::{ApiKeyMutationCore, ApiKeyQueryCore},
+    },
+    response::{
+        errors::Errors,
+        response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk, ResponseObjectError},
+    },
+    util::{
+        secrets::{random_token, sha256_hex},
+        snowflake::new_id,
+    },
+    Timestamp, ID,
+};
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub static MAX_API_KEYS_PER_ACCOUNT: usize = 25;
+pub static MAX_API_KEY_NAME_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateApiKeyForm {
+    pub name: Option<String>,
+    /// Comma separated, e.g. `services:summarize,accounts:read`
+    pub scopes: Option<String>,
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RenameApiKeyForm {
+    pub name: Option<String>,
+}
+
+fn validate_api_key_name(name: &Option<String>) -> Result<String, String> {
+    let name = match name {
+        Some(n) => n.trim().to_string(),
+        None => return Err("No name provided".to_string()),
+    };
+
+    if name.is_empty() || name.chars().count() > MAX_API_KEY_NAME_LENGTH {
+        return Err(format!(
+            "Name must be between 1 and {} characters",
+            MAX_API_KEY_NAME_LENGTH
+        ));
+    }
+
+    Ok(name)
+}
+
+/// # Create API Key Controller
+///
+/// Creates a key for the account. The key itself is only in this response, after that
+/// only its prefix can be seen.
+pub async fn create_api_key_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    form: web::Form<CreateApiKeyForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let name = match validate_api_key_name(&form.name) {
+        Ok(n) => n,
+        Err(message) => {
+            return Ok(build_err(ResponseBuilderError {
+                message,
+                errors: vec![],
+            }))
+        }
+    };
+
+    let scopes = match &form.scopes {
+        Some(s) => s
+            .split(',')
+            .map(|scope| scope.trim().to_string())
+            .filter(|scope| !scope.is_empty())
+            .collect::<Vec<String>>(),
+        None => vec![],
+    };
+
+    if scopes.is_empty() {
+        return Ok(build_err(ResponseBuilderError {
+            message: "No scopes provided".to_string(),
+            errors: vec![],
+        }));
+    }
+
+    if let Some(unknown) = scopes.iter().find(|s| !API_KEY_SCOPES.contains(&s.as_str())) {
+        return Ok(build_err(ResponseBuilderError {
+            message: format!("Unknown scope {}", unknown),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::UnprocessableEntity,
+                message: Some(format!("Valid scopes: {}", API_KEY_SCOPES.join(", "))),
+                field: None,
+            }],
+        }));
+    }
+
+    let now = Utc::now();
+    let expires_at = match form.expires_in_days {
+        Some(days) if days > 0 => Some((now + Duration::days(days)).timestamp()),
+        Some(_) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "expires_in_days must be positive".to_string(),
+                errors: vec![],
+            }))
+        }
+        None => None,
+    };
+
+    let existing = match ApiKeyQueryCore::get_api_keys_by_account_id(
+        &state.databases.postgres_conn,
+        authenticated.account.id,
+    )
+    .await
+    {
+        Ok(k) => k,
+        Err(err) => {
+            error!("Error getting API keys {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting API keys".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    if existing.len() >= MAX_API_KEYS_PER_ACCOUNT {
+        return Ok(build_err(ResponseBuilderError {
+            message: format!("You can't have more than {} API keys", MAX_API_KEYS_PER_ACCOUNT),
+            errors: vec![],
+        }));
+    }
+
+    let prefix = format!("{}{}", API_KEY_PREFIX, random_token(API_KEY_PREFIX_RANDOM_LEN));
+    let secret = format!("{}_{}", prefix, random_token(API_KEY_SECRET_LEN));
+
+    let created = match ApiKeyMutationCore::create_api_key(
+        &state.databases.postgres_conn,
+        api_key::Model {
+            id: new_id(),
+            account_id: authenticated.account.id,
+            name,
+            prefix,
+            key_hash: sha256_hex(&secret),
+            scopes,
+            created_at: now.timestamp() as Timestamp,
+            expires_at,
+            last_used_at: None,
+            revoked: false,
+        },
+    )
+    .await
+    {
+        Ok(k) => k,
+        Err(err) => {
+            error!("Error creating API key {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error creating API key".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("API key created, store it now, it won't be shown again".to_string()),
+        data: Some(json!({
+            "api_key": created,
+            "key": secret,
+        })),
+    }))
+}
+
+pub async fn get_my_api_keys_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let api_keys = match ApiKeyQueryCore::get_api_keys_by_account_id(
+        &state.databases.postgres_conn,
+        authenticated.account.id,
+    )
+    .await
+    {
+        Ok(k) => k,
+        Err(err) => {
+            error!("Error getting API keys {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting API keys".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("API keys found".to_string()),
+        data: Some(json!({ "api_keys": api_keys })),
+    }))
+}
+
+pub async fn rename_api_key_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+    form: web::Form<RenameApiKeyForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let name = match validate_api_key_name(&form.name) {
+        Ok(n) => n,
+        Err(message) => {
+            return Ok(build_err(ResponseBuilderError {
+                message,
+                errors: vec![],
+            }))
+        }
+    };
+
+    let api_key_id = path.into_inner();
+    match ApiKeyMutationCore::rename_api_key(
+        &state.databases.postgres_conn,
+        authenticated.account.id,
+        api_key_id,
+        &name,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "API key not found".to_string(),
+                errors: vec![],
+            }))
+        }
+        Err(err) => {
+            error!("Error renaming API key {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error renaming API key".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("API key renamed".to_string()),
+        data: Some(json!({ "api_key_id": api_key_id, "name": name })),
+    }))
+}
+
+pub async fn revoke_api_key_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let api_key_id = path.into_inner();
+    match ApiKeyMutationCore::revoke_api_key(
+        &state.databases.postgres_conn,
+        authenticated.account.id,
+        api_key_id,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "API key not found".to_string(),
+                errors: vec![],
+            }))
+        }
+        Err(err) => {
+            error!("Error revoking API key {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error revoking API key".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("API key revoked".to_string()),
+        data: Some(json!({ "api_key_id": api_key_id })),
+    }))
+}
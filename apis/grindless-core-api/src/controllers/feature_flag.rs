@@ -0,0 +1,266 @@
+use crate::{extractors::AuthenticatedAccount, state::APIStateWrapper};
+use actix_web::{web, Responder, Result};
+use chrono::Utc;
+use grindless_core::{
+    entities::{
+        account::AccountFlags,
+        admin_action,
+        admin_action_ops::AdminActionMutationCore,
+        feature_flag,
+        feature_flag_ops::{FeatureFlagMutationCore, FeatureFlagQueryCore},
+    },
+    permissions::Permission,
+    response::{
+        errors::Errors,
+        response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk, ResponseObjectError},
+    },
+    util::snowflake::new_id,
+    Timestamp, ID,
+};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+pub static MAX_FEATURE_FLAG_KEY_LENGTH: usize = 64;
+pub static MAX_FEATURE_FLAG_DESCRIPTION_LENGTH: usize = 500;
+pub static MAX_FEATURE_FLAG_ACCOUNT_IDS: usize = 1000;
+
+fn invalid_field(field: &str, message: String) -> ResponseBuilderError {
+    ResponseBuilderError {
+        message: "Invalid fields".to_string(),
+        errors: vec![ResponseObjectError {
+            error_id: Errors::UnprocessableEntity,
+            message: Some(message),
+            field: Some(field.to_string()),
+        }],
+    }
+}
+
+/// Keys end up in code and in the client, so they are kept to lowercase letters, digits,
+/// `_`, `-` and `.`
+fn valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && key.len() <= MAX_FEATURE_FLAG_KEY_LENGTH
+        && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-' | '.'))
+}
+
+fn comma_separated(list: &Option<String>) -> impl Iterator<Item = &str> {
+    list.as_deref().unwrap_or_default().split(',').map(str::trim).filter(|i| !i.is_empty())
+}
+
+fn error_getting_feature_flag(err: sea_orm::DbErr) -> ResponseBuilderError {
+    error!("Error getting feature flag {:?}", err);
+    ResponseBuilderError {
+        message: "Error getting feature flag".to_string(),
+        errors: vec![],
+    }
+}
+
+/// Adds the change to the admin audit log, with the settings before and after. The change
+/// already happened, so a failure here is only logged.
+async fn record_feature_flag_action(
+    state: &APIStateWrapper,
+    authenticated: &AuthenticatedAccount,
+    action: &str,
+    key: &str,
+    before: Option<feature_flag::Model>,
+    after: Option<feature_flag::Model>,
+) {
+    info!("Account {} did {} on feature flag {}", authenticated.account.id, action, key);
+
+    if let Err(err) = AdminActionMutationCore::create_admin_action(
+        &state.databases.postgres_conn,
+        admin_action::Model {
+            id: new_id(),
+            admin_account_id: authenticated.account.id,
+            target_account_id: None,
+            action: action.to_string(),
+            reason: None,
+            details: json!({ "key": key, "before": before, "after": after }),
+            created_at: Utc::now().timestamp() as Timestamp,
+        },
+    )
+    .await
+    {
+        error!("Error recording admin action {} on feature flag {} {:?}", action, key, err);
+    }
+}
+
+/// # Get Feature Flags Controller
+///
+/// Every feature flag, by key.
+pub async fn get_feature_flags_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::ManageFeatureFlags)?;
+
+    match FeatureFlagQueryCore::get_all_feature_flags(&state.databases.postgres_conn).await {
+        Ok(flags) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Feature flags found".to_string()),
+            data: Some(json!({ "feature_flags": flags })),
+        })),
+        Err(err) => {
+            error!("Error getting feature flags {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error getting feature flags".to_string(),
+                errors: vec![],
+            }))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SaveFeatureFlagForm {
+    pub description: Option<String>,
+    pub enabled: Option<bool>,
+    /// Comma separated account flags, e.g. `beta,developer`
+    pub account_flags: Option<String>,
+    /// Comma separated account ids
+    pub account_ids: Option<String>,
+    /// From 0 to 100
+    pub rollout_percentage: Option<i32>,
+}
+
+/// # Save Feature Flag Controller
+///
+/// Creates the flag, or replaces all of its settings. Fields left out are off or empty.
+pub async fn save_feature_flag_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<String>,
+    data: web::Form<SaveFeatureFlagForm>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::ManageFeatureFlags)?;
+
+    let key = path.into_inner();
+    if !valid_key(&key) {
+        return Ok(build_err(invalid_field(
+            "key",
+            format!("Keys are 1 to {} lowercase letters, digits, _, - and .", MAX_FEATURE_FLAG_KEY_LENGTH),
+        )));
+    }
+
+    let description = data.description.as_deref().map(str::trim).filter(|d| !d.is_empty());
+    if description.is_some_and(|d| d.chars().count() > MAX_FEATURE_FLAG_DESCRIPTION_LENGTH) {
+        return Ok(build_err(invalid_field(
+            "description",
+            format!("Description can't be longer than {} characters", MAX_FEATURE_FLAG_DESCRIPTION_LENGTH),
+        )));
+    }
+
+    let mut account_flags: Vec<AccountFlags> = vec![];
+    for name in comma_separated(&data.account_flags) {
+        match serde_json::from_value::<AccountFlags>(Value::String(name.to_string())) {
+            Ok(f) if !account_flags.contains(&f) => account_flags.push(f),
+            Ok(_) => (),
+            Err(_) => return Ok(build_err(invalid_field("account_flags", format!("Unknown flag {}", name)))),
+        }
+    }
+    account_flags.sort();
+
+    let mut account_ids: Vec<ID> = vec![];
+    for id in comma_separated(&data.account_ids) {
+        match id.parse::<ID>() {
+            Ok(id) if !account_ids.contains(&id) => account_ids.push(id),
+            Ok(_) => (),
+            Err(_) => return Ok(build_err(invalid_field("account_ids", format!("Invalid account id {}", id)))),
+        }
+    }
+    if account_ids.len() > MAX_FEATURE_FLAG_ACCOUNT_IDS {
+        return Ok(build_err(invalid_field(
+            "account_ids",
+            format!("Can't target more than {} accounts by id, use a rollout", MAX_FEATURE_FLAG_ACCOUNT_IDS),
+        )));
+    }
+
+    let rollout_percentage = data.rollout_percentage.unwrap_or(0);
+    if !(0..=100).contains(&rollout_percentage) {
+        return Ok(build_err(invalid_field(
+            "rollout_percentage",
+            "Rollout percentage is between 0 and 100".to_string(),
+        )));
+    }
+
+    let before = match FeatureFlagQueryCore::get_feature_flag_by_key(&state.databases.postgres_conn, &key).await {
+        Ok(flag) => flag,
+        Err(err) => return Ok(build_err(error_getting_feature_flag(err))),
+    };
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    match FeatureFlagMutationCore::save_feature_flag(
+        &state.databases.postgres_conn,
+        feature_flag::Model {
+            id: new_id(),
+            key,
+            description: description.map(str::to_string),
+            enabled: data.enabled.unwrap_or(false),
+            account_flags,
+            account_ids,
+            rollout_percentage,
+            created_at: now_unix,
+            updated_at: now_unix,
+        },
+    )
+    .await
+    {
+        Ok(flag) => {
+            record_feature_flag_action(&state, &authenticated, "save_feature_flag", &flag.key, before, Some(flag.clone()))
+                .await;
+            Ok(build_ok(ResponseBuilderOk {
+                message: Some("Feature flag saved".to_string()),
+                data: Some(json!({ "feature_flag": flag })),
+            }))
+        }
+        Err(err) => {
+            error!("Error saving feature flag {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error saving feature flag".to_string(),
+                errors: vec![],
+            }))
+        }
+    }
+}
+
+/// # Delete Feature Flag Controller
+///
+/// Removes the flag, which turns its feature off for everyone.
+pub async fn delete_feature_flag_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<String>,
+) -> Result<impl Responder> {
+    authenticated.require(Permission::ManageFeatureFlags)?;
+    let key = path.into_inner();
+
+    let before = match FeatureFlagQueryCore::get_feature_flag_by_key(&state.databases.postgres_conn, &key).await {
+        Ok(flag) => flag,
+        Err(err) => return Ok(build_err(error_getting_feature_flag(err))),
+    };
+
+    match FeatureFlagMutationCore::delete_feature_flag(&state.databases.postgres_conn, &key).await {
+        Ok(true) => {
+            record_feature_flag_action(&state, &authenticated, "delete_feature_flag", &key, before, None).await;
+            Ok(build_ok(ResponseBuilderOk {
+                message: Some("Feature flag deleted".to_string()),
+                data: Some(json!({ "key": key })),
+            }))
+        }
+        Ok(false) => Ok(build_err(ResponseBuilderError {
+            message: "Feature flag not found".to_string(),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::NotFound,
+                message: None,
+                field: None,
+            }],
+        })),
+        Err(err) => {
+            error!("Error deleting feature flag {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error deleting feature flag".to_string(),
+                errors: vec![],
+            }))
+        }
+    }
+}
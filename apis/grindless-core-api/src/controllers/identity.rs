@@ -0,0 +1,216 @@
+use crate::{
+    controllers::oauth::{begin_login, BeginLoginForm},
+    extractors::AuthenticatedAccount,
+    oauth::{complete_oauth_login, oauth_error},
+    state::APIStateWrapper,
+};
+use actix_web::{web, Responder, Result};
+use chrono::Utc;
+use grindless_core::{
+    entities::{
+        provider_identity,
+        provider_identity_ops::{ProviderIdentityMutationCore, ProviderIdentityQueryCore, Unlink},
+    },
+    response::{
+        errors::Errors,
+        response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk},
+    },
+    util::snowflake::new_id,
+    Timestamp, ID,
+};
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub async fn get_my_identities_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let identities = match ProviderIdentityQueryCore::get_provider_identities_by_account_id(
+        &state.databases.postgres_conn,
+        authenticated.account.id,
+    )
+    .await
+    {
+        Ok(i) => i,
+        Err(err) => {
+            error!("Error getting identities {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting identities".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Identities found".to_string()),
+        data: Some(json!({ "identities": identities })),
+    }))
+}
+
+/// # Begin Link Identity Controller
+///
+/// Like logging in, but the identity the user comes back with gets linked to their account.
+pub async fn begin_link_identity_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<String>,
+    data: web::Form<BeginLoginForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    Ok(begin_login(&state, &path, data.redirect_uri.as_deref(), Some(authenticated.account.id)).await)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct LinkIdentityForm {
+    pub code: Option<String>,
+    pub state: Option<String>,
+}
+
+pub async fn link_identity_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<String>,
+    data: web::Form<LinkIdentityForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let provider = match state.oauth_providers.get(&path) {
+        Some(p) => p,
+        None => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Unknown provider".to_string(),
+                errors: vec![],
+            }))
+        }
+    };
+
+    let (code, signed_state) = match (&data.code, &data.state) {
+        (Some(c), Some(s)) => (c, s),
+        (None, _) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "no code".to_string(),
+                errors: vec![],
+            }))
+        }
+        (_, None) => return Ok(build_err(oauth_error(provider.name(), Errors::InvalidOAuthState))),
+    };
+
+    let account_id = authenticated.account.id;
+    let profile = match complete_oauth_login(&state, provider, code, signed_state, Some(account_id)).await {
+        Ok(p) => p,
+        Err(err) => return Ok(build_err(oauth_error(provider.name(), err))),
+    };
+
+    match ProviderIdentityQueryCore::get_provider_identity(
+        &state.databases.postgres_conn,
+        provider.name(),
+        &profile.subject,
+    )
+    .await
+    {
+        Ok(None) => (),
+        Ok(Some(identity)) if identity.account_id == account_id => {
+            return Ok(build_err(ResponseBuilderError {
+                message: format!("This {} account is already linked", provider.name()),
+                errors: vec![],
+            }))
+        }
+        Ok(Some(_)) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: format!("This {} account is linked to another account", provider.name()),
+                errors: vec![],
+            }))
+        }
+        Err(err) => {
+            error!("Error getting identity {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting identity".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    let identity = match ProviderIdentityMutationCore::create_provider_identity(
+        &state.databases.postgres_conn,
+        provider_identity::Model {
+            id: new_id(),
+            account_id,
+            provider: provider.name().to_string(),
+            subject: profile.subject,
+            email: profile.email,
+            email_verified: profile.email_verified,
+            created_at: now_unix,
+            updated_at: now_unix,
+        },
+    )
+    .await
+    {
+        Ok(i) => i,
+        Err(err) => {
+            error!("Error linking identity {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error linking identity".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some(format!("{} account linked", provider.name())),
+        data: Some(json!({ "identity": identity })),
+    }))
+}
+
+/// # Unlink Identity Controller
+///
+/// The last identity can't be unlinked, the account would have no way to log in left.
+pub async fn unlink_identity_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let identity_id = path.into_inner();
+    let account_id = authenticated.account.id;
+
+    match ProviderIdentityMutationCore::unlink_provider_identity(
+        &state.databases.postgres_conn,
+        account_id,
+        identity_id,
+    )
+    .await
+    {
+        Ok(Unlink::Unlinked) => (),
+        Ok(Unlink::NotFound) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Identity not found".to_string(),
+                errors: vec![],
+            }))
+        }
+        Ok(Unlink::LastIdentity) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "You can't unlink your only way to log in".to_string(),
+                errors: vec![],
+            }))
+        }
+        Err(err) => {
+            error!("Error unlinking identity {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error unlinking identity".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Identity unlinked".to_string()),
+        data: Some(json!({ "identity_id": identity_id })),
+    }))
+}
@@ -0,0 +1,43 @@
+use crate::{extractors::AuthenticatedAccount, state::APIStateWrapper};
+use actix_web::{Responder, Result};
+use grindless_core::{
+    entities::{api_key::SCOPE_ACCOUNTS_READ, ledger_entry_ops::LedgerEntryQueryCore},
+    response::response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk},
+};
+use log::error;
+use serde_json::json;
+
+pub static LEDGER_ENTRIES_SHOWN: u64 = 100;
+
+/// # Get My Ledger Controller
+///
+/// The latest changes to the account's own credits, newest first.
+pub async fn get_my_ledger_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<impl Responder> {
+    authenticated.require_scope(SCOPE_ACCOUNTS_READ)?;
+
+    match LedgerEntryQueryCore::get_ledger_entries_by_billing_id(
+        &state.databases.postgres_conn,
+        authenticated.billing.id,
+        Some(LEDGER_ENTRIES_SHOWN),
+    )
+    .await
+    {
+        Ok(entries) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Ledger found".to_string()),
+            data: Some(json!({
+                "entries": entries,
+                "credits": authenticated.billing.credits,
+            })),
+        })),
+        Err(err) => {
+            error!("Error getting ledger {:?}", err);
+            Ok(build_err(ResponseBuilderError {
+                message: "Error getting ledger".to_string(),
+                errors: vec![],
+            }))
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{
+    controllers::oauth::{find_or_create_account, log_in},
+    mail::Email,
+    oauth::{redirects::redirect_allowed, OAuthProfile},
+    state::APIStateWrapper,
+    tokens::{MAGIC_LINK_MAX_PER_TTL, MAGIC_LINK_TOKEN_LEN, MAGIC_LINK_TTL_MINUTES},
+};
+use actix_web::{web, HttpRequest, Responder, Result};
+use chrono::{Duration, Utc};
+use grindless_core::{
+    entities::{
+        magic_link,
+        magic_link_ops::{MagicLinkMutationCore, MagicLinkQueryCore},
+    },
+    response::{
+        errors::Errors,
+        response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk, ResponseObjectError},
+    },
+    util::{
+        secrets::{random_token, sha256_hex},
+        snowflake::new_id,
+    },
+    Timestamp,
+};
+use log::error;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+/// Provider name of identities that log in with magic links, the subject is the email
+pub static MAGIC_LINK_PROVIDER: &str = "email";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RequestMagicLinkForm {
+    pub email: Option<String>,
+    pub redirect_uri: Option<String>,
+}
+
+/// # Request Magic Link Controller
+///
+/// Mails a login link to the address. The answer is the same whether or not an account
+/// uses the address, so this can't be used to find out who has one.
+pub async fn request_magic_link_controller(
+    state: APIStateWrapper,
+    data: web::Form<RequestMagicLinkForm>,
+) -> Result<impl Responder> {
+    let redirects = &state.env.oauth.magic_link_redirects;
+    if redirects.is_empty() {
+        return Ok(build_err(ResponseBuilderError {
+            message: "Magic links are not enabled".to_string(),
+            errors: vec![],
+        }));
+    }
+
+    let email = match data.email.as_deref().map(|e| e.trim().to_lowercase()) {
+        Some(e) if e.parse::<lettre::Address>().is_ok() => e,
+        _ => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Invalid email".to_string(),
+                errors: vec![],
+            }))
+        }
+    };
+
+    let redirect_uri = match data.redirect_uri.as_deref() {
+        Some(r) if redirect_allowed(redirects, r) => r,
+        Some(_) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Redirect URI not allowed".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::BadRequest,
+                    message: Some("redirect_uri is not in the allowlist".to_string()),
+                    field: None,
+                }],
+            }))
+        }
+        None => &redirects[0],
+    };
+
+    let now = Utc::now();
+    let now_unix = now.timestamp() as Timestamp;
+    let since = (now - Duration::minutes(MAGIC_LINK_TTL_MINUTES)).timestamp();
+
+    match MagicLinkQueryCore::count_magic_links_since(&state.databases.postgres_conn, &email, since).await {
+        Ok(sent) if sent >= MAGIC_LINK_MAX_PER_TTL => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Too many links requested, check your inbox or try again later".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::TooManyRequests,
+                    message: None,
+                    field: None,
+                }],
+            }))
+        }
+        Ok(_) => (),
+        Err(err) => {
+            error!("Error counting magic links {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error sending link".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    if let Err(err) = MagicLinkMutationCore::delete_expired_magic_links(&state.databases.postgres_conn, since).await {
+        error!("Error deleting expired magic links {:?}", err);
+    }
+
+    let token = random_token(MAGIC_LINK_TOKEN_LEN);
+    let mut url = match Url::parse(redirect_uri) {
+        Ok(u) => u,
+        Err(_) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Redirect URI not allowed".to_string(),
+                errors: vec![],
+            }))
+        }
+    };
+    url.query_pairs_mut().append_pair("token", &token);
+
+    if let Err(err) = MagicLinkMutationCore::create_magic_link(
+        &state.databases.postgres_conn,
+        magic_link::Model {
+            id: new_id(),
+            email: email.clone(),
+            token_hash: sha256_hex(&token),
+            created_at: now_unix,
+            expires_at: (now + Duration::minutes(MAGIC_LINK_TTL_MINUTES)).timestamp(),
+            used_at: None,
+        },
+    )
+    .await
+    {
+        error!("Error storing magic link {:?}", err);
+        return Ok(build_err(ResponseBuilderError {
+            message: "Error sending link".to_string(),
+            errors: vec![],
+        }));
+    }
+
+    let sent = state
+        .mailer
+        .send(Email {
+            to: email,
+            subject: "Your Grindless login link".to_string(),
+            body: format!(
+                "Open this link to log in to Grindless:\n\n{}\n\nIt works once and expires in {} minutes. If you didn't ask for it, you can ignore this mail.\n",
+                url, MAGIC_LINK_TTL_MINUTES
+            ),
+        })
+        .await;
+
+    if let Err(err) = sent {
+        error!("Error sending magic link {:?}", err);
+        return Ok(build_err(ResponseBuilderError {
+            message: "Error sending link".to_string(),
+            errors: vec![],
+        }));
+    }
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Check your inbox for a login link".to_string()),
+        data: Some(serde_json::json!({ "expires_in": MAGIC_LINK_TTL_MINUTES * 60 })),
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct VerifyMagicLinkForm {
+    pub token: Option<String>,
+    pub device: Option<String>,
+}
+
+/// # Verify Magic Link Controller
+///
+/// Swaps the token from the link for the same tokens any other login gets. Clicking the
+/// link proves the address, so it counts as a verified email.
+pub async fn verify_magic_link_controller(
+    req: HttpRequest,
+    state: APIStateWrapper,
+    data: web::Form<VerifyMagicLinkForm>,
+) -> Result<impl Responder> {
+    let token = match &data.token {
+        Some(t) if !t.is_empty() => t,
+        _ => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "no token".to_string(),
+                errors: vec![],
+            }))
+        }
+    };
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    let link = match MagicLinkMutationCore::consume_magic_link(
+        &state.databases.postgres_conn,
+        &sha256_hex(token),
+        now_unix,
+    )
+    .await
+    {
+        Ok(Some(l)) => l,
+        Ok(None) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "This link is invalid, expired or was already used".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::InvalidToken,
+                    message: None,
+                    field: None,
+                }],
+            }))
+        }
+        Err(err) => {
+            error!("Error consuming magic link {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error logging in".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    // no name, new accounts are named after the address and existing ones keep theirs
+    let profile = OAuthProfile {
+        subject: link.email.clone(),
+        email: Some(link.email),
+        email_verified: true,
+        name: String::new(),
+        avatar: None,
+    };
+
+    let account = match find_or_create_account(&state, MAGIC_LINK_PROVIDER, &profile).await {
+        Ok(a) => a,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    log_in(&req, &state, &account, data.device.clone()).await
+}
@@ -1,3 +1,12 @@
 pub mod oauth;
 pub mod account;
-pub mod services;
\ No newline at end of file
+pub mod admin;
+pub mod api_key;
+pub mod feature_flag;
+pub mod identity;
+pub mod ledger;
+pub mod magic_link;
+pub mod organization;
+pub mod services;
+pub mod session;
+pub mod well_known;
@@ -1,39 +1,151 @@
-use crate::{env::Enviroment, services::DEFAULT_STARTER_CREDITS, state::APIStateWrapper, util::get_claims_from_header};
+use crate::{
+    extractors::suspension_message,
+    oauth::{complete_oauth_login, oauth_error, redirects::redirect_allowed, OAuthProfile},
+    services::DEFAULT_STARTER_CREDITS,
+    state::APIStateWrapper,
+    tokens::{begin_oauth_login, issue_token_pair, start_session, TokenPair},
+    util::{get_claims_from_header, get_client_ip},
+};
 use actix_web::{web, HttpRequest, Responder, Result};
-use chrono::{Duration, Utc};
+use chrono::Utc;
 use grindless_core::{
     entities::{
-        account::Model,
-        account_ops::{AccountMutationCore, AccountQueryCore}, billing,
+        account::{Model, ProfileField},
+        account_ops::{AccountMutationCore, AccountProfilePatch, AccountQueryCore},
+        billing, provider_identity,
+        provider_identity_ops::{ProviderIdentityMutationCore, ProviderIdentityQueryCore},
+        refresh_token_ops::{RefreshTokenMutationCore, RefreshTokenQueryCore},
+        session_ops::{SessionMutationCore, SessionQueryCore},
     },
     response::{
         errors::Errors,
-        response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk},
+        response::{
+            build_err, build_ok, ResponseBuilderError, ResponseBuilderOk, ResponseObject,
+            ResponseObjectError,
+        },
     },
     util::{
-        jwt::{self, JWTClaims, JWTTokenType},
-        random_int,
+        jwt::{self, JWTTokenType},
+        snowflake::new_id,
     },
     Timestamp, ID,
 };
-use jsonwebtoken::Algorithm;
-use log::{debug, error};
-use reqwest::{Client, Url};
+use log::{debug, error, warn};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub async fn get_providers_controller(state: APIStateWrapper) -> Result<impl Responder> {
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("OAuth providers".to_string()),
+        data: Some(json!({ "providers": state.oauth_providers.names() })),
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct BeginLoginForm {
+    pub redirect_uri: Option<String>,
+}
+
+/// # Begin Login Controller
+///
+/// Starts logging in with the provider. Returns where to send the user, and the `state`
+/// the browser must keep and check against the one the provider redirects back with.
+pub async fn begin_login_controller(
+    state: APIStateWrapper,
+    path: web::Path<String>,
+    data: web::Form<BeginLoginForm>,
+) -> Result<impl Responder> {
+    Ok(begin_login(&state, &path, data.redirect_uri.as_deref(), None).await)
+}
+
+/// # Begin Login
+///
+/// Shared by logging in and linking an identity. The client picks where the provider sends
+/// the user back to, from the provider's redirect allowlist.
+pub async fn begin_login(
+    state: &APIStateWrapper,
+    provider: &str,
+    redirect_uri: Option<&str>,
+    link_account_id: Option<ID>,
+) -> web::Json<ResponseObject<serde_json::Value>> {
+    let provider = match state.oauth_providers.get(provider) {
+        Some(p) => p,
+        None => {
+            return build_err(ResponseBuilderError {
+                message: "Unknown provider".to_string(),
+                errors: vec![],
+            })
+        }
+    };
+
+    let redirect_uri = match redirect_uri {
+        Some(r) if redirect_allowed(&provider.client().client_redirects, r) => r,
+        Some(_) => {
+            return build_err(ResponseBuilderError {
+                message: "Redirect URI not allowed".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::BadRequest,
+                    message: Some("redirect_uri is not in the allowlist".to_string()),
+                    field: None,
+                }],
+            })
+        }
+        None => provider.client().default_redirect_uri(),
+    };
+
+    let login = match begin_oauth_login(state, provider.name(), redirect_uri, link_account_id).await
+    {
+        Ok(l) => l,
+        Err(_) => {
+            return build_err(ResponseBuilderError {
+                message: "Error starting login".to_string(),
+                errors: vec![],
+            })
+        }
+    };
 
-pub const GOOGLE_GET_TOKENS_URL: &str = "https://oauth2.googleapis.com/token";
-pub const GOOGLE_GET_PROFILE_URL: &str = "https://www.googleapis.com/oauth2/v1/userinfo";
+    let url = provider.authorize_url(redirect_uri, &login.state, &login.code_challenge);
+
+    build_ok(ResponseBuilderOk {
+        message: Some("Login started".to_string()),
+        data: Some(json!({
+            "provider": provider.name(),
+            "url": url,
+            "state": login.state,
+            "expires_in": login.expires_in,
+        })),
+    })
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub struct AccessGoogleForm {
+pub struct AccessProviderForm {
     pub code: Option<String>,
+    pub state: Option<String>,
+    pub device: Option<String>,
 }
 
-pub async fn access_google_controller(
+/// # Access Provider Controller
+///
+/// Logs in with the code and `state` the provider redirected back with. The first login
+/// with an identity creates its account.
+pub async fn access_provider_controller(
+    req: HttpRequest,
     state: APIStateWrapper,
-    data: web::Form<AccessGoogleForm>,
+    path: web::Path<String>,
+    data: web::Form<AccessProviderForm>,
 ) -> Result<impl Responder> {
+    let provider = match state.oauth_providers.get(&path) {
+        Some(p) => p,
+        None => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Unknown provider".to_string(),
+                errors: vec![],
+            }))
+        }
+    };
+
     let code = match &data.code {
         Some(u) => u,
         None => {
@@ -44,121 +156,535 @@ pub async fn access_google_controller(
         }
     };
 
-    // Get Google user tokens
-    let tokens = match get_google_user_tokens(code.to_owned(), state.env.clone()).await {
-        Ok(t) => t,
+    let signed_state = match &data.state {
+        Some(s) => s,
+        None => {
+            return Ok(build_err(oauth_error(
+                provider.name(),
+                Errors::InvalidOAuthState,
+            )))
+        }
+    };
+
+    let profile = match complete_oauth_login(&state, provider, code, signed_state, None).await {
+        Ok(p) => p,
+        Err(err) => return Ok(build_err(oauth_error(provider.name(), err))),
+    };
+
+    let account = match find_or_create_account(&state, provider.name(), &profile).await {
+        Ok(a) => a,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    log_in(&req, &state, &account, data.device.clone()).await
+}
+
+/// # Find Or Create Account
+///
+/// The account the identity logs in to. An identity seen for the first time joins the
+/// account with the same verified email, or else gets a new account.
+pub async fn find_or_create_account(
+    state: &APIStateWrapper,
+    provider: &str,
+    profile: &OAuthProfile,
+) -> Result<Model, ResponseBuilderError> {
+    // Check if account already exists
+    let existing = match ProviderIdentityQueryCore::get_account_by_provider_identity(
+        &state.databases.postgres_conn,
+        provider,
+        &profile.subject,
+    )
+    .await
+    {
+        Ok(a) => a,
         Err(err) => {
-            return Ok(build_err(ResponseBuilderError {
-                message: err.to_string(),
+            error!("Error getting account {:?}", err);
+            return Err(ResponseBuilderError {
+                message: "Error getting account".to_string(),
                 errors: vec![],
-            }))
+            });
         }
     };
 
-    if tokens.access_token.is_empty() {
-        return Ok(build_err(ResponseBuilderError {
-            message: "Invalid access token".to_string(),
-            errors: vec![],
-        }));
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    if let Some((identity, account)) = existing {
+        refuse_blocked_account(&account, now_unix)?;
+        if identity.email != profile.email || identity.email_verified != profile.email_verified {
+            if let Err(err) = ProviderIdentityMutationCore::update_provider_identity_email(
+                &state.databases.postgres_conn,
+                identity.id,
+                profile.email.clone(),
+                profile.email_verified,
+                now_unix,
+            )
+            .await
+            {
+                error!("Error updating identity email {:?}", err);
+            }
+        }
+
+        return Ok(sync_profile(state, account, profile, identity.email.as_deref()).await);
     }
 
-    let profile = match get_google_user_profile(tokens.access_token).await {
-        Ok(p) => p,
+    // A new identity joins the account that already has the same email, but only when both
+    // providers verified it, otherwise anyone could claim an account by typing its email
+    let matched = match (&profile.email, profile.email_verified) {
+        (Some(email), true) => {
+            match ProviderIdentityQueryCore::get_account_by_verified_email(
+                &state.databases.postgres_conn,
+                email,
+            )
+            .await
+            {
+                Ok(a) => a,
+                Err(err) => {
+                    error!("Error getting account by email {:?}", err);
+                    return Err(ResponseBuilderError {
+                        message: "Error getting account".to_string(),
+                        errors: vec![],
+                    });
+                }
+            }
+        }
+        _ => None,
+    };
+
+    let identity = provider_identity::Model {
+        id: new_id(),
+        account_id: 0,
+        provider: provider.to_string(),
+        subject: profile.subject.clone(),
+        email: profile.email.clone(),
+        email_verified: profile.email_verified,
+        created_at: now_unix,
+        updated_at: now_unix,
+    };
+
+    match matched {
+        Some(account) => {
+            debug!(
+                "Linking new {} identity to account {} by verified email",
+                provider, account.id
+            );
+            refuse_blocked_account(&account, now_unix)?;
+            let account = sync_profile(state, account, profile, None).await;
+
+            if let Err(err) = ProviderIdentityMutationCore::create_provider_identity(
+                &state.databases.postgres_conn,
+                provider_identity::Model {
+                    account_id: account.id,
+                    ..identity
+                },
+            )
+            .await
+            {
+                error!("Error creating provider identity {:?}", err);
+                return Err(ResponseBuilderError {
+                    message: "Error creating account".to_string(),
+                    errors: vec![],
+                });
+            }
+
+            Ok(account)
+        }
+        None => {
+            debug!("Account does not exist, creating new account");
+            let email = match &profile.email {
+                Some(e) if !e.is_empty() => e.clone(),
+                _ => {
+                    return Err(ResponseBuilderError {
+                        message: format!("Your {} account has no email address", provider),
+                        errors: vec![],
+                    })
+                }
+            };
+
+            let billing = billing::Model {
+                id: new_id(),
+                account_id: None,
+                organization_id: None,
+                credits: DEFAULT_STARTER_CREDITS,
+                total_spent_usd: 0.0,
+                created_at: now_unix,
+                updated_at: now_unix,
+            };
+
+            let new_account = Model {
+                id: new_id(),
+                name: match profile.name.is_empty() {
+                    true => email.split('@').next().unwrap_or_default().to_string(),
+                    false => profile.name.clone(),
+                },
+                // Invitations and admins trust the account email, so an unverified one stays
+                // on the identity until a provider verifies it
+                email: match profile.email_verified {
+                    true => email,
+                    false => String::new(),
+                },
+                avatar: profile.avatar.clone(),
+                flags: vec![],
+                locked_fields: vec![],
+                preferences: json!({}),
+                billing_id: 0,
+                created_at: now_unix,
+                updated_at: now_unix,
+                suspended_at: None,
+                suspension_reason: None,
+                suspended_by: None,
+                suspended_until: None,
+                deleted: false,
+                deletion_requested_at: None,
+                deletion_reason: None,
+            };
+
+            // The identity is created with the account, so no account is left without a login
+            match AccountMutationCore::create_account(
+                &state.databases.postgres_conn,
+                new_account,
+                billing,
+                identity,
+            )
+            .await
+            {
+                Ok(a) => Ok(a),
+                Err(err) => {
+                    // Two first logins of the same identity race on its unique index, the
+                    // one that lost logs in to the account the other created
+                    if let Ok(Some((_, account))) =
+                        ProviderIdentityQueryCore::get_account_by_provider_identity(
+                            &state.databases.postgres_conn,
+                            provider,
+                            &profile.subject,
+                        )
+                        .await
+                    {
+                        refuse_blocked_account(&account, now_unix)?;
+                        return Ok(account);
+                    }
+
+                    error!("Error creating account {:?}", err);
+                    Err(ResponseBuilderError {
+                        message: "Error creating account".to_string(),
+                        errors: vec![],
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Deleted and suspended accounts can't log in
+fn refuse_blocked_account(account: &Model, now: Timestamp) -> Result<(), ResponseBuilderError> {
+    let (error_id, message) = if account.deleted {
+        (
+            Errors::AccountDeleted,
+            "This account was deleted".to_string(),
+        )
+    } else if account.is_suspended(now) {
+        (Errors::AccountSuspended, suspension_message(account))
+    } else {
+        return Ok(());
+    };
+
+    Err(ResponseBuilderError {
+        message,
+        errors: vec![ResponseObjectError {
+            error_id,
+            message: None,
+            field: None,
+        }],
+    })
+}
+
+/// Copies what changed at the provider onto the account, except the fields the user locked.
+/// Empty names and missing avatars are skipped. An email is only taken when verified and
+/// when the identity's old email was the account's, so two identities don't take turns, or
+/// when the account has none yet.
+async fn sync_profile(
+    state: &APIStateWrapper,
+    account: Model,
+    profile: &OAuthProfile,
+    previous_email: Option<&str>,
+) -> Model {
+    let unlocked = |field: ProfileField| !account.locked_fields.contains(&field);
+    let mut patch = AccountProfilePatch::default();
+
+    if unlocked(ProfileField::Name) && !profile.name.is_empty() && profile.name != account.name {
+        patch.name = Some(profile.name.clone());
+    }
+
+    if let (Some(email), true) = (&profile.email, profile.email_verified) {
+        let was_account_email = account.email.is_empty()
+            || previous_email.is_some_and(|e| e.eq_ignore_ascii_case(&account.email));
+        if unlocked(ProfileField::Email)
+            && was_account_email
+            && !email.eq_ignore_ascii_case(&account.email)
+        {
+            patch.email = Some(email.clone());
+        }
+    }
+
+    if profile.avatar.is_some()
+        && unlocked(ProfileField::Avatar)
+        && profile.avatar != account.avatar
+    {
+        patch.avatar = Some(profile.avatar.clone());
+    }
+
+    if patch.is_empty() {
+        return account;
+    }
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    match AccountMutationCore::patch_account_profile(
+        &state.databases.postgres_conn,
+        account.id,
+        patch,
+        now_unix,
+    )
+    .await
+    {
+        Ok(a) => a,
+        Err(err) => {
+            // a stale profile is no reason to refuse the login
+            error!("Error syncing profile of account {} {:?}", account.id, err);
+            account
+        }
+    }
+}
+
+/// Starts a session for the account and answers with its tokens
+pub async fn log_in(
+    req: &HttpRequest,
+    state: &APIStateWrapper,
+    account: &Model,
+    device: Option<String>,
+) -> Result<web::Json<ResponseObject<TokenPair>>> {
+    let session_id = match start_session(state, req, account, device).await {
+        Ok(s) => s,
+        Err(_) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error creating session".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    let tokens = match issue_token_pair(state, account, session_id).await {
+        Ok(t) => t,
         Err(_) => {
             return Ok(build_err(ResponseBuilderError {
-                message: "Error getting Google profile".to_string(),
+                message: "Error creating token".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Successfully logged in".to_string()),
+        data: Some(tokens),
+    }))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct RefreshTokenForm {
+    pub refresh_token: Option<String>,
+}
+
+/// # Refresh Controller
+///
+/// Swaps a refresh token for a new access and refresh token pair. Every refresh token can be
+/// used once; if a spent token shows up again, someone else holds a copy, so the whole family
+/// is revoked and the user has to log in again.
+pub async fn refresh_controller(
+    req: HttpRequest,
+    state: APIStateWrapper,
+    data: web::Form<RefreshTokenForm>,
+) -> Result<impl Responder> {
+    let refresh_token = match &data.refresh_token {
+        Some(t) => t,
+        None => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "no refresh token".to_string(),
                 errors: vec![],
             }))
         }
     };
 
-    error!("Google profile: {:?}", profile);
+    let claims = match jwt::validate_token(&state.key_ring, &state.jwt_settings, refresh_token) {
+        Ok(c) => c,
+        Err(err) => {
+            let message = err.to_string();
+            return Ok(build_err(ResponseBuilderError {
+                message: "Invalid refresh token".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: err,
+                    message: Some(message),
+                    field: None,
+                }],
+            }));
+        }
+    };
+
+    let token_id = match (&claims.r#type, claims.jti) {
+        (JWTTokenType::Refresh, Some(jti)) => jti,
+        _ => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Invalid refresh token".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::InvalidToken,
+                    message: None,
+                    field: None,
+                }],
+            }))
+        }
+    };
 
-    if profile.id.is_empty() {
-        debug!("Invalid Google profile");
+    let stored = match RefreshTokenQueryCore::get_refresh_token_by_id(
+        &state.databases.postgres_conn,
+        token_id,
+    )
+    .await
+    {
+        Ok(Some(t)) if t.account_id == claims.sub => t,
+        Ok(_) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Invalid refresh token".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::InvalidToken,
+                    message: None,
+                    field: None,
+                }],
+            }))
+        }
+        Err(err) => {
+            error!("Error getting refresh token {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting refresh token".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
 
+    if stored.revoked {
         return Ok(build_err(ResponseBuilderError {
-            message: "Invalid email".to_string(),
-            errors: vec![],
+            message: "Refresh token revoked".to_string(),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::RevokedToken,
+                message: None,
+                field: None,
+            }],
         }));
     }
 
-    // Check if account already exists
-    let mut account = match AccountQueryCore::get_account_by_google_id(
+    // Logging a session out revokes its refresh tokens too, this is just belt and braces
+    match SessionQueryCore::get_session_by_id(&state.databases.postgres_conn, stored.family_id)
+        .await
+    {
+        Ok(Some(s)) if !s.revoked => (),
+        Ok(_) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Session revoked".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::RevokedToken,
+                    message: None,
+                    field: None,
+                }],
+            }));
+        }
+        Err(err) => {
+            error!("Error getting session {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting session".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    let first_use = match RefreshTokenMutationCore::mark_refresh_token_used(
         &state.databases.postgres_conn,
-        &profile.id,
+        stored.id,
+        now_unix,
     )
     .await
     {
-        Ok(a) => a,
+        Ok(f) => f,
         Err(err) => {
-            error!("Error getting account {:?}", err);
+            error!("Error marking refresh token as used {:?}", err);
             return Ok(build_err(ResponseBuilderError {
-                message: "Error getting account".to_string(),
+                message: "Error refreshing token".to_string(),
                 errors: vec![],
-            }))
+            }));
         }
     };
 
-    let now_unix = Utc::now().timestamp() as Timestamp;
-    if account.is_none() {
-        debug!("Account does not exist, creating new account");
-        let billing = billing::Model {
-            id: random_int() as ID,
-            account_id: 0,
-            credits: DEFAULT_STARTER_CREDITS,
-            total_spent_usd: 0.0,
-            created_at: now_unix,
-            updated_at: now_unix,
-        };
-
-        let new_account = Model {
-            id: random_int() as ID,
-            google_id: profile.id,
-            email: profile.email,
-            name: profile.name,
-            avatar: profile.picture,
-            flags: vec![],
-            billing_id: 0,
-            created_at: now_unix,
-            updated_at: now_unix,
-            deleted: false,
-            deletion_requested_at: None,
-            deletion_reason: None,
-        };
-
-        match AccountMutationCore::create_account(
+    if !first_use {
+        warn!(
+            "Refresh token reuse detected, revoking family {} of account {}",
+            stored.family_id, stored.account_id
+        );
+
+        if let Err(err) = SessionMutationCore::revoke_session(
             &state.databases.postgres_conn,
-            new_account.clone(),
-            billing,
+            stored.account_id,
+            stored.family_id,
+            now_unix,
         )
         .await
         {
-            Ok(_) => (),
-            Err(_) => {
-                return Ok(build_err(ResponseBuilderError {
-                    message: "Error creating account".to_string(),
-                    errors: vec![],
-                }))
-            }
-        };
+            error!("Error revoking session {:?}", err);
+        }
 
-        account = Some(new_account);
+        return Ok(build_err(ResponseBuilderError {
+            message: "Refresh token reused".to_string(),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::RevokedToken,
+                message: Some("Log in again".to_string()),
+                field: None,
+            }],
+        }));
     }
 
-    let account = account.unwrap();
-    let token = match jwt::new_token(
-        &state.pems.tokens_private,
-        Algorithm::RS256,
-        JWTClaims {
-            r#type: JWTTokenType::Access,
-            sub: account.id,
-            provider_id: account.google_id,
-            exp: (Utc::now() + Duration::days(30)).timestamp() as i64,
-            iss: Some(now_unix),
-            max_requests_per_hour: 512,
-        },
-    ) {
+    let account = match AccountQueryCore::get_account_by_id(
+        &state.databases.postgres_conn,
+        stored.account_id,
+    )
+    .await
+    {
+        Ok(Some(a)) => a,
+        Ok(None) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Account not found".to_string(),
+                errors: vec![],
+            }))
+        }
+        Err(err) => {
+            error!("Error getting account {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting account".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    if let Err(err) = refuse_blocked_account(&account, now_unix) {
+        return Ok(build_err(err));
+    }
+
+    if let Err(err) = SessionMutationCore::touch_session(
+        &state.databases.postgres_conn,
+        stored.family_id,
+        get_client_ip(&req),
+        now_unix,
+    )
+    .await
+    {
+        error!("Error updating session last seen {:?}", err);
+    }
+
+    let tokens = match issue_token_pair(&state, &account, stored.family_id).await {
         Ok(t) => t,
         Err(_) => {
             return Ok(build_err(ResponseBuilderError {
@@ -169,8 +695,8 @@ pub async fn access_google_controller(
     };
 
     Ok(build_ok(ResponseBuilderOk {
-        message: Some("Successfully logged in".to_string()),
-        data: Some(token),
+        message: Some("Token refreshed".to_string()),
+        data: Some(tokens),
     }))
 }
 
@@ -184,107 +710,10 @@ pub async fn validate_token_controller(
     req: HttpRequest,
     state: APIStateWrapper,
 ) -> Result<impl Responder> {
-    let claims = match get_claims_from_header(req.headers().get("Authorization"), state.pems.tokens_public.clone()) {
-        Ok(c) => c,
-        Err(e) => {
-            return Ok(e);
-        }
-    };
+    let claims = get_claims_from_header(&req, &state).await?;
 
     Ok(build_ok(ResponseBuilderOk {
         message: Some("Token is valid".to_string()),
         data: Some(claims),
     }))
 }
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct GetGoogleTokens {
-    pub code: String,
-    pub client_id: String,
-    pub client_secret: String,
-    pub redirect_uri: String,
-    pub grant_type: String,
-}
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct OAuthResponse {
-    pub access_token: String,
-    pub expires_in: i64,
-    pub refresh_token: Option<String>,
-    pub scope: String,
-    pub token_type: String,
-}
-
-pub async fn get_google_user_tokens(
-    code: String,
-    env: Enviroment,
-) -> Result<OAuthResponse, Errors> {
-    let mut redirect_uri = env.oauth.google.client_redirects[1].clone();
-    if env.production {
-        redirect_uri = env.oauth.google.client_redirects[0].clone();
-    }
-
-    let params = [
-        ("grant_type", "authorization_code"),
-        (
-            "redirect_uri",
-            redirect_uri.as_str(),
-        ),
-        ("client_id", env.oauth.google.client_id.as_str()),
-        ("code", code.as_str()),
-        ("client_secret", env.oauth.google.client_secret.as_str()),
-    ];
-
-    let client = reqwest::Client::new();
-    let response = client
-        .post(GOOGLE_GET_TOKENS_URL)
-        .form(&params)
-        .send()
-        .await.map_err(|_| Errors::BadRequest)?;
-
-    if response.status().is_success() {
-        let oauth_response = response
-            .json::<OAuthResponse>()
-            .await
-            .map_err(|_| Errors::BadRequest)?;
-        Ok(oauth_response)
-    } else {
-        Err(Errors::BadRequest)
-    }
-}
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct GoogleUserResult {
-    pub id: String,
-    pub email: String,
-    pub verified_email: bool,
-    pub name: String,
-    pub given_name: Option<String>,
-    pub family_name: Option<String>,
-    pub picture: Option<String>,
-    pub locale: Option<String>,
-}
-
-pub async fn get_google_user_profile(access_token: String) -> Result<GoogleUserResult, Errors> {
-    let client = Client::new();
-    let mut url = Url::parse(GOOGLE_GET_PROFILE_URL).unwrap();
-    url.query_pairs_mut().append_pair("alt", "json");
-    url.query_pairs_mut()
-        .append_pair("access_token", &access_token);
-
-    let response = client
-        .get(url)
-        .bearer_auth(access_token)
-        .send()
-        .await.map_err(|_| Errors::BadRequest)?;
-
-    if response.status().is_success() {
-        let user_info = response
-            .json::<GoogleUserResult>()
-            .await.map_err(|_| Errors::BadRequest)?;
-
-        Ok(user_info)
-    } else {
-        Err(Errors::BadRequest)
-    }
-}
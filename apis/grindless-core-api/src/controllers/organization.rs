@@ -0,0 +1,943 @@
+use crate::{
+    controllers::{account::validate_name, ledger::LEDGER_ENTRIES_SHOWN},
+    extractors::AuthenticatedAccount,
+    mail::Email,
+    state::APIStateWrapper,
+    tokens::{ORGANIZATION_INVITATION_TOKEN_LEN, ORGANIZATION_INVITATION_TTL_DAYS},
+};
+use actix_web::{web, Responder, Result};
+use chrono::{Duration, Utc};
+use grindless_core::{
+    entities::{
+        account_ops::AccountQueryCore,
+        api_key::SCOPE_ACCOUNTS_READ,
+        billing,
+        ledger_entry::LedgerReason,
+        ledger_entry_ops::{CreditMovement, LedgerEntryQueryCore},
+        organization, organization_invitation,
+        organization_invitation_ops::{
+            InvitationAcceptance, OrganizationInvitationMutationCore,
+            OrganizationInvitationQueryCore,
+        },
+        organization_member::{self, OrganizationRole},
+        organization_member_ops::{
+            MemberChange, OrganizationMemberMutationCore, OrganizationMemberQueryCore,
+        },
+        organization_ops::{OrganizationMutationCore, OrganizationQueryCore},
+        provider_identity_ops::ProviderIdentityQueryCore,
+    },
+    response::{
+        errors::Errors,
+        response::{
+            build_err, build_ok, ResponseBuilderError, ResponseBuilderOk, ResponseObjectError,
+        },
+    },
+    util::{
+        secrets::{random_token, sha256_hex},
+        snowflake::new_id,
+    },
+    Timestamp, ID,
+};
+use log::error;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+pub static MAX_ORGANIZATION_MEMBERS: u64 = 100;
+
+fn not_found() -> ResponseBuilderError {
+    ResponseBuilderError {
+        message: "Organization not found".to_string(),
+        errors: vec![ResponseObjectError {
+            error_id: Errors::NotFound,
+            message: None,
+            field: None,
+        }],
+    }
+}
+
+fn forbidden(message: &str) -> ResponseBuilderError {
+    ResponseBuilderError {
+        message: message.to_string(),
+        errors: vec![ResponseObjectError {
+            error_id: Errors::Forbidden,
+            message: None,
+            field: None,
+        }],
+    }
+}
+
+fn conflict(message: &str) -> ResponseBuilderError {
+    ResponseBuilderError {
+        message: message.to_string(),
+        errors: vec![ResponseObjectError {
+            error_id: Errors::Conflict,
+            message: None,
+            field: None,
+        }],
+    }
+}
+
+fn invalid_field(field: &str, message: String) -> ResponseBuilderError {
+    ResponseBuilderError {
+        message: "Invalid fields".to_string(),
+        errors: vec![ResponseObjectError {
+            error_id: Errors::UnprocessableEntity,
+            message: Some(message),
+            field: Some(field.to_string()),
+        }],
+    }
+}
+
+fn database_error(message: &str, err: sea_orm::DbErr) -> ResponseBuilderError {
+    error!("{} {:?}", message, err);
+    ResponseBuilderError {
+        message: message.to_string(),
+        errors: vec![],
+    }
+}
+
+fn parse_role(role: Option<&str>) -> Result<OrganizationRole, ResponseBuilderError> {
+    match role.map(OrganizationRole::from_string) {
+        None => Ok(OrganizationRole::Member),
+        Some(Some(r)) => Ok(r),
+        Some(None) => Err(invalid_field(
+            "role",
+            "Role is member, admin or owner".to_string(),
+        )),
+    }
+}
+
+/// The membership of the account in the organization. Organizations the account is not in
+/// are reported as not found, so their ids can't be probed.
+async fn get_membership(
+    state: &APIStateWrapper,
+    organization_id: ID,
+    account_id: ID,
+) -> Result<organization_member::Model, ResponseBuilderError> {
+    match OrganizationMemberQueryCore::get_member(
+        &state.databases.postgres_conn,
+        organization_id,
+        account_id,
+    )
+    .await
+    {
+        Ok(Some(m)) => Ok(m),
+        Ok(None) => Err(not_found()),
+        Err(err) => Err(database_error("Error getting organization", err)),
+    }
+}
+
+fn require_manager(member: &organization_member::Model) -> Result<(), ResponseBuilderError> {
+    match member.role.can_manage_members() {
+        true => Ok(()),
+        false => Err(forbidden("Only owners and admins can manage members")),
+    }
+}
+
+/// Owners can act on anyone, admins only on plain members
+fn require_outranks(
+    actor: &organization_member::Model,
+    target: &organization_member::Model,
+) -> Result<(), ResponseBuilderError> {
+    require_manager(actor)?;
+    match actor.role == OrganizationRole::Owner || target.role < actor.role {
+        true => Ok(()),
+        false => Err(forbidden("Only owners can manage admins and owners")),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateOrganizationForm {
+    pub name: Option<String>,
+}
+
+/// # Create Organization Controller
+///
+/// Creates an organization with an empty credit pool, the account becomes its owner.
+pub async fn create_organization_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    data: web::Form<CreateOrganizationForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let name = match validate_name(data.name.as_deref().unwrap_or_default()) {
+        Ok(n) => n,
+        Err(message) => return Ok(build_err(invalid_field("name", message))),
+    };
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    let organization = organization::Model {
+        id: new_id(),
+        name,
+        billing_id: new_id(),
+        created_by: authenticated.account.id,
+        created_at: now_unix,
+        updated_at: now_unix,
+    };
+    let billing = billing::Model {
+        id: organization.billing_id,
+        account_id: None,
+        organization_id: Some(organization.id),
+        credits: 0,
+        total_spent_usd: 0.0,
+        created_at: now_unix,
+        updated_at: now_unix,
+    };
+    let owner = organization_member::Model {
+        id: new_id(),
+        organization_id: organization.id,
+        account_id: authenticated.account.id,
+        role: OrganizationRole::Owner,
+        spending_cap: None,
+        credits_spent: 0,
+        created_at: now_unix,
+        updated_at: now_unix,
+    };
+
+    match OrganizationMutationCore::create_organization(
+        &state.databases.postgres_conn,
+        organization,
+        billing.clone(),
+        owner.clone(),
+    )
+    .await
+    {
+        Ok(o) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Organization created".to_string()),
+            data: Some(json!({
+                "organization": o,
+                "billing": billing,
+                "membership": owner,
+            })),
+        })),
+        Err(err) => Ok(build_err(database_error(
+            "Error creating organization",
+            err,
+        ))),
+    }
+}
+
+/// # Get My Organizations Controller
+///
+/// Every organization the account is in, with its role and spending in each.
+pub async fn get_my_organizations_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<impl Responder> {
+    authenticated.require_scope(SCOPE_ACCOUNTS_READ)?;
+
+    match OrganizationQueryCore::get_organizations_by_account_id(
+        &state.databases.postgres_conn,
+        authenticated.account.id,
+    )
+    .await
+    {
+        Ok(memberships) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Organizations found".to_string()),
+            data: Some(json!({
+                "organizations": memberships
+                    .into_iter()
+                    .map(|(membership, organization)| json!({
+                        "organization": organization,
+                        "membership": membership,
+                    }))
+                    .collect::<Vec<_>>(),
+            })),
+        })),
+        Err(err) => Ok(build_err(database_error(
+            "Error getting organizations",
+            err,
+        ))),
+    }
+}
+
+/// # Get Organization Controller
+///
+/// The organization with its credit pool and members.
+pub async fn get_organization_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<impl Responder> {
+    authenticated.require_scope(SCOPE_ACCOUNTS_READ)?;
+
+    let membership = match get_membership(&state, path.into_inner(), authenticated.account.id).await
+    {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    let db = &state.databases.postgres_conn;
+    let organization =
+        match OrganizationQueryCore::get_organization_by_id(db, membership.organization_id).await {
+            Ok(Some(o)) => o,
+            Ok(None) => return Ok(build_err(not_found())),
+            Err(err) => return Ok(build_err(database_error("Error getting organization", err))),
+        };
+    let billing = match OrganizationQueryCore::get_organization_billing(db, organization.id).await {
+        Ok(b) => b,
+        Err(err) => return Ok(build_err(database_error("Error getting organization", err))),
+    };
+    let members =
+        match OrganizationMemberQueryCore::get_members_with_accounts(db, organization.id).await {
+            Ok(m) => m,
+            Err(err) => return Ok(build_err(database_error("Error getting members", err))),
+        };
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Organization found".to_string()),
+        data: Some(json!({
+            "organization": organization,
+            "billing": billing,
+            "membership": membership,
+            "members": members
+                .into_iter()
+                .map(|(member, account)| json!({
+                    "member": member,
+                    "name": account.as_ref().map(|a| &a.name),
+                    "email": account.as_ref().map(|a| &a.email),
+                    "avatar": account.as_ref().and_then(|a| a.avatar.as_ref()),
+                }))
+                .collect::<Vec<_>>(),
+        })),
+    }))
+}
+
+/// # Get Organization Ledger Controller
+///
+/// The latest changes to the pool, newest first. Only owners and admins see who spent what.
+pub async fn get_organization_ledger_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<impl Responder> {
+    authenticated.require_scope(SCOPE_ACCOUNTS_READ)?;
+
+    let membership = match get_membership(&state, path.into_inner(), authenticated.account.id).await
+    {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+    if let Err(err) = require_manager(&membership) {
+        return Ok(build_err(err));
+    }
+
+    let db = &state.databases.postgres_conn;
+    let billing =
+        match OrganizationQueryCore::get_organization_billing(db, membership.organization_id).await
+        {
+            Ok(Some(b)) => b,
+            Ok(None) => return Ok(build_err(not_found())),
+            Err(err) => return Ok(build_err(database_error("Error getting organization", err))),
+        };
+
+    match LedgerEntryQueryCore::get_ledger_entries_by_billing_id(
+        db,
+        billing.id,
+        Some(LEDGER_ENTRIES_SHOWN),
+    )
+    .await
+    {
+        Ok(entries) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Ledger found".to_string()),
+            data: Some(json!({
+                "entries": entries,
+                "credits": billing.credits,
+            })),
+        })),
+        Err(err) => Ok(build_err(database_error("Error getting ledger", err))),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TransferCreditsForm {
+    pub amount: Option<i64>,
+}
+
+/// # Transfer Credits Controller
+///
+/// Moves credits from the account's own balance into the organization's pool.
+pub async fn transfer_credits_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+    data: web::Form<TransferCreditsForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let amount = match data.amount {
+        Some(a) if a > 0 => a,
+        _ => {
+            return Ok(build_err(invalid_field(
+                "amount",
+                "Amount must be positive".to_string(),
+            )))
+        }
+    };
+
+    let membership = match get_membership(&state, path.into_inner(), authenticated.account.id).await
+    {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    match OrganizationMutationCore::transfer_credits_to_organization(
+        &state.databases.postgres_conn,
+        authenticated.account.id,
+        membership.organization_id,
+        amount,
+        &CreditMovement::new(LedgerReason::Transfer, authenticated.account.id),
+        now_unix,
+    )
+    .await
+    {
+        Ok(Some(billing)) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Credits transferred".to_string()),
+            data: Some(json!({ "billing": billing })),
+        })),
+        Ok(None) => Ok(build_err(invalid_field(
+            "amount",
+            "Insufficient credits".to_string(),
+        ))),
+        Err(err) => Ok(build_err(database_error("Error transferring credits", err))),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct InviteMemberForm {
+    pub email: Option<String>,
+    /// `member`, `admin` or `owner`, `member` when left out
+    pub role: Option<String>,
+}
+
+/// # Invite Member Controller
+///
+/// Mails an invitation to join the organization. Only owners can invite admins and owners.
+pub async fn invite_member_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+    data: web::Form<InviteMemberForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let invite_url = match &state.env.organization_invite_url {
+        Some(u) => u,
+        None => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Invitations are not enabled".to_string(),
+                errors: vec![],
+            }))
+        }
+    };
+
+    let membership = match get_membership(&state, path.into_inner(), authenticated.account.id).await
+    {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+    if let Err(err) = require_manager(&membership) {
+        return Ok(build_err(err));
+    }
+
+    let email = match data.email.as_deref().map(|e| e.trim().to_lowercase()) {
+        Some(e) if e.parse::<lettre::Address>().is_ok() => e,
+        _ => {
+            return Ok(build_err(invalid_field(
+                "email",
+                "Invalid email".to_string(),
+            )))
+        }
+    };
+
+    let role = match parse_role(data.role.as_deref()) {
+        Ok(r) => r,
+        Err(err) => return Ok(build_err(err)),
+    };
+    if role != OrganizationRole::Member && membership.role != OrganizationRole::Owner {
+        return Ok(build_err(forbidden(
+            "Only owners can invite admins and owners",
+        )));
+    }
+
+    let db = &state.databases.postgres_conn;
+    let organization =
+        match OrganizationQueryCore::get_organization_by_id(db, membership.organization_id).await {
+            Ok(Some(o)) => o,
+            Ok(None) => return Ok(build_err(not_found())),
+            Err(err) => return Ok(build_err(database_error("Error getting organization", err))),
+        };
+
+    match OrganizationMemberQueryCore::count_members(db, organization.id).await {
+        Ok(members) if members >= MAX_ORGANIZATION_MEMBERS => {
+            return Ok(build_err(conflict(&format!(
+                "Organizations can't have more than {} members",
+                MAX_ORGANIZATION_MEMBERS
+            ))))
+        }
+        Ok(_) => (),
+        Err(err) => return Ok(build_err(database_error("Error counting members", err))),
+    }
+
+    if let Ok(Some(invited)) = AccountQueryCore::get_account_by_email(db, &email).await {
+        match OrganizationMemberQueryCore::get_member(db, organization.id, invited.id).await {
+            Ok(Some(_)) => return Ok(build_err(conflict("Already a member"))),
+            Ok(None) => (),
+            Err(err) => return Ok(build_err(database_error("Error inviting member", err))),
+        }
+    }
+
+    let token = random_token(ORGANIZATION_INVITATION_TOKEN_LEN);
+    let mut url = match Url::parse(invite_url) {
+        Ok(u) => u,
+        Err(_) => {
+            error!("ORGANIZATION_INVITE_URL is not a valid URL");
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error inviting member".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+    url.query_pairs_mut().append_pair("token", &token);
+
+    let now = Utc::now();
+    let invitation = match OrganizationInvitationMutationCore::create_invitation(
+        db,
+        organization_invitation::Model {
+            id: new_id(),
+            organization_id: organization.id,
+            email: email.clone(),
+            role,
+            token_hash: sha256_hex(&token),
+            invited_by: authenticated.account.id,
+            accepted_by: None,
+            created_at: now.timestamp(),
+            expires_at: (now + Duration::days(ORGANIZATION_INVITATION_TTL_DAYS)).timestamp(),
+            accepted_at: None,
+        },
+    )
+    .await
+    {
+        Ok(i) => i,
+        Err(err) => return Ok(build_err(database_error("Error inviting member", err))),
+    };
+
+    let sent = state
+        .mailer
+        .send(Email {
+            to: email,
+            subject: format!("Join {} on Grindless", organization.name),
+            body: format!(
+                "{} invited you to join {} on Grindless.\n\nOpen this link to accept, logged in with this address:\n\n{}\n\nThe invitation expires in {} days. If you don't know them, you can ignore this mail.\n",
+                authenticated.account.name, organization.name, url, ORGANIZATION_INVITATION_TTL_DAYS
+            ),
+        })
+        .await;
+
+    if let Err(err) = sent {
+        error!("Error sending invitation {:?}", err);
+        if let Err(err) = OrganizationInvitationMutationCore::revoke_invitation(
+            db,
+            organization.id,
+            invitation.id,
+        )
+        .await
+        {
+            error!("Error removing unsent invitation {:?}", err);
+        }
+        return Ok(build_err(ResponseBuilderError {
+            message: "Error sending invitation".to_string(),
+            errors: vec![],
+        }));
+    }
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Invitation sent".to_string()),
+        data: Some(json!({ "invitation": invitation })),
+    }))
+}
+
+/// # Get Invitations Controller
+///
+/// Invitations of the organization that were not accepted yet.
+pub async fn get_invitations_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<impl Responder> {
+    authenticated.require_scope(SCOPE_ACCOUNTS_READ)?;
+
+    let membership = match get_membership(&state, path.into_inner(), authenticated.account.id).await
+    {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+    if let Err(err) = require_manager(&membership) {
+        return Ok(build_err(err));
+    }
+
+    match OrganizationInvitationQueryCore::get_pending_invitations_by_organization_id(
+        &state.databases.postgres_conn,
+        membership.organization_id,
+        Utc::now().timestamp() as Timestamp,
+    )
+    .await
+    {
+        Ok(invitations) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Invitations found".to_string()),
+            data: Some(json!({ "invitations": invitations })),
+        })),
+        Err(err) => Ok(build_err(database_error("Error getting invitations", err))),
+    }
+}
+
+/// # Revoke Invitation Controller
+///
+/// Cancels an invitation, its link stops working.
+pub async fn revoke_invitation_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<(ID, ID)>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+    let (organization_id, invitation_id) = path.into_inner();
+
+    let membership = match get_membership(&state, organization_id, authenticated.account.id).await {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+    if let Err(err) = require_manager(&membership) {
+        return Ok(build_err(err));
+    }
+
+    match OrganizationInvitationMutationCore::revoke_invitation(
+        &state.databases.postgres_conn,
+        organization_id,
+        invitation_id,
+    )
+    .await
+    {
+        Ok(true) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Invitation revoked".to_string()),
+            data: Some(json!({ "invitation_id": invitation_id })),
+        })),
+        Ok(false) => Ok(build_err(ResponseBuilderError {
+            message: "Invitation not found".to_string(),
+            errors: vec![ResponseObjectError {
+                error_id: Errors::NotFound,
+                message: None,
+                field: None,
+            }],
+        })),
+        Err(err) => Ok(build_err(database_error("Error revoking invitation", err))),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct AcceptInvitationForm {
+    pub token: Option<String>,
+}
+
+/// # Accept Invitation Controller
+///
+/// Joins the organization with the token from the invitation mail. Only works for an
+/// account with a verified identity for the address the invitation was sent to.
+pub async fn accept_invitation_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    data: web::Form<AcceptInvitationForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+
+    let token = match &data.token {
+        Some(t) if !t.is_empty() => t,
+        _ => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "no token".to_string(),
+                errors: vec![],
+            }))
+        }
+    };
+
+    let invalid = || ResponseBuilderError {
+        message: "This invitation is invalid, expired or was already accepted".to_string(),
+        errors: vec![ResponseObjectError {
+            error_id: Errors::InvalidToken,
+            message: None,
+            field: None,
+        }],
+    };
+
+    let db = &state.databases.postgres_conn;
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    let invitation = match OrganizationInvitationQueryCore::get_pending_invitation_by_token_hash(
+        db,
+        &sha256_hex(token),
+        now_unix,
+    )
+    .await
+    {
+        Ok(Some(i)) => i,
+        Ok(None) => return Ok(build_err(invalid())),
+        Err(err) => return Ok(build_err(database_error("Error accepting invitation", err))),
+    };
+
+    // Like merges, only an address a provider verified for this account counts
+    match ProviderIdentityQueryCore::has_verified_email(
+        db,
+        authenticated.account.id,
+        &invitation.email,
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => {
+            return Ok(build_err(forbidden(
+                "This invitation was sent to an email address you haven't verified",
+            )))
+        }
+        Err(err) => return Ok(build_err(database_error("Error accepting invitation", err))),
+    }
+
+    match OrganizationMemberQueryCore::get_member(
+        db,
+        invitation.organization_id,
+        authenticated.account.id,
+    )
+    .await
+    {
+        Ok(Some(_)) => return Ok(build_err(conflict("Already a member"))),
+        Ok(None) => (),
+        Err(err) => return Ok(build_err(database_error("Error accepting invitation", err))),
+    }
+
+    match OrganizationInvitationMutationCore::accept_invitation(
+        db,
+        &invitation,
+        new_id(),
+        authenticated.account.id,
+        MAX_ORGANIZATION_MEMBERS,
+        now_unix,
+    )
+    .await
+    {
+        Ok(InvitationAcceptance::Accepted(member)) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Invitation accepted".to_string()),
+            data: Some(json!({ "membership": member })),
+        })),
+        Ok(InvitationAcceptance::Unavailable) => Ok(build_err(invalid())),
+        Ok(InvitationAcceptance::OrganizationFull) => Ok(build_err(conflict(&format!(
+            "Organizations can't have more than {} members",
+            MAX_ORGANIZATION_MEMBERS
+        )))),
+        Err(err) => Ok(build_err(database_error("Error accepting invitation", err))),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SetMemberRoleForm {
+    pub role: Option<String>,
+}
+
+/// # Set Member Role Controller
+///
+/// Owners only. The last owner can't step down.
+pub async fn set_member_role_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<(ID, ID)>,
+    data: web::Form<SetMemberRoleForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+    let (organization_id, account_id) = path.into_inner();
+
+    let role = match data.role.as_deref() {
+        Some(r) => match parse_role(Some(r)) {
+            Ok(r) => r,
+            Err(err) => return Ok(build_err(err)),
+        },
+        None => {
+            return Ok(build_err(invalid_field(
+                "role",
+                "A role is required".to_string(),
+            )))
+        }
+    };
+
+    let membership = match get_membership(&state, organization_id, authenticated.account.id).await {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+    if membership.role != OrganizationRole::Owner {
+        return Ok(build_err(forbidden("Only owners can change roles")));
+    }
+
+    let member = match get_membership(&state, organization_id, account_id).await {
+        Ok(m) => m,
+        Err(_) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Member not found".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::NotFound,
+                    message: None,
+                    field: None,
+                }],
+            }))
+        }
+    };
+    if member.role == role {
+        return Ok(build_ok(ResponseBuilderOk {
+            message: Some("Role updated".to_string()),
+            data: Some(json!({ "membership": member })),
+        }));
+    }
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    match OrganizationMemberMutationCore::set_member_role(
+        &state.databases.postgres_conn,
+        organization_id,
+        account_id,
+        role,
+        now_unix,
+    )
+    .await
+    {
+        Ok(MemberChange::Changed) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Role updated".to_string()),
+            data: Some(json!({
+                "membership": organization_member::Model { role, updated_at: now_unix, ..member },
+            })),
+        })),
+        Ok(MemberChange::NotMember) => Ok(build_err(not_found())),
+        Ok(MemberChange::LastOwner) => Ok(build_err(conflict(
+            "The organization needs another owner first",
+        ))),
+        Err(err) => Ok(build_err(database_error("Error updating role", err))),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SetSpendingCapForm {
+    /// Credits the member may spend from the pool, empty to lift the cap
+    pub spending_cap: Option<String>,
+    /// Stops counting what the member spent so far
+    pub reset_spent: Option<bool>,
+}
+
+/// # Set Spending Cap Controller
+///
+/// Limits how many credits a member can spend from the pool.
+pub async fn set_spending_cap_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<(ID, ID)>,
+    data: web::Form<SetSpendingCapForm>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+    let (organization_id, account_id) = path.into_inner();
+
+    let spending_cap = match data
+        .spending_cap
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+    {
+        None => None,
+        Some(c) => match c.parse::<i64>() {
+            Ok(c) if c >= 0 => Some(c),
+            _ => {
+                return Ok(build_err(invalid_field(
+                    "spending_cap",
+                    "Spending cap must be a positive number of credits".to_string(),
+                )))
+            }
+        },
+    };
+
+    let membership = match get_membership(&state, organization_id, authenticated.account.id).await {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+    let member = match get_membership(&state, organization_id, account_id).await {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+    if let Err(err) = require_outranks(&membership, &member) {
+        return Ok(build_err(err));
+    }
+
+    let now_unix = Utc::now().timestamp() as Timestamp;
+    match OrganizationMemberMutationCore::set_member_spending_cap(
+        &state.databases.postgres_conn,
+        organization_id,
+        account_id,
+        spending_cap,
+        data.reset_spent.unwrap_or(false),
+        now_unix,
+    )
+    .await
+    {
+        Ok(Some(m)) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Spending cap updated".to_string()),
+            data: Some(json!({ "membership": m })),
+        })),
+        Ok(None) => Ok(build_err(not_found())),
+        Err(err) => Ok(build_err(database_error(
+            "Error updating spending cap",
+            err,
+        ))),
+    }
+}
+
+/// # Remove Member Controller
+///
+/// Removes a member, or leaves the organization when it is the account's own id.
+pub async fn remove_member_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<(ID, ID)>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+    let (organization_id, account_id) = path.into_inner();
+
+    let membership = match get_membership(&state, organization_id, authenticated.account.id).await {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+    let member = match get_membership(&state, organization_id, account_id).await {
+        Ok(m) => m,
+        Err(err) => return Ok(build_err(err)),
+    };
+    if member.account_id != membership.account_id {
+        if let Err(err) = require_outranks(&membership, &member) {
+            return Ok(build_err(err));
+        }
+    }
+
+    match OrganizationMemberMutationCore::remove_member(
+        &state.databases.postgres_conn,
+        organization_id,
+        account_id,
+    )
+    .await
+    {
+        Ok(MemberChange::Changed) => Ok(build_ok(ResponseBuilderOk {
+            message: Some("Member removed".to_string()),
+            data: Some(json!({ "account_id": account_id })),
+        })),
+        Ok(MemberChange::NotMember) => Ok(build_err(not_found())),
+        Ok(MemberChange::LastOwner) => Ok(build_err(conflict(
+            "The organization needs another owner first",
+        ))),
+        Err(err) => Ok(build_err(database_error("Error removing member", err))),
+    }
+}
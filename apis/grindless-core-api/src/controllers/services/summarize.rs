@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::{
+    extractors::AuthenticatedAccount,
     services::summary::{
         get_summarize_prompt, SummarizePromptKind, DEFAULT_SUMMARIZE_MAX_INPUT_TOKENS,
         DEFAULT_SUMMARIZE_MAX_OUTPUT_TOKENS, DEFAULT_SUMMARIZE_PRICE_PER_1000_TOKENS_IN_CREDITS,
@@ -8,34 +9,46 @@ use crate::{
     },
     services::{CREDIT_PRICE, DEFAULT_MODEL, TOKEN_WEIGHT},
     state::APIStateWrapper,
-    util::get_claims_from_header,
 };
-use actix_web::{web, HttpRequest, Responder, Result};
+use actix_web::{web, Responder, Result};
 use grindless_core::{
-    entities::account_ops::{AccountMutationCore, AccountQueryCore},
+    billing::{charge, refund, BillingError, Payer},
+    entities::{
+        api_key::SCOPE_SERVICES_SUMMARIZE, ledger_entry::LedgerReason,
+        ledger_entry_ops::CreditMovement, organization_member_ops::OrganizationMemberQueryCore,
+        service_usage, service_usage_ops::ServiceUsageMutationCore,
+    },
     response::{
         errors::Errors,
         response::{
             build_err, build_ok, ResponseBuilderError, ResponseBuilderOk, ResponseObjectError,
         },
     },
+    util::snowflake::new_id,
+    ID,
 };
 use log::{debug, error};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::sync::Mutex;
 
+pub static SUMMARIZE_SERVICE: &str = "summarize";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SummarizeForm {
     pub kind: Option<String>,
     pub text: Option<String>,
+    /// Pays from the pool of this organization instead of the account's own credits
+    pub organization_id: Option<ID>,
 }
 
 pub async fn summarize_controller(
-    req: HttpRequest,
     state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
     form: web::Form<SummarizeForm>,
 ) -> Result<impl Responder> {
+    authenticated.require_scope(SCOPE_SERVICES_SUMMARIZE)?;
+
     let summary_kind = match &form.kind {
         Some(k) => k,
         None => {
@@ -74,51 +87,7 @@ pub async fn summarize_controller(
         }
     };
 
-    let claims = match get_claims_from_header(
-        req.headers().get("Authorization"),
-        state.pems.tokens_public.clone(),
-    ) {
-        Ok(c) => c,
-        Err(e) => {
-            return Ok(e);
-        }
-    };
-
-    let account_and_billing = match AccountQueryCore::get_account_by_id_with_billing(
-        &state.databases.postgres_conn,
-        claims.sub,
-    )
-    .await
-    {
-        Ok(a) => a,
-        Err(err) => {
-            debug!("Error getting account: {:?}", err);
-            return Ok(build_err(ResponseBuilderError {
-                message: "Error getting account".to_string(),
-                errors: vec![],
-            }));
-        }
-    };
-
-    let (account, billing) = match account_and_billing {
-        Some(ab) => ab,
-        None => {
-            return Ok(build_err(ResponseBuilderError {
-                message: "Account not found".to_string(),
-                errors: vec![],
-            }));
-        }
-    };
-
-    let mut billing = match billing {
-        Some(b) => b,
-        None => {
-            return Ok(build_err(ResponseBuilderError {
-                message: "Billing not found".to_string(),
-                errors: vec![],
-            }));
-        }
-    };
+    let AuthenticatedAccount { account, .. } = authenticated;
 
     // 4 tokens per word
     debug!("Information for summarize request:");
@@ -136,34 +105,102 @@ pub async fn summarize_controller(
     let total_cost = total_cost.ceil() as i64;
     debug!("Total cost (ceil, in credits): {}", total_cost);
 
-    if billing.credits < total_cost as i64 {
-        return Ok(build_err(ResponseBuilderError {
-            message: "Insufficient credits".to_string(),
-            errors: vec![],
-        }));
-    }
-
     let now = chrono::Utc::now().timestamp();
-    billing.credits -= total_cost as i64;
-    billing.updated_at = now;
+    let request_id = new_id();
+    let charge_movement = CreditMovement::service(
+        LedgerReason::ServiceCharge,
+        account.id,
+        SUMMARIZE_SERVICE,
+        request_id,
+    );
+    let refund_movement = CreditMovement::service(
+        LedgerReason::Refund,
+        account.id,
+        SUMMARIZE_SERVICE,
+        request_id,
+    );
+    let payer = match form.organization_id {
+        Some(organization_id) => {
+            match OrganizationMemberQueryCore::get_member(
+                &state.databases.postgres_conn,
+                organization_id,
+                account.id,
+            )
+            .await
+            {
+                Ok(Some(_)) => (),
+                Ok(None) => {
+                    return Ok(build_err(ResponseBuilderError {
+                        message: "Organization not found".to_string(),
+                        errors: vec![ResponseObjectError {
+                            error_id: Errors::NotFound,
+                            message: None,
+                            field: Some("organization_id".to_string()),
+                        }],
+                    }))
+                }
+                Err(err) => {
+                    error!("Error getting organization member: {:?}", err);
+                    return Ok(build_err(ResponseBuilderError {
+                        message: "Error updating billing".to_string(),
+                        errors: vec![],
+                    }));
+                }
+            }
 
-    let billing_credits = billing.credits.clone();
-    match AccountMutationCore::update_account_billing_by_account_id(
+            Payer::Organization {
+                organization_id,
+                account_id: account.id,
+            }
+        }
+        None => Payer::Account(account.id),
+    };
+
+    let billing_credits = match charge(
         &state.databases.postgres_conn,
-        account.id,
-        billing.clone(),
+        payer,
+        total_cost,
+        &charge_movement,
+        now,
     )
     .await
     {
-        Ok(_) => (),
-        Err(err) => {
+        Ok(billing) => billing.credits,
+        Err(BillingError::OverSpendingCap) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Spending cap reached".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::SpendingCapReached,
+                    message: None,
+                    field: None,
+                }],
+            }))
+        }
+        Err(BillingError::InsufficientFunds) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Insufficient credits".to_string(),
+                errors: vec![ResponseObjectError {
+                    error_id: Errors::InsufficientCredits,
+                    message: None,
+                    field: None,
+                }],
+            }))
+        }
+        Err(BillingError::NotFound) => {
+            error!("Billing not found for {:?}", payer);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error updating billing".to_string(),
+                errors: vec![],
+            }));
+        }
+        Err(BillingError::Database(err)) => {
             error!("Error updating billing: {:?}", err);
             return Ok(build_err(ResponseBuilderError {
                 message: "Error updating billing".to_string(),
                 errors: vec![],
             }));
         }
-    }
+    };
 
     let prompt = get_summarize_prompt(to_summarize.to_owned(), kind);
 
@@ -185,17 +222,18 @@ pub async fn summarize_controller(
             error!("Error building request: {:?}", e);
             error!("Error after payment");
             error!("Account ID: {}", account.id);
+            error!("Request ID: {}", request_id);
+            error!("Organization ID: {:?}", form.organization_id);
             error!("Credits Deducted: {}", total_cost);
             error!("Remaining Credits: {}", billing_credits);
             error!("Suggested action: Refund the credits");
 
-            billing.credits += total_cost;
-            billing.updated_at = now;
-
-            match AccountMutationCore::update_account_billing_by_account_id(
+            match refund(
                 &state.databases.postgres_conn,
-                account.id,
-                billing.clone(),
+                payer,
+                total_cost,
+                &refund_movement,
+                now,
             )
             .await
             {
@@ -203,7 +241,7 @@ pub async fn summarize_controller(
                 Err(err) => {
                     error!("Error updating billing: {:?}", err);
                     return Ok(build_err(ResponseBuilderError {
-                        message: format!("Error refunding credits, contact support and give them this codee \"id={};gid={};at={};update_at={};total={}\", error message: {}", account.id, account.google_id, now, account.updated_at, total_cost, e.to_string()),
+                        message: format!("Error refunding credits, contact support and give them this codee \"id={};at={};update_at={};total={}\", error message: {}", account.id, now, account.updated_at, total_cost, e.to_string()),
                         errors: vec![],
                     }));
                 }
@@ -214,6 +252,7 @@ pub async fn summarize_controller(
                 errors: vec![ResponseObjectError {
                     error_id: Errors::ServiceUnavailable,
                     message: Some(e.to_string()),
+                    field: None,
                 }],
             }));
         }
@@ -232,17 +271,18 @@ pub async fn summarize_controller(
         error!("Error executing request: {:?}", err);
         error!("Error after payment");
         error!("Account ID: {}", account.id);
+        error!("Request ID: {}", request_id);
+        error!("Organization ID: {:?}", form.organization_id);
         error!("Credits Deducted: {}", total_cost);
         error!("Remaining Credits: {}", billing_credits);
         error!("Suggested action: Refund the credits");
 
-        billing.credits += total_cost;
-        billing.updated_at = now;
-
-        match AccountMutationCore::update_account_billing_by_account_id(
+        match refund(
             &state.databases.postgres_conn,
-            account.id,
-            billing.clone(),
+            payer,
+            total_cost,
+            &refund_movement,
+            now,
         )
         .await
         {
@@ -250,7 +290,7 @@ pub async fn summarize_controller(
             Err(err) => {
                 error!("Error updating billing: {:?}", err);
                 return Ok(build_err(ResponseBuilderError {
-                        message: format!("Error refunding credits, contact support and give them this codee \"id={};gid={};at={};update_at={};total={}\", error message: External Service Error", account.id, account.google_id, now, account.updated_at, total_cost),
+                        message: format!("Error refunding credits, contact support and give them this codee \"id={};at={};update_at={};total={}\", error message: External Service Error", account.id, now, account.updated_at, total_cost),
                         errors: vec![],
                     }));
             }
@@ -261,12 +301,32 @@ pub async fn summarize_controller(
             errors: vec![ResponseObjectError {
                 error_id: Errors::ServiceUnavailable,
                 message: Some("So sorry, appear there are a error with the API.".to_owned()),
+                field: None,
             }],
         }));
     }
 
     let message = message.lock().await.to_string();
 
+    if let Err(err) = ServiceUsageMutationCore::create_service_usage(
+        &state.databases.postgres_conn,
+        service_usage::Model {
+            id: request_id,
+            account_id: account.id,
+            organization_id: form.organization_id,
+            service: SUMMARIZE_SERVICE.to_string(),
+            credits: total_cost,
+            created_at: now,
+        },
+    )
+    .await
+    {
+        error!(
+            "Error recording service usage of account {}: {:?}",
+            account.id, err
+        );
+    }
+
     Ok(build_ok(ResponseBuilderOk {
         message: Some("OK".to_string()),
         data: Some(json!({
@@ -276,6 +336,7 @@ pub async fn summarize_controller(
             "operation_cost_in_credits": total_cost,
             "operation_cost_in_usd": total_cost as f64 * CREDIT_PRICE,
             "remaining_credits": billing_credits,
+            "organization_id": form.organization_id,
         })),
     }))
 }
@@ -0,0 +1,153 @@
+use crate::{extractors::AuthenticatedAccount, state::APIStateWrapper};
+use actix_web::{web, Responder, Result};
+use chrono::Utc;
+use grindless_core::{
+    entities::session_ops::{SessionMutationCore, SessionQueryCore},
+    response::response::{build_err, build_ok, ResponseBuilderError, ResponseBuilderOk},
+    ID,
+};
+use log::error;
+use serde_json::json;
+
+/// # Get My Sessions Controller
+///
+/// Lists the devices the account is logged in on.
+pub async fn get_my_sessions_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+    let claims = authenticated.claims;
+
+    let sessions = match SessionQueryCore::get_active_sessions_by_account_id(
+        &state.databases.postgres_conn,
+        claims.sub,
+    )
+    .await
+    {
+        Ok(s) => s,
+        Err(err) => {
+            error!("Error getting sessions {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error getting sessions".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Sessions found".to_string()),
+        data: Some(json!({
+            "sessions": sessions,
+            "current_session_id": claims.sid,
+        })),
+    }))
+}
+
+/// # Revoke My Session Controller
+///
+/// Logs out one device of the account.
+pub async fn revoke_my_session_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+    path: web::Path<ID>,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+    let claims = authenticated.claims;
+
+    let session_id = path.into_inner();
+    match SessionMutationCore::revoke_session(
+        &state.databases.postgres_conn,
+        claims.sub,
+        session_id,
+        Utc::now().timestamp(),
+    )
+    .await
+    {
+        Ok(true) => (),
+        Ok(false) => {
+            return Ok(build_err(ResponseBuilderError {
+                message: "Session not found".to_string(),
+                errors: vec![],
+            }))
+        }
+        Err(err) => {
+            error!("Error revoking session {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error revoking session".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Session revoked".to_string()),
+        data: Some(json!({ "session_id": session_id })),
+    }))
+}
+
+/// # Revoke All My Sessions Controller
+///
+/// Logs the account out everywhere, including the device making the request.
+pub async fn revoke_all_my_sessions_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+    let claims = authenticated.claims;
+
+    let revoked = match SessionMutationCore::revoke_all_sessions(
+        &state.databases.postgres_conn,
+        claims.sub,
+        Utc::now().timestamp(),
+    )
+    .await
+    {
+        Ok(r) => r,
+        Err(err) => {
+            error!("Error revoking sessions {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error revoking sessions".to_string(),
+                errors: vec![],
+            }));
+        }
+    };
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Logged out everywhere".to_string()),
+        data: Some(json!({ "revoked_sessions": revoked })),
+    }))
+}
+
+/// # Logout Controller
+///
+/// Revokes the session the access token belongs to.
+pub async fn logout_controller(
+    state: APIStateWrapper,
+    authenticated: AuthenticatedAccount,
+) -> Result<impl Responder> {
+    authenticated.require_session()?;
+    let claims = authenticated.claims;
+
+    if let Some(sid) = claims.sid {
+        if let Err(err) = SessionMutationCore::revoke_session(
+            &state.databases.postgres_conn,
+            claims.sub,
+            sid,
+            Utc::now().timestamp(),
+        )
+        .await
+        {
+            error!("Error revoking session {:?}", err);
+            return Ok(build_err(ResponseBuilderError {
+                message: "Error logging out".to_string(),
+                errors: vec![],
+            }));
+        }
+    }
+
+    Ok(build_ok(ResponseBuilderOk {
+        message: Some("Logged out".to_string()),
+        data: Some(json!({ "session_id": claims.sid })),
+    }))
+}
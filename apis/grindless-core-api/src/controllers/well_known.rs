@@ -0,0 +1,13 @@
+use crate::state::APIStateWrapper;
+use actix_web::{HttpResponse, Responder, Result};
+
+/// # JWKS Controller
+///
+/// Publishes the public half of every key in the key ring, so other services can verify
+/// Grindless tokens by their `kid`. Served as a bare JWK set, not wrapped in a response
+/// object, because that is what JWT libraries expect.
+pub async fn jwks_controller(state: APIStateWrapper) -> Result<impl Responder> {
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "public, max-age=300"))
+        .json(state.key_ring.jwks()))
+}
@@ -1,15 +1,57 @@
 use std::{env, path::Path};
 
+use crate::oauth::redirects::valid_redirect_pattern;
+
 #[derive(Clone, Debug)]
-pub struct GoogleOAuth {
+pub struct OAuthClient {
     pub client_id: String,
     pub client_secret: String,
     pub client_redirects: Vec<String>,
 }
 
+impl OAuthClient {
+    /// Used when the client doesn't say where it wants to be sent back to
+    pub fn default_redirect_uri(&self) -> &str {
+        &self.client_redirects[0]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OidcOAuth {
+    pub name: String,
+    pub issuer: String,
+    pub client: OAuthClient,
+    /// Whether the issuer's `email_verified` is believed. Off by default, an issuer that
+    /// vouches for addresses it doesn't own could take over accounts by email.
+    pub trust_email_verification: bool,
+}
+
 #[derive(Clone, Debug)]
 pub struct OAuth {
-    pub google: GoogleOAuth,
+    pub google: Option<OAuthClient>,
+    pub github: Option<OAuthClient>,
+    pub oidc: Vec<OidcOAuth>,
+    /// Where magic links may point to, magic links are off when empty
+    pub magic_link_redirects: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Mail {
+    pub transport: String,
+    /// Only needed when magic links or organization invitations are on
+    pub from: Option<String>,
+    pub smtp_url: Option<String>,
+    pub outbox_dir: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Tokens {
+    pub keys_dir: Option<String>,
+    pub signing_key_pem: Option<String>,
+    pub signing_key_id: Option<String>,
+    pub issuer: String,
+    pub audience: String,
+    pub leeway_seconds: Option<u64>,
 }
 
 #[derive(Clone, Debug)]
@@ -29,6 +71,8 @@ pub struct Llm {
     pub openai: OpenAI,
 }
 
+pub static DEFAULT_ACCOUNT_DELETION_GRACE_DAYS: i64 = 30;
+
 #[derive(Clone, Debug)]
 pub struct Enviroment {
     pub production: bool,
@@ -37,7 +81,15 @@ pub struct Enviroment {
     pub dev_port: u16,
     pub postgres_url: String,
     pub logger_level_filter: String,
+    /// Part of every ID this instance makes, each running instance needs its own
+    pub worker_id: u16,
+    /// Days a deleted account can still be restored before its data is purged
+    pub account_deletion_grace_days: i64,
+    /// Page organization invitations link to with their token, invitations are off when unset
+    pub organization_invite_url: Option<String>,
     pub oauth: OAuth,
+    pub mail: Mail,
+    pub tokens: Tokens,
     pub llm: Llm,
 }
 
@@ -51,10 +103,34 @@ pub fn load_enviroment_vars() -> Result<Enviroment, Box<dyn std::error::Error>>
     let env_path = Path::new(".env");
     dotenvy::from_path(env_path)?;
 
-    let client_redirects = dotenvy::var("GOOGLE_CLIENT_REDIRECTS")?;
-    let client_redirects = client_redirects.split(",").into_iter().map(|t| t.to_string()).collect::<Vec<String>>();
-    if client_redirects.len() != 2 {
-        panic!("Invalid GOOGLE_CLIENT_REDIRECTS format. Should be: http://localhost:3000,https://example.com");
+    let mut oidc = vec![];
+    if let Ok(names) = dotenvy::var("OIDC_PROVIDERS") {
+        for name in names.split(",").map(|n| n.trim()).filter(|n| !n.is_empty()) {
+            if ["google", "github", "email"].contains(&name.to_lowercase().as_str()) {
+                panic!("Invalid OIDC_PROVIDERS, {} is already a built in provider", name);
+            }
+
+            let prefix = format!("OIDC_{}", name.to_uppercase().replace("-", "_"));
+            if oidc.iter().any(|o: &OidcOAuth| {
+                format!("OIDC_{}", o.name.to_uppercase().replace("-", "_")) == prefix
+            }) {
+                panic!("Invalid OIDC_PROVIDERS, {} is listed twice", name);
+            }
+            let client = match load_oauth_client(&prefix)? {
+                Some(c) => c,
+                None => panic!("OIDC provider {} has no {}_CLIENT_ID", name, prefix),
+            };
+
+            oidc.push(OidcOAuth {
+                name: name.to_string(),
+                issuer: dotenvy::var(format!("{}_ISSUER", prefix))?,
+                client,
+                trust_email_verification: match dotenvy::var(format!("{}_TRUST_EMAIL_VERIFICATION", prefix)) {
+                    Ok(t) => t.parse()?,
+                    Err(_) => false,
+                },
+            });
+        }
     }
 
     let anthropic_api_keys = dotenvy::var("ANTHROPIC_API_KEYS")?;
@@ -69,6 +145,31 @@ pub fn load_enviroment_vars() -> Result<Enviroment, Box<dyn std::error::Error>>
         panic!("Invalid OPENAI_KEYS format. Should be: key1,key2");
     }
     
+    let magic_link_redirects = dotenvy::var("MAGIC_LINK_REDIRECTS")
+        .unwrap_or_default()
+        .split(",")
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<String>>();
+    if let Some(invalid) = magic_link_redirects.iter().find(|r| !valid_redirect_pattern(r)) {
+        panic!("Invalid redirect {} in MAGIC_LINK_REDIRECTS, only a leading *. in the host is allowed", invalid);
+    }
+    if magic_link_redirects.first().map(|r| r.contains('*')).unwrap_or(false) {
+        panic!("The first of MAGIC_LINK_REDIRECTS is the default one and can't be a pattern");
+    }
+
+    let mail_from = dotenvy::var("MAIL_FROM").ok().filter(|f| !f.is_empty());
+    let organization_invite_url = dotenvy::var("ORGANIZATION_INVITE_URL").ok().filter(|u| !u.is_empty());
+    if mail_from.is_none() && (!magic_link_redirects.is_empty() || organization_invite_url.is_some()) {
+        panic!("MAIL_FROM is needed to send magic links and organization invitations");
+    }
+
+    if dotenvy::var("TOKEN_KEYS_DIR").unwrap_or_default().is_empty()
+        && dotenvy::var("TOKEN_SIGNING_KEY_PEM").unwrap_or_default().is_empty()
+    {
+        panic!("No token keys configured. Set TOKEN_KEYS_DIR and/or TOKEN_SIGNING_KEY_PEM");
+    }
+
     let env = Enviroment {
         production: dotenvy::var("PRODUCTION")?.parse()?,
         host: dotenvy::var("HOST")?,
@@ -76,11 +177,40 @@ pub fn load_enviroment_vars() -> Result<Enviroment, Box<dyn std::error::Error>>
         dev_port: dotenvy::var("DEV_PORT")?.parse()?,
         postgres_url: dotenvy::var("POSTGRES_URL")?,
         logger_level_filter: dotenvy::var("LOGGER_LEVEL_FILTER")?,
+        worker_id: match dotenvy::var("WORKER_ID") {
+            Ok(w) => w.parse()?,
+            Err(_) => 0,
+        },
+        account_deletion_grace_days: match dotenvy::var("ACCOUNT_DELETION_GRACE_DAYS") {
+            Ok(d) => d.parse()?,
+            Err(_) => DEFAULT_ACCOUNT_DELETION_GRACE_DAYS,
+        },
+        organization_invite_url,
         oauth: OAuth {
-            google: GoogleOAuth {
-                client_id: dotenvy::var("GOOGLE_CLIENT_ID")?,
-                client_secret: dotenvy::var("GOOGLE_CLIENT_SECRET")?,
-                client_redirects,
+            google: load_oauth_client("GOOGLE")?,
+            github: load_oauth_client("GITHUB")?,
+            oidc,
+            magic_link_redirects,
+        },
+        mail: Mail {
+            transport: dotenvy::var("MAIL_TRANSPORT").unwrap_or("file".to_string()),
+            from: mail_from,
+            smtp_url: dotenvy::var("SMTP_URL").ok().filter(|u| !u.is_empty()),
+            outbox_dir: dotenvy::var("MAIL_OUTBOX_DIR").unwrap_or("outbox".to_string()),
+        },
+        tokens: Tokens {
+            keys_dir: dotenvy::var("TOKEN_KEYS_DIR").ok().filter(|d| !d.is_empty()),
+            // Escaped newlines are allowed so the key fits on one line
+            signing_key_pem: dotenvy::var("TOKEN_SIGNING_KEY_PEM")
+                .ok()
+                .filter(|k| !k.is_empty())
+                .map(|k| k.replace("\\n", "\n")),
+            signing_key_id: dotenvy::var("TOKEN_SIGNING_KEY_ID").ok().filter(|k| !k.is_empty()),
+            issuer: dotenvy::var("TOKEN_ISSUER")?,
+            audience: dotenvy::var("TOKEN_AUDIENCE")?,
+            leeway_seconds: match dotenvy::var("TOKEN_LEEWAY_SECONDS") {
+                Ok(l) => Some(l.parse()?),
+                Err(_) => None,
             },
         },
         llm: Llm {
@@ -94,4 +224,37 @@ pub fn load_enviroment_vars() -> Result<Enviroment, Box<dyn std::error::Error>>
         },
     };
     Ok(env)
-}
\ No newline at end of file
+}
+
+/// # Load OAuth Client
+///
+/// Reads `<PREFIX>_CLIENT_ID`, `<PREFIX>_CLIENT_SECRET` and `<PREFIX>_CLIENT_REDIRECTS`, the
+/// allowlist of redirect URIs. The provider is off when there is no client id.
+fn load_oauth_client(prefix: &str) -> Result<Option<OAuthClient>, Box<dyn std::error::Error>> {
+    let client_id = match dotenvy::var(format!("{}_CLIENT_ID", prefix)) {
+        Ok(id) if !id.is_empty() => id,
+        _ => return Ok(None),
+    };
+
+    let client_redirects = dotenvy::var(format!("{}_CLIENT_REDIRECTS", prefix))?;
+    let client_redirects = client_redirects
+        .split(",")
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<String>>();
+    if client_redirects.is_empty() {
+        panic!("Invalid {}_CLIENT_REDIRECTS format. Should be: https://example.com/callback,https://*.preview.example.com/callback", prefix);
+    }
+    if let Some(invalid) = client_redirects.iter().find(|r| !valid_redirect_pattern(r)) {
+        panic!("Invalid redirect {} in {}_CLIENT_REDIRECTS, only a leading *. in the host is allowed", invalid, prefix);
+    }
+    if client_redirects[0].contains('*') {
+        panic!("The first of {}_CLIENT_REDIRECTS is the default one and can't be a pattern", prefix);
+    }
+
+    Ok(Some(OAuthClient {
+        client_id,
+        client_secret: dotenvy::var(format!("{}_CLIENT_SECRET", prefix))?,
+        client_redirects,
+    }))
+}
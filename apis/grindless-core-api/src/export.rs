@@ -0,0 +1,161 @@
+use std::io::{Cursor, Write};
+
+use actix_web::{
+    http::header::{ContentDisposition, DispositionParam, DispositionType},
+    HttpResponse,
+};
+use chrono::Utc;
+use grindless_core::{
+    entities::{
+        account,
+        api_key_ops::ApiKeyQueryCore,
+        billing,
+        ledger_entry_ops::LedgerEntryQueryCore,
+        organization_ops::OrganizationQueryCore,
+        provider_identity_ops::ProviderIdentityQueryCore,
+        service_usage_ops::ServiceUsageQueryCore,
+        session_ops::SessionQueryCore,
+    },
+    ID,
+};
+use sea_orm::DbConn;
+use serde::Serialize;
+use serde_json::{json, Value};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// Put at the top of every archive, so whoever opens it knows what it holds
+static EXPORT_README: &str = "This archive holds everything Grindless stores about one account.
+
+Every table comes as JSON and as CSV, with one row per line in the CSV. Lists and objects
+inside a CSV cell are written as JSON. Times are Unix timestamps in seconds.
+
+account       the account itself
+billing       the credit balance and what was spent
+identities    the logins linked to the account (Google, GitHub, email...)
+sessions      every device that logged in, including logged out ones
+api_keys      the API keys, without their secrets
+organizations the organizations the account is in, with its role and spending
+usage         every paid request, and the organization that paid when it wasn't the account
+ledger        every change to the credit balance, with the balance right after it
+
+Texts sent to services are not stored, so there are none to export.
+";
+
+/// # Export Account
+///
+/// Builds a zip with everything stored for the account, as JSON and CSV files.
+pub async fn export_account(
+    db: &DbConn,
+    account: &account::Model,
+    billing: Option<&billing::Model>,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let identities = ProviderIdentityQueryCore::get_provider_identities_by_account_id(db, account.id).await?;
+    let sessions = SessionQueryCore::get_sessions_by_account_id(db, account.id).await?;
+    let api_keys = ApiKeyQueryCore::get_all_api_keys_by_account_id(db, account.id).await?;
+    let organizations = OrganizationQueryCore::get_organizations_by_account_id(db, account.id)
+        .await?
+        .into_iter()
+        .map(|(membership, organization)| {
+            json!({
+                "organization_id": organization.id,
+                "organization_name": organization.name,
+                "role": membership.role,
+                "spending_cap": membership.spending_cap,
+                "credits_spent": membership.credits_spent,
+                "joined_at": membership.created_at,
+            })
+        })
+        .collect::<Vec<Value>>();
+    let usage = ServiceUsageQueryCore::get_service_usages_by_account_id(db, account.id).await?;
+    let ledger = match billing {
+        Some(b) => LedgerEntryQueryCore::get_ledger_entries_by_billing_id(db, b.id, None).await?,
+        None => vec![],
+    };
+
+    let tables: Vec<(&str, Vec<Value>)> = vec![
+        ("account", rows(std::slice::from_ref(account))?),
+        ("billing", rows(billing.as_slice())?),
+        ("identities", rows(&identities)?),
+        ("sessions", rows(&sessions)?),
+        ("api_keys", rows(&api_keys)?),
+        ("organizations", organizations),
+        ("usage", rows(&usage)?),
+        ("ledger", rows(&ledger)?),
+    ];
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("README.txt", options)?;
+    zip.write_all(EXPORT_README.as_bytes())?;
+
+    zip.start_file("export.json", options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&json!({
+        "account_id": account.id,
+        "exported_at": Utc::now().timestamp(),
+    }))?)?;
+
+    for (name, table) in &tables {
+        zip.start_file(format!("{}.json", name), options)?;
+        zip.write_all(&serde_json::to_vec_pretty(table)?)?;
+
+        zip.start_file(format!("{}.csv", name), options)?;
+        zip.write_all(to_csv(table).as_bytes())?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// # Export Response
+///
+/// Sends the archive as a download named after the account.
+pub fn export_response(account_id: ID, archive: Vec<u8>) -> HttpResponse {
+    let file_name = format!("grindless-export-{}-{}.zip", account_id, Utc::now().format("%Y%m%d"));
+
+    HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        })
+        .body(archive)
+}
+
+fn rows<T: Serialize>(models: &[T]) -> Result<Vec<Value>, serde_json::Error> {
+    models.iter().map(serde_json::to_value).collect()
+}
+
+/// Columns are the keys of the first row, every row of a table has the same ones
+fn to_csv(rows: &[Value]) -> String {
+    let columns: Vec<&String> = match rows.first().and_then(Value::as_object) {
+        Some(first) => first.keys().collect(),
+        None => return String::new(),
+    };
+
+    let mut csv = columns.iter().map(|c| csv_field(c)).collect::<Vec<_>>().join(",");
+    csv.push_str("\r\n");
+
+    for row in rows {
+        let line = columns
+            .iter()
+            .map(|c| match row.get(c.as_str()) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => csv_field(s),
+                Some(v) => csv_field(&v.to_string()),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        csv.push_str(&line);
+        csv.push_str("\r\n");
+    }
+
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,253 @@
+use std::{future::Future, pin::Pin};
+
+use actix_web::{
+    dev::Payload, http::StatusCode, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+    ResponseError,
+};
+use chrono::{DateTime, Utc};
+use grindless_core::{
+    entities::{account, account_ops::AccountQueryCore, billing},
+    permissions::{has_permission, Permission},
+    response::{
+        errors::Errors,
+        response::{ResponseObject, ResponseObjectError, Success},
+    },
+    util::jwt::JWTClaims,
+    Timestamp,
+};
+use log::error;
+
+use crate::{state::APIStateWrapper, util::get_claims_from_header};
+
+/// # Authenticated Account
+///
+/// Extractor for endpoints that need a logged in user. Taking it as an argument validates
+/// the token and loads the account and its billing; the request is rejected before the
+/// handler runs if any of it fails. The result is cached on the request, so asking for it
+/// twice costs nothing.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedAccount {
+    pub claims: JWTClaims,
+    pub account: account::Model,
+    pub billing: billing::Model,
+}
+
+impl AuthenticatedAccount {
+    /// # Require
+    ///
+    /// Checks a permission against the flags of the loaded account, which are always fresh,
+    /// unlike the roles in the token that the `require` guard looks at.
+    pub fn require(&self, permission: Permission) -> Result<(), AuthenticationError> {
+        if has_permission(&self.account.flags, permission) {
+            Ok(())
+        } else {
+            Err(AuthenticationError::new(Errors::Forbidden, "Missing permission"))
+        }
+    }
+
+    /// # Require Scope
+    ///
+    /// Lets logged in users through, and API keys only if they were given `scope`.
+    pub fn require_scope(&self, scope: &str) -> Result<(), AuthenticationError> {
+        match &self.claims.scopes {
+            Some(scopes) if !scopes.iter().any(|s| s == scope) => Err(AuthenticationError::new(
+                Errors::Forbidden,
+                &format!("API key is missing the {} scope", scope),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// # Require Session
+    ///
+    /// For endpoints that manage the account's credentials, which API keys can't reach.
+    pub fn require_session(&self) -> Result<(), AuthenticationError> {
+        match self.claims.sid {
+            Some(_) => Ok(()),
+            None => Err(AuthenticationError::new(
+                Errors::Forbidden,
+                "Log in to use this endpoint, API keys can't",
+            )),
+        }
+    }
+}
+
+/// # Authentication Error
+///
+/// Why a request could not be authenticated. Rendered as a regular response object with
+/// the error in `errors`, and a 401/403/500 status.
+#[derive(Debug, Clone)]
+pub struct AuthenticationError {
+    pub error_id: Errors,
+    pub message: String,
+}
+
+impl AuthenticationError {
+    pub fn new(error_id: Errors, message: &str) -> Self {
+        AuthenticationError {
+            error_id,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for AuthenticationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl ResponseError for AuthenticationError {
+    fn status_code(&self) -> StatusCode {
+        match self.error_id {
+            Errors::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            Errors::Forbidden
+            | Errors::AccountDeleted
+            | Errors::AccountPendingDeletion
+            | Errors::AccountSuspended => StatusCode::FORBIDDEN,
+            Errors::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ResponseObject::<()> {
+            success: Success::Error,
+            message: Some(self.message.clone()),
+            data: None,
+            errors: Some(vec![ResponseObjectError {
+                error_id: self.error_id.clone(),
+                message: Some(self.error_id.to_string()),
+                field: None,
+            }]),
+        })
+    }
+}
+
+/// # Suspension Message
+///
+/// Tells a suspended user why, and until when.
+pub fn suspension_message(account: &account::Model) -> String {
+    let mut message = "Account suspended".to_string();
+    if let Some(reason) = &account.suspension_reason {
+        message.push_str(&format!(": {}", reason));
+    }
+    if let Some(until) = account.suspended_until.and_then(|u| DateTime::from_timestamp(u, 0)) {
+        message.push_str(&format!(" (until {})", until.to_rfc3339()));
+    }
+
+    message
+}
+
+/// Validates the token and loads the account, whether or not it is scheduled for deletion
+async fn authenticate(req: HttpRequest) -> Result<AuthenticatedAccount, AuthenticationError> {
+    if let Some(authenticated) = req.extensions().get::<AuthenticatedAccount>() {
+        return Ok(authenticated.clone());
+    }
+
+    let state = match req.app_data::<APIStateWrapper>() {
+        Some(s) => s.clone(),
+        None => {
+            error!("APIState is not registered");
+            return Err(AuthenticationError::new(
+                Errors::InternalServerError,
+                "Error authenticating request",
+            ));
+        }
+    };
+
+    let claims = get_claims_from_header(&req, &state).await?;
+
+    let account_and_billing = match AccountQueryCore::get_account_by_id_with_billing(
+        &state.databases.postgres_conn,
+        claims.sub,
+    )
+    .await
+    {
+        Ok(a) => a,
+        Err(err) => {
+            error!("Error getting account {:?}", err);
+            return Err(AuthenticationError::new(
+                Errors::InternalServerError,
+                "Error getting account",
+            ));
+        }
+    };
+
+    let (account, billing) = match account_and_billing {
+        Some(a) => a,
+        None => {
+            return Err(AuthenticationError::new(
+                Errors::Unauthorized,
+                "Account not found",
+            ))
+        }
+    };
+
+    if account.is_suspended(Utc::now().timestamp() as Timestamp) {
+        return Err(AuthenticationError::new(
+            Errors::AccountSuspended,
+            &suspension_message(&account),
+        ));
+    }
+
+    let billing = match billing {
+        Some(b) => b,
+        None => {
+            error!("Account {} has no billing", account.id);
+            return Err(AuthenticationError::new(
+                Errors::InternalServerError,
+                "Billing not found",
+            ));
+        }
+    };
+
+    let authenticated = AuthenticatedAccount {
+        claims,
+        account,
+        billing,
+    };
+
+    req.extensions_mut().insert(authenticated.clone());
+    Ok(authenticated)
+}
+
+impl FromRequest for AuthenticatedAccount {
+    type Error = AuthenticationError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move {
+            let authenticated = authenticate(req).await?;
+
+            if authenticated.account.deletion_requested_at.is_some() {
+                return Err(AuthenticationError::new(
+                    Errors::AccountPendingDeletion,
+                    "Account is scheduled for deletion, restore it to keep using it",
+                ));
+            }
+
+            Ok(authenticated)
+        })
+    }
+}
+
+/// # Restorable Account
+///
+/// Like `AuthenticatedAccount`, but also lets through accounts scheduled for deletion, for
+/// the few endpoints they may still use to come back.
+#[derive(Debug, Clone)]
+pub struct RestorableAccount(pub AuthenticatedAccount);
+
+impl FromRequest for RestorableAccount {
+    type Error = AuthenticationError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let req = req.clone();
+
+        Box::pin(async move { Ok(RestorableAccount(authenticate(req).await?)) })
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+};
+
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    Error,
+};
+use grindless_core::{
+    permissions::{has_permission, Permission},
+    response::errors::Errors,
+};
+use log::{error, warn};
+
+use crate::{extractors::AuthenticationError, state::APIStateWrapper, util::get_claims_from_header};
+
+/// # Require
+///
+/// Middleware that only lets through requests whose access token carries a role granting
+/// `permission`. Roles come from the token, so no account is loaded; a role that was taken
+/// away lasts until the access token expires.
+///
+/// ```ignore
+/// web::scope("/admin").wrap(require(Permission::ViewAccounts))
+/// ```
+pub fn require(permission: Permission) -> RequirePermission {
+    RequirePermission { permission }
+}
+
+#[derive(Clone)]
+pub struct RequirePermission {
+    permission: Permission,
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequirePermission
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequirePermissionMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequirePermissionMiddleware {
+            service: Rc::new(service),
+            permission: self.permission,
+        }))
+    }
+}
+
+pub struct RequirePermissionMiddleware<S> {
+    service: Rc<S>,
+    permission: Permission,
+}
+
+impl<S, B> Service<ServiceRequest> for RequirePermissionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let permission = self.permission;
+
+        Box::pin(async move {
+            let state = match req.app_data::<APIStateWrapper>() {
+                Some(s) => s.clone(),
+                None => {
+                    error!("APIState is not registered");
+                    return Err(AuthenticationError::new(
+                        Errors::InternalServerError,
+                        "Error authenticating request",
+                    )
+                    .into());
+                }
+            };
+
+            let claims = get_claims_from_header(req.request(), &state).await?;
+            if !has_permission(&claims.roles, permission) {
+                warn!(
+                    "Account {} tried {} without {:?}",
+                    claims.sub,
+                    req.path(),
+                    permission
+                );
+
+                return Err(AuthenticationError::new(Errors::Forbidden, "Missing permission").into());
+            }
+
+            service.call(req).await
+        })
+    }
+}
@@ -0,0 +1,86 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::Utc;
+use grindless_core::{
+    entities::{
+        account_ops::{AccountMutationCore, AccountQueryCore},
+        ledger_entry_ops::LedgerEntryQueryCore,
+    },
+    Timestamp,
+};
+use log::{error, info};
+use sea_orm::DatabaseConnection;
+
+/// How often accounts past their deletion grace period or suspension end are looked for
+pub static ACCOUNT_JOBS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Accounts purged per run, the rest wait for the next one
+pub static ACCOUNT_PURGE_BATCH: u64 = 100;
+/// Mismatched billings logged per run
+pub static LEDGER_MISMATCHES_LOGGED: u64 = 100;
+
+/// # Spawn Account Jobs
+///
+/// Runs in the background for as long as the server does, purging the personal data of
+/// accounts deleted more than `grace_days` ago, clearing suspensions that ran out and
+/// checking that every balance matches its ledger.
+pub fn spawn_account_jobs(postgres_conn: Arc<DatabaseConnection>, grace_days: i64) {
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(ACCOUNT_JOBS_INTERVAL);
+        loop {
+            interval.tick().await;
+            purge_deleted_accounts(&postgres_conn, grace_days).await;
+            lift_expired_suspensions(&postgres_conn).await;
+            reconcile_ledger(&postgres_conn).await;
+        }
+    });
+}
+
+async fn lift_expired_suspensions(postgres_conn: &DatabaseConnection) {
+    match AccountMutationCore::lift_expired_suspensions(postgres_conn, Utc::now().timestamp() as Timestamp).await {
+        Ok(0) => (),
+        Ok(lifted) => info!("Lifted {} expired suspensions", lifted),
+        Err(err) => error!("Error lifting expired suspensions {:?}", err),
+    }
+}
+
+/// A mismatch means a balance changed without a ledger entry, which needs a person to look
+/// at it, so nothing is corrected here
+async fn reconcile_ledger(postgres_conn: &DatabaseConnection) {
+    match LedgerEntryQueryCore::get_ledger_mismatches(postgres_conn, LEDGER_MISMATCHES_LOGGED).await {
+        Ok(mismatches) => {
+            for m in mismatches {
+                error!(
+                    "Billing {} has {} credits but its ledger adds up to {}",
+                    m.billing_id, m.credits, m.ledger_total
+                );
+            }
+        }
+        Err(err) => error!("Error reconciling the ledger {:?}", err),
+    }
+}
+
+async fn purge_deleted_accounts(postgres_conn: &DatabaseConnection, grace_days: i64) {
+    let now = Utc::now();
+    let requested_before = (now - chrono::Duration::days(grace_days)).timestamp() as Timestamp;
+
+    let accounts = match AccountQueryCore::get_accounts_due_for_purge(
+        postgres_conn,
+        requested_before,
+        ACCOUNT_PURGE_BATCH,
+    )
+    .await
+    {
+        Ok(a) => a,
+        Err(err) => {
+            error!("Error getting accounts to purge {:?}", err);
+            return;
+        }
+    };
+
+    for account in accounts {
+        match AccountMutationCore::purge_account(postgres_conn, &account, now.timestamp() as Timestamp).await {
+            Ok(_) => info!("Purged deleted account {}", account.id),
+            Err(err) => error!("Error purging account {} {:?}", account.id, err),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use grindless_core::ID;
+use log::error;
+use nervio_limiter::limiter::{BucketConfig, LimitEntityType, Limiter};
+use tokio::sync::Mutex;
+
+use crate::tokens::{DEFAULT_MAX_REQUESTS_PER_HOUR, PARTNER_MAX_REQUESTS_PER_HOUR};
+
+static ACCOUNT_BUCKET_CYCLE: Duration = Duration::from_secs(60 * 60);
+
+// One account bucket per plan size, an account is counted in the one its token names
+static ACCOUNT_BUCKET_SIZES: [u64; 2] = [DEFAULT_MAX_REQUESTS_PER_HOUR, PARTNER_MAX_REQUESTS_PER_HOUR];
+
+/// # Rate Limit Exceeded
+///
+/// Returned when the account used up its bucket.
+#[derive(Debug, Clone)]
+pub struct RateLimitExceeded {
+    pub limit: u64,
+}
+
+/// # Account Bucket Name
+///
+/// The nervio bucket counting accounts allowed `max_requests_per_hour`.
+pub fn account_bucket_name(max_requests_per_hour: u64) -> String {
+    format!("account_{}", max_requests_per_hour)
+}
+
+/// # Add Account Buckets
+///
+/// Registers the per-account buckets, keyed by the `sub` of the token, on the same limiter
+/// (and so the same storage) as the `global_ip` bucket. The IP bucket stops floods before
+/// anything is decoded, these stop a single account spread over many IPs, and let accounts
+/// behind a shared NAT each have their own allowance.
+pub async fn add_account_buckets(limiter: &Mutex<Limiter>) {
+    let mut limiter = limiter.lock().await;
+    for max_requests_per_cycle in ACCOUNT_BUCKET_SIZES {
+        limiter
+            .add_bucket(BucketConfig {
+                name: account_bucket_name(max_requests_per_cycle),
+                limit_by: LimitEntityType::Account,
+                max_requests_per_cycle,
+                cycle_duration: ACCOUNT_BUCKET_CYCLE,
+            })
+            .await;
+    }
+}
+
+/// # Hit Account Bucket
+///
+/// Counts one request for the account in the bucket sized `max_requests_per_hour`. Sizes
+/// without a bucket fall back to the default plan. If the limiter storage fails the request
+/// is let through, the IP bucket still applies.
+///
+/// For access tokens the size is the one in the token, so an account that loses its plan
+/// keeps the bigger bucket until the token expires, at most `ACCESS_TOKEN_TTL_MINUTES`.
+/// API keys are sized from the account on every request.
+pub async fn hit_account_bucket(
+    limiter: &Mutex<Limiter>,
+    account_id: ID,
+    max_requests_per_hour: u64,
+) -> Result<(), RateLimitExceeded> {
+    let limit = if ACCOUNT_BUCKET_SIZES.contains(&max_requests_per_hour) {
+        max_requests_per_hour
+    } else {
+        DEFAULT_MAX_REQUESTS_PER_HOUR
+    };
+
+    let allowed = limiter
+        .lock()
+        .await
+        .limit_entity(&account_bucket_name(limit), &account_id.to_string())
+        .await;
+
+    match allowed {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(RateLimitExceeded { limit }),
+        Err(err) => {
+            error!("Error counting request for account {} {:?}", account_id, err);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use grindless_core::util::snowflake::new_id;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    Message,
+};
+use log::info;
+
+use super::{Email, Mailer};
+
+/// # File Mailer
+///
+/// Writes every mail to `<outbox_dir>/<time>-<id>.eml` instead of sending it, so flows like
+/// the magic link can be followed locally without a mail server.
+pub struct FileMailer {
+    outbox_dir: PathBuf,
+    from: Mailbox,
+}
+
+impl FileMailer {
+    pub fn new(outbox_dir: &str, from: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(outbox_dir)?;
+
+        Ok(FileMailer {
+            outbox_dir: PathBuf::from(outbox_dir),
+            from: from.parse()?,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: Email) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse()?)
+            .subject(email.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(email.body)?;
+
+        let path = self.outbox_dir.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            new_id()
+        ));
+        std::fs::write(&path, message.formatted())?;
+
+        info!("Mail to {} written to {}", email.to, path.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+
+use crate::env::Mail;
+
+pub mod file;
+pub mod smtp;
+
+/// # Email
+///
+/// A plain text mail, which is all the API sends for now.
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// # Mailer
+///
+/// Where mail goes out through. SMTP in production, files on disk when working locally.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// # Disabled Mailer
+///
+/// Stands in when `MAIL_FROM` is not set. Nothing that sends mail is on then, so reaching
+/// it is a bug.
+pub struct DisabledMailer;
+
+#[async_trait]
+impl Mailer for DisabledMailer {
+    async fn send(&self, _email: Email) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Err("mail is off, set MAIL_FROM".into())
+    }
+}
+
+/// # Mailer From Env
+///
+/// The transport picked with `MAIL_TRANSPORT`, or none without `MAIL_FROM`.
+pub fn mailer_from_env(mail: &Mail) -> Result<Box<dyn Mailer>, Box<dyn std::error::Error>> {
+    let from = match &mail.from {
+        Some(f) => f,
+        None => return Ok(Box::new(DisabledMailer)),
+    };
+
+    match mail.transport.as_str() {
+        "smtp" => match &mail.smtp_url {
+            Some(url) => Ok(Box::new(smtp::SmtpMailer::new(url, from)?)),
+            None => Err("MAIL_TRANSPORT=smtp needs SMTP_URL".into()),
+        },
+        "file" => Ok(Box::new(file::FileMailer::new(&mail.outbox_dir, from)?)),
+        other => Err(format!("unknown MAIL_TRANSPORT {}, use smtp or file", other).into()),
+    }
+}
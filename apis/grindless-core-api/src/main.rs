@@ -1,17 +1,35 @@
+use cli::run_command;
 use env::load_enviroment_vars;
-use grindless_core::{db::sql::establish_postgres_connection, util::log::set_up_logger};
+use grindless_core::{
+    db::sql::establish_postgres_connection,
+    util::{log::set_up_logger, snowflake::init_id_generator},
+};
 use server::init_server;
 
+pub mod cli;
 pub mod env;
+pub mod export;
+pub mod extractors;
+pub mod guards;
+pub mod jobs;
+pub mod limits;
+pub mod mail;
+pub mod oauth;
 pub mod server;
 pub mod state;
 pub mod routers;
 pub mod controllers;
 pub mod services;
+pub mod tokens;
 pub mod util;
 
 #[actix_web::main]
 pub async fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    if run_command(&args) {
+        return;
+    }
+
     let enviroment = match load_enviroment_vars() {
         Ok(env) => env,
         Err(e) => {
@@ -34,6 +52,13 @@ pub async fn main() {
         }
     }
 
+    match init_id_generator(enviroment.worker_id) {
+        Ok(_) => log::info!("Generating IDs as worker {}", enviroment.worker_id),
+        Err(e) => {
+            panic!("Error setting up the id generator: {}", e);
+        }
+    }
+
     let postgres_conn = match establish_postgres_connection(&enviroment.postgres_url).await {
         Ok(conn) => conn,
         Err(e) => {
@@ -0,0 +1,124 @@
+use async_trait::async_trait;
+use grindless_core::response::errors::Errors;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+
+use crate::env::OAuthClient;
+
+use super::{exchange_code, OAuthProfile, OAuthProvider, OAuthTokens};
+
+pub const GITHUB_AUTHORIZE_URL: &str = "https://github.com/login/oauth/authorize";
+pub const GITHUB_GET_TOKENS_URL: &str = "https://github.com/login/oauth/access_token";
+pub const GITHUB_GET_PROFILE_URL: &str = "https://api.github.com/user";
+pub const GITHUB_GET_EMAILS_URL: &str = "https://api.github.com/user/emails";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubUserResult {
+    pub id: u64,
+    pub login: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubEmailResult {
+    pub email: String,
+    pub primary: bool,
+    pub verified: bool,
+}
+
+pub struct GitHubProvider {
+    http: Client,
+    client: OAuthClient,
+}
+
+impl GitHubProvider {
+    pub fn new(http: Client, client: OAuthClient) -> Self {
+        GitHubProvider { http, client }
+    }
+
+    async fn get<T: for<'de> Deserialize<'de>>(&self, url: &str, access_token: &str) -> Result<T, Errors> {
+        let response = self
+            .http
+            .get(url)
+            .bearer_auth(access_token)
+            .header("Accept", "application/vnd.github+json")
+            .send()
+            .await
+            .map_err(|_| Errors::BadRequest)?;
+
+        if !response.status().is_success() {
+            return Err(Errors::BadRequest);
+        }
+
+        response.json::<T>().await.map_err(|_| Errors::BadRequest)
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for GitHubProvider {
+    fn name(&self) -> &str {
+        "github"
+    }
+
+    fn client(&self) -> &OAuthClient {
+        &self.client
+    }
+
+    fn authorize_url(&self, redirect_uri: &str, state: &str, code_challenge: &str) -> String {
+        let mut url = Url::parse(GITHUB_AUTHORIZE_URL).unwrap();
+        url.query_pairs_mut()
+            .append_pair("client_id", &self.client.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", "read:user user:email")
+            .append_pair("state", state)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        url.to_string()
+    }
+
+    async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<OAuthTokens, Errors> {
+        exchange_code(
+            &self.http,
+            GITHUB_GET_TOKENS_URL,
+            &self.client,
+            code,
+            redirect_uri,
+            code_verifier,
+        )
+        .await
+    }
+
+    async fn fetch_profile(&self, tokens: &OAuthTokens) -> Result<OAuthProfile, Errors> {
+        let user = self
+            .get::<GitHubUserResult>(GITHUB_GET_PROFILE_URL, &tokens.access_token)
+            .await?;
+
+        // The public profile email may be unset or unverified, the emails endpoint says which
+        // one is primary and verified
+        let emails = self
+            .get::<Vec<GitHubEmailResult>>(GITHUB_GET_EMAILS_URL, &tokens.access_token)
+            .await
+            .unwrap_or_default();
+
+        let (email, email_verified) = match emails.iter().find(|e| e.primary) {
+            Some(e) => (Some(e.email.clone()), e.verified),
+            None => (user.email.clone(), false),
+        };
+
+        Ok(OAuthProfile {
+            subject: user.id.to_string(),
+            email,
+            email_verified,
+            name: user.name.filter(|n| !n.is_empty()).unwrap_or(user.login),
+            avatar: user.avatar_url,
+        })
+    }
+}
@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use grindless_core::response::errors::Errors;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+
+use crate::env::OAuthClient;
+
+use super::{exchange_code, OAuthProfile, OAuthProvider, OAuthTokens};
+
+pub const GOOGLE_AUTHORIZE_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+pub const GOOGLE_GET_TOKENS_URL: &str = "https://oauth2.googleapis.com/token";
+pub const GOOGLE_GET_PROFILE_URL: &str = "https://www.googleapis.com/oauth2/v1/userinfo";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoogleUserResult {
+    pub id: String,
+    pub email: String,
+    pub verified_email: bool,
+    pub name: String,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub picture: Option<String>,
+    pub locale: Option<String>,
+}
+
+pub struct GoogleProvider {
+    http: Client,
+    client: OAuthClient,
+}
+
+impl GoogleProvider {
+    pub fn new(http: Client, client: OAuthClient) -> Self {
+        GoogleProvider { http, client }
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for GoogleProvider {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    fn client(&self) -> &OAuthClient {
+        &self.client
+    }
+
+    fn authorize_url(&self, redirect_uri: &str, state: &str, code_challenge: &str) -> String {
+        let mut url = Url::parse(GOOGLE_AUTHORIZE_URL).unwrap();
+        url.query_pairs_mut()
+            .append_pair("client_id", &self.client.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("response_type", "code")
+            .append_pair("scope", "profile email")
+            .append_pair("prompt", "consent")
+            .append_pair("state", state)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        url.to_string()
+    }
+
+    async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<OAuthTokens, Errors> {
+        exchange_code(
+            &self.http,
+            GOOGLE_GET_TOKENS_URL,
+            &self.client,
+            code,
+            redirect_uri,
+            code_verifier,
+        )
+        .await
+    }
+
+    async fn fetch_profile(&self, tokens: &OAuthTokens) -> Result<OAuthProfile, Errors> {
+        let mut url = Url::parse(GOOGLE_GET_PROFILE_URL).unwrap();
+        url.query_pairs_mut().append_pair("alt", "json");
+
+        let response = self
+            .http
+            .get(url)
+            .bearer_auth(&tokens.access_token)
+            .send()
+            .await
+            .map_err(|_| Errors::BadRequest)?;
+
+        if !response.status().is_success() {
+            return Err(Errors::BadRequest);
+        }
+
+        let user_info = response
+            .json::<GoogleUserResult>()
+            .await
+            .map_err(|_| Errors::BadRequest)?;
+
+        Ok(OAuthProfile {
+            subject: user_info.id,
+            email: Some(user_info.email),
+            email_verified: user_info.verified_email,
+            name: user_info.name,
+            avatar: user_info.picture,
+        })
+    }
+}
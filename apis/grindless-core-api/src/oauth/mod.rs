@@ -0,0 +1,217 @@
+use async_trait::async_trait;
+use grindless_core::{
+    response::{
+        errors::Errors,
+        response::{ResponseBuilderError, ResponseObjectError},
+    },
+    ID,
+};
+use log::{error, info};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    env::{OAuth, OAuthClient},
+    state::APIStateWrapper,
+    tokens::finish_oauth_login,
+};
+
+pub mod github;
+pub mod google;
+pub mod oidc;
+pub mod redirects;
+
+/// # OAuth Tokens
+///
+/// What a provider answers when a code is exchanged. Only the access token is used, to
+/// fetch the profile.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthTokens {
+    pub access_token: String,
+    pub token_type: Option<String>,
+    pub expires_in: Option<i64>,
+    pub scope: Option<String>,
+    pub id_token: Option<String>,
+}
+
+/// # OAuth Profile
+///
+/// The user as the provider sees them. `subject` is the provider's stable user id, emails
+/// can change and are only trusted when `email_verified` is set.
+#[derive(Debug, Clone, Serialize)]
+pub struct OAuthProfile {
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub name: String,
+    pub avatar: Option<String>,
+}
+
+/// # OAuth Provider
+///
+/// Something accounts can log in with. The flow is always the same: send the user to
+/// `authorize_url`, get a code back on the redirect, `exchange_code` it and `fetch_profile`.
+/// Every provider must send the PKCE `S256` challenge and verifier along.
+#[async_trait]
+pub trait OAuthProvider: Send + Sync {
+    /// Name used in routes and stored with the identity, never change it once in use
+    fn name(&self) -> &str;
+
+    fn client(&self) -> &OAuthClient;
+
+    fn authorize_url(&self, redirect_uri: &str, state: &str, code_challenge: &str) -> String;
+
+    async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<OAuthTokens, Errors>;
+
+    async fn fetch_profile(&self, tokens: &OAuthTokens) -> Result<OAuthProfile, Errors>;
+}
+
+/// # Exchange Code
+///
+/// Standard `authorization_code` grant against `token_url`, which every provider here
+/// supports.
+pub async fn exchange_code(
+    http: &Client,
+    token_url: &str,
+    client: &OAuthClient,
+    code: &str,
+    redirect_uri: &str,
+    code_verifier: &str,
+) -> Result<OAuthTokens, Errors> {
+    let params = [
+        ("grant_type", "authorization_code"),
+        ("redirect_uri", redirect_uri),
+        ("client_id", client.client_id.as_str()),
+        ("code", code),
+        ("client_secret", client.client_secret.as_str()),
+        ("code_verifier", code_verifier),
+    ];
+
+    let response = http
+        .post(token_url)
+        .header("Accept", "application/json")
+        .form(&params)
+        .send()
+        .await
+        .map_err(|_| Errors::BadRequest)?;
+
+    if !response.status().is_success() {
+        return Err(Errors::BadRequest);
+    }
+
+    let tokens = response
+        .json::<OAuthTokens>()
+        .await
+        .map_err(|_| Errors::BadRequest)?;
+
+    if tokens.access_token.is_empty() {
+        return Err(Errors::BadRequest);
+    }
+
+    Ok(tokens)
+}
+
+/// # Complete OAuth Login
+///
+/// Checks the `state`, exchanges the code and fetches the profile. `link_account_id` must
+/// match the one the login was started with, so a login can't be used to link and the
+/// other way around.
+pub async fn complete_oauth_login(
+    state: &APIStateWrapper,
+    provider: &dyn OAuthProvider,
+    code: &str,
+    signed_state: &str,
+    link_account_id: Option<ID>,
+) -> Result<OAuthProfile, Errors> {
+    let login = finish_oauth_login(state, provider.name(), signed_state).await?;
+    if login.link_account_id != link_account_id {
+        return Err(Errors::InvalidOAuthState);
+    }
+
+    // Must be the same redirect_uri the user was sent to the provider with
+    let tokens = provider
+        .exchange_code(code, &login.redirect_uri, &login.code_verifier)
+        .await?;
+
+    let profile = provider.fetch_profile(&tokens).await?;
+    if profile.subject.is_empty() {
+        return Err(Errors::BadRequest);
+    }
+
+    Ok(profile)
+}
+
+/// # OAuth Error
+///
+/// What to answer when [`complete_oauth_login`] fails.
+pub fn oauth_error(provider: &str, err: Errors) -> ResponseBuilderError {
+    let message = match err {
+        Errors::InvalidOAuthState => "Invalid login state, start again".to_string(),
+        _ => format!("Error logging in with {}", provider),
+    };
+
+    ResponseBuilderError {
+        message,
+        errors: vec![ResponseObjectError {
+            message: Some(err.to_string()),
+            error_id: err,
+            field: None,
+        }],
+    }
+}
+
+/// # OAuth Providers
+///
+/// Every provider configured in the enviroment.
+pub struct OAuthProviders {
+    providers: Vec<Box<dyn OAuthProvider>>,
+}
+
+impl OAuthProviders {
+    /// # From Env
+    ///
+    /// Sets up the configured providers. OIDC issuers are discovered here, so one that is
+    /// down keeps the server from starting instead of breaking logins later.
+    pub async fn from_env(oauth: &OAuth) -> Result<Self, Box<dyn std::error::Error>> {
+        let http = Client::builder().user_agent("grindless").build()?;
+        let mut providers: Vec<Box<dyn OAuthProvider>> = vec![];
+
+        if let Some(client) = &oauth.google {
+            providers.push(Box::new(google::GoogleProvider::new(http.clone(), client.clone())));
+        }
+
+        if let Some(client) = &oauth.github {
+            providers.push(Box::new(github::GitHubProvider::new(http.clone(), client.clone())));
+        }
+
+        for config in &oauth.oidc {
+            match oidc::OidcProvider::discover(http.clone(), config.clone()).await {
+                Ok(p) => providers.push(Box::new(p)),
+                Err(e) => {
+                    error!("Error discovering OIDC provider {}: {:?}", config.name, e);
+                    return Err(e);
+                }
+            }
+        }
+
+        info!(
+            "OAuth providers: {}",
+            providers.iter().map(|p| p.name()).collect::<Vec<&str>>().join(", ")
+        );
+
+        Ok(OAuthProviders { providers })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn OAuthProvider> {
+        self.providers.iter().find(|p| p.name() == name).map(|p| p.as_ref())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.providers.iter().map(|p| p.name()).collect()
+    }
+}
@@ -0,0 +1,148 @@
+use async_trait::async_trait;
+use grindless_core::response::errors::Errors;
+use log::info;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+
+use crate::env::{OAuthClient, OidcOAuth};
+
+use super::{exchange_code, OAuthProfile, OAuthProvider, OAuthTokens};
+
+/// # OIDC Discovery
+///
+/// The parts of `/.well-known/openid-configuration` the login flow needs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcDiscovery {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcUserInfo {
+    pub sub: String,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+    pub picture: Option<String>,
+}
+
+/// # OIDC Provider
+///
+/// Any OpenID Connect issuer, set up from its discovery document.
+pub struct OidcProvider {
+    http: Client,
+    config: OidcOAuth,
+    discovery: OidcDiscovery,
+}
+
+impl OidcProvider {
+    pub async fn discover(http: Client, config: OidcOAuth) -> Result<Self, Box<dyn std::error::Error>> {
+        let issuer = config.issuer.trim_end_matches('/');
+        let discovery = http
+            .get(format!("{}/.well-known/openid-configuration", issuer))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<OidcDiscovery>()
+            .await?;
+
+        // Per the spec the document must be about the issuer it was fetched from
+        if discovery.issuer.trim_end_matches('/') != issuer {
+            return Err(format!(
+                "discovery document of {} is for issuer {}",
+                issuer, discovery.issuer
+            )
+            .into());
+        }
+
+        info!("Discovered OIDC provider {} at {}", config.name, issuer);
+        Ok(OidcProvider {
+            http,
+            config,
+            discovery,
+        })
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for OidcProvider {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn client(&self) -> &OAuthClient {
+        &self.config.client
+    }
+
+    fn authorize_url(&self, redirect_uri: &str, state: &str, code_challenge: &str) -> String {
+        let mut url = match Url::parse(&self.discovery.authorization_endpoint) {
+            Ok(u) => u,
+            Err(_) => return self.discovery.authorization_endpoint.clone(),
+        };
+        url.query_pairs_mut()
+            .append_pair("client_id", &self.config.client.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("response_type", "code")
+            .append_pair("scope", "openid profile email")
+            .append_pair("state", state)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", "S256");
+
+        url.to_string()
+    }
+
+    async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+    ) -> Result<OAuthTokens, Errors> {
+        exchange_code(
+            &self.http,
+            &self.discovery.token_endpoint,
+            &self.config.client,
+            code,
+            redirect_uri,
+            code_verifier,
+        )
+        .await
+    }
+
+    async fn fetch_profile(&self, tokens: &OAuthTokens) -> Result<OAuthProfile, Errors> {
+        let response = self
+            .http
+            .get(&self.discovery.userinfo_endpoint)
+            .bearer_auth(&tokens.access_token)
+            .send()
+            .await
+            .map_err(|_| Errors::BadRequest)?;
+
+        if !response.status().is_success() {
+            return Err(Errors::BadRequest);
+        }
+
+        let user_info = response
+            .json::<OidcUserInfo>()
+            .await
+            .map_err(|_| Errors::BadRequest)?;
+
+        let name = user_info
+            .name
+            .or(user_info.preferred_username)
+            .or(user_info.email.clone())
+            .unwrap_or_else(|| user_info.sub.clone());
+
+        // Untrusted issuers' emails are kept, but never match or set an account's email
+        Ok(OAuthProfile {
+            subject: user_info.sub,
+            email: user_info.email,
+            email_verified: self.config.trust_email_verification && user_info.email_verified,
+            name,
+            avatar: user_info.picture,
+        })
+    }
+}
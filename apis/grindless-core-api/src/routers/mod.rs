@@ -1,22 +1,110 @@
 use actix_web::{web, Scope};
+use grindless_core::permissions::Permission;
 
-use crate::controllers::{account::get_me_controller, oauth::access_google_controller, services::summarize::summarize_controller};
+use crate::controllers::{
+    account::{
+        delete_me_controller, export_me_controller, get_me_controller, patch_me_controller,
+        restore_me_controller, update_locked_fields_controller,
+    },
+    admin::{
+        adjust_credits_controller, export_account_controller, get_account_actions_controller,
+        get_account_controller, get_account_ledger_controller, restore_account_controller,
+        search_accounts_controller, set_account_flags_controller, suspend_account_controller,
+    },
+    api_key::{
+        create_api_key_controller, get_my_api_keys_controller, rename_api_key_controller,
+        revoke_api_key_controller,
+    },
+    feature_flag::{
+        delete_feature_flag_controller, get_feature_flags_controller, save_feature_flag_controller,
+    },
+    identity::{
+        begin_link_identity_controller, get_my_identities_controller, link_identity_controller,
+        unlink_identity_controller,
+    },
+    ledger::get_my_ledger_controller,
+    magic_link::{request_magic_link_controller, verify_magic_link_controller},
+    oauth::{
+        access_provider_controller, begin_login_controller, get_providers_controller,
+        refresh_controller,
+    },
+    organization::{
+        accept_invitation_controller, create_organization_controller, get_invitations_controller,
+        get_my_organizations_controller, get_organization_controller,
+        get_organization_ledger_controller, invite_member_controller, remove_member_controller,
+        revoke_invitation_controller, set_member_role_controller, set_spending_cap_controller,
+        transfer_credits_controller,
+    },
+    services::summarize::summarize_controller,
+    session::{
+        get_my_sessions_controller, logout_controller, revoke_all_my_sessions_controller,
+        revoke_my_session_controller,
+    },
+    well_known::jwks_controller,
+};
+use crate::guards::require;
 
 pub fn build_api_router() -> Scope {
     web::scope("/api")
         .service(build_oauth_router())
         .service(build_account_router())
+        .service(build_organization_router())
         .service(build_services_router())
+        .service(build_admin_router())
+}
+
+pub fn build_well_known_router() -> Scope {
+    web::scope("/.well-known")
+        .route("/jwks.json", web::get().to(jwks_controller))
 }
 
 fn build_oauth_router() -> Scope {
     web::scope("/oauth")
-        .route("/access/google", web::post().to(access_google_controller))
+        .route("/providers", web::get().to(get_providers_controller))
+        .route("/magic-link", web::post().to(request_magic_link_controller))
+        .route("/magic-link/verify", web::post().to(verify_magic_link_controller))
+        .route("/access/{provider}", web::post().to(access_provider_controller))
+        .route("/{provider}/begin", web::post().to(begin_login_controller))
+        .route("/refresh", web::post().to(refresh_controller))
+        .route("/logout", web::post().to(logout_controller))
 }
 
 fn build_account_router() -> Scope {
     web::scope("accounts")
         .route("/@me", web::get().to(get_me_controller))
+        .route("/@me", web::patch().to(patch_me_controller))
+        .route("/@me", web::delete().to(delete_me_controller))
+        .route("/@me/restore", web::post().to(restore_me_controller))
+        .route("/@me/locked-fields", web::put().to(update_locked_fields_controller))
+        .route("/@me/export", web::get().to(export_me_controller))
+        .route("/@me/ledger", web::get().to(get_my_ledger_controller))
+        .route("/@me/sessions", web::get().to(get_my_sessions_controller))
+        .route("/@me/sessions", web::delete().to(revoke_all_my_sessions_controller))
+        .route("/@me/sessions/{session_id}", web::delete().to(revoke_my_session_controller))
+        .route("/@me/identities", web::get().to(get_my_identities_controller))
+        .route("/@me/identities/{identity_id}", web::delete().to(unlink_identity_controller))
+        .route("/@me/identities/{provider}/begin", web::post().to(begin_link_identity_controller))
+        .route("/@me/identities/{provider}", web::post().to(link_identity_controller))
+        .route("/@me/api-keys", web::get().to(get_my_api_keys_controller))
+        .route("/@me/api-keys", web::post().to(create_api_key_controller))
+        .route("/@me/api-keys/{api_key_id}", web::patch().to(rename_api_key_controller))
+        .route("/@me/api-keys/{api_key_id}", web::delete().to(revoke_api_key_controller))
+}
+
+fn build_organization_router() -> Scope {
+    web::scope("/organizations")
+        .route("", web::get().to(get_my_organizations_controller))
+        .route("", web::post().to(create_organization_controller))
+        .route("/invitations/accept", web::post().to(accept_invitation_controller))
+        .route("/{organization_id}", web::get().to(get_organization_controller))
+        .route("/{organization_id}/credits", web::post().to(transfer_credits_controller))
+        .route("/{organization_id}/ledger", web::get().to(get_organization_ledger_controller))
+        .route("/{organization_id}/invitations", web::get().to(get_invitations_controller))
+        .route("/{organization_id}/invitations", web::post().to(invite_member_controller))
+        .route("/{organization_id}/invitations/{invitation_id}", web::delete().to(revoke_invitation_controller))
+        .route("/{organization_id}/members/{account_id}", web::delete().to(remove_member_controller))
+        .route("/{organization_id}/members/{account_id}/role", web::put().to(set_member_role_controller))
+        .route("/{organization_id}/members/{account_id}/spending-cap", web::put().to(set_spending_cap_controller))
 }
 
 fn build_services_router() -> Scope {
@@ -26,4 +114,50 @@ fn build_services_router() -> Scope {
         // 65536 Tokens = 262144 Characters
         .app_data(web::FormConfig::default().limit(262_144))
         .route("/summarize", web::post().to(summarize_controller))
-}
\ No newline at end of file
+}
+
+/// Every route needs a staff role in the token, and each controller checks its own
+/// permission again against the account's current flags
+fn build_admin_router() -> Scope {
+    web::scope("/admin")
+        .service(
+            web::scope("/accounts")
+                .wrap(require(Permission::ViewAccounts))
+                .route("", web::get().to(search_accounts_controller))
+                .route("/{account_id}", web::get().to(get_account_controller))
+                .route("/{account_id}/actions", web::get().to(get_account_actions_controller))
+                .route("/{account_id}/ledger", web::get().to(get_account_ledger_controller))
+                .service(
+                    web::resource("/{account_id}/flags")
+                        .wrap(require(Permission::ManageAccountFlags))
+                        .route(web::put().to(set_account_flags_controller)),
+                )
+                .service(
+                    web::resource("/{account_id}/suspend")
+                        .wrap(require(Permission::SuspendAccounts))
+                        .route(web::post().to(suspend_account_controller)),
+                )
+                .service(
+                    web::resource("/{account_id}/restore")
+                        .wrap(require(Permission::RestoreAccounts))
+                        .route(web::post().to(restore_account_controller)),
+                )
+                .service(
+                    web::resource("/{account_id}/credits")
+                        .wrap(require(Permission::GrantCredits))
+                        .route(web::post().to(adjust_credits_controller)),
+                )
+                .service(
+                    web::resource("/{account_id}/export")
+                        .wrap(require(Permission::ExportAccountData))
+                        .route(web::get().to(export_account_controller)),
+                ),
+        )
+        .service(
+            web::scope("/feature-flags")
+                .wrap(require(Permission::ManageFeatureFlags))
+                .route("", web::get().to(get_feature_flags_controller))
+                .route("/{key}", web::put().to(save_feature_flag_controller))
+                .route("/{key}", web::delete().to(delete_feature_flag_controller)),
+        )
+}
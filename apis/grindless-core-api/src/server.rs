@@ -1,22 +1,63 @@
 use crate::{
-    env::Enviroment, routers::build_api_router, services::DEFAULT_MODEL, state::{APIState, APIStateDatabases, Anthropic, OpenAI, Pems, LLM}
+    env::Enviroment, jobs::spawn_account_jobs, limits::add_account_buckets, mail::mailer_from_env, oauth::OAuthProviders, routers::{build_api_router, build_well_known_router}, services::DEFAULT_MODEL, state::{APIState, APIStateDatabases, Anthropic, OpenAI, LLM}
 };
 use actix_cors::Cors;
 use actix_web::{middleware::Logger, web, App, HttpServer};
-use log::{error, info};
+use grindless_core::util::{
+    jwt::{JWTSettings, DEFAULT_LEEWAY_SECONDS},
+    keyring::KeyRing,
+};
+use log::{error, info, warn};
 use nervio_limiter::{limiter::{BucketConfig, LimitEntityType, Limiter}, middleware::actix_web::ActixWebLimiterMiddleware, storage::StorageType};
 use sea_orm::DatabaseConnection;
-use std::{sync::Arc, time::Duration};
+use std::{path::Path, sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 
+/// Kid of the key in `TOKEN_SIGNING_KEY_PEM` when `TOKEN_SIGNING_KEY_ID` is not set
+pub static INLINE_KEY_ID: &str = "env";
+
 pub async fn init_server(
     postgres_conn: DatabaseConnection,
     enviroment: Enviroment,
 ) -> std::io::Result<()> {
-    let cert = include_bytes!("../cert.pem");
-    let key = include_bytes!("../key.pem");
+    let inline_key = enviroment.tokens.signing_key_pem.as_ref().map(|key_pem| {
+        (
+            enviroment.tokens.signing_key_id.as_deref().unwrap_or(INLINE_KEY_ID),
+            key_pem.as_bytes(),
+        )
+    });
+
+    let key_ring = match KeyRing::load(
+        enviroment.tokens.keys_dir.as_deref().map(Path::new),
+        inline_key,
+        enviroment.tokens.signing_key_id.as_deref(),
+    ) {
+        Ok(k) => k,
+        Err(e) => {
+            panic!("Error loading token keys: {:?}", e);
+        }
+    };
+    info!("Signing tokens with key {}", key_ring.signing_key().kid);
+
+    let oauth_providers = match OAuthProviders::from_env(&enviroment.oauth).await {
+        Ok(p) => p,
+        Err(e) => {
+            panic!("Error setting up OAuth providers: {:?}", e);
+        }
+    };
+
+    let mailer = match mailer_from_env(&enviroment.mail) {
+        Ok(m) => m,
+        Err(e) => {
+            panic!("Error setting up mail: {:?}", e);
+        }
+    };
+    if enviroment.production && enviroment.mail.from.is_some() && enviroment.mail.transport != "smtp" {
+        warn!("Mail is written to {} instead of being sent", enviroment.mail.outbox_dir);
+    }
 
     let postgres_conn = Arc::new(postgres_conn);
+    spawn_account_jobs(postgres_conn.clone(), enviroment.account_deletion_grace_days);
 
     let limiter = Arc::new(Mutex::new(Limiter::new(
         StorageType::InMemory,
@@ -36,6 +77,9 @@ pub async fn init_server(
         },
     };
 
+    // Sized per account from the max_requests_per_hour claim
+    add_account_buckets(&limiter).await;
+
     let anthropic_client = anthropic_sdk::Client::new()
         .auth(&enviroment.llm.anthropic.api_keys[0].clone())
         .model(DEFAULT_MODEL)
@@ -49,10 +93,14 @@ pub async fn init_server(
         databases: APIStateDatabases {
             postgres_conn: postgres_conn.clone(),
         },
-        pems: Arc::new(Pems {
-            tokens_public: cert.to_vec(),
-            tokens_private: key.to_vec(),
-        }),
+        key_ring: Arc::new(key_ring),
+        jwt_settings: JWTSettings {
+            issuer: enviroment.tokens.issuer.clone(),
+            audience: enviroment.tokens.audience.clone(),
+            leeway: enviroment.tokens.leeway_seconds.unwrap_or(DEFAULT_LEEWAY_SECONDS),
+        },
+        oauth_providers: Arc::new(oauth_providers),
+        mailer: Arc::from(mailer),
         limiter: limiter.clone(),
         llm: LLM {
             anthropic: Anthropic {
@@ -77,6 +125,7 @@ pub async fn init_server(
             )
             .wrap(Logger::default())
             .app_data(state.clone())
+            .service(build_well_known_router())
             .service(build_api_router())
     })
     .workers(2);
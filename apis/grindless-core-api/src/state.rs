@@ -1,19 +1,15 @@
 use actix_web::web;
+use grindless_core::util::{jwt::JWTSettings, keyring::KeyRing};
 use sea_orm::DatabaseConnection;
 use tokio::sync::Mutex;
 use std::sync::Arc;
-use crate::env::Enviroment;
+use crate::{env::Enviroment, mail::Mailer, oauth::OAuthProviders};
 
 #[derive(Clone)]
 pub struct APIStateDatabases {
     pub postgres_conn: Arc<DatabaseConnection>,
 }
 
-pub struct Pems {
-    pub tokens_public: Vec<u8>,
-    pub tokens_private: Vec<u8>,
-}
-
 pub struct Anthropic {
     pub client: anthropic_sdk::Client,
 }
@@ -29,7 +25,10 @@ pub struct LLM {
 pub struct APIState {
     pub env: Enviroment,
     pub databases: APIStateDatabases,
-    pub pems: Arc<Pems>,
+    pub key_ring: Arc<KeyRing>,
+    pub jwt_settings: JWTSettings,
+    pub oauth_providers: Arc<OAuthProviders>,
+    pub mailer: Arc<dyn Mailer>,
     pub limiter: Arc<Mutex<nervio_limiter::limiter::Limiter>>,
     pub llm: LLM,
 }
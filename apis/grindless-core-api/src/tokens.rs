@@ -0,0 +1,304 @@
+use actix_web::HttpRequest;
+use chrono::{Duration, Utc};
+use grindless_core::{
+    entities::{
+        account::{AccountFlags, Model},
+        oauth_state,
+        oauth_state_ops::OAuthStateMutationCore,
+        refresh_token,
+        refresh_token_ops::RefreshTokenMutationCore,
+        session,
+        session_ops::SessionMutationCore,
+    },
+    response::errors::Errors,
+    util::{
+        jwt::{self, JWTClaims, JWTTokenType, OAuthStateClaims},
+        secrets::{pkce_challenge, random_token},
+        snowflake::new_id,
+    },
+    Timestamp, ID,
+};
+use log::error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    state::APIStateWrapper,
+    util::{get_client_ip, get_user_agent},
+};
+
+// Access tokens are short lived, the refresh token is what keeps the user logged in
+pub static ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+pub static REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+pub static DEFAULT_MAX_REQUESTS_PER_HOUR: u64 = 512;
+pub static PARTNER_MAX_REQUESTS_PER_HOUR: u64 = 4096;
+
+// API keys look like gl_<8 chars>_<40 chars>, the first part is the stored prefix
+pub static API_KEY_PREFIX_RANDOM_LEN: usize = 8;
+pub static API_KEY_SECRET_LEN: usize = 40;
+
+// An OAuth login has to be finished within this time after it was started
+pub static OAUTH_STATE_TTL_MINUTES: i64 = 10;
+pub static PKCE_CODE_VERIFIER_LEN: usize = 64;
+
+// Magic links work once, for a short time, and only so many are sent to one address
+pub static MAGIC_LINK_TTL_MINUTES: i64 = 15;
+pub static MAGIC_LINK_TOKEN_LEN: usize = 48;
+pub static MAGIC_LINK_MAX_PER_TTL: u64 = 5;
+
+// Organization invitations are mailed like magic links but stay valid for longer
+pub static ORGANIZATION_INVITATION_TTL_DAYS: i64 = 7;
+pub static ORGANIZATION_INVITATION_TOKEN_LEN: usize = 48;
+
+/// # Max Requests Per Hour For
+///
+/// The size of the per-account rate limit bucket, carried in the token. A new size only
+/// applies from the next refresh.
+pub fn max_requests_per_hour_for(account: &Model) -> u64 {
+    let privileged = account.flags.iter().any(|f| {
+        matches!(
+            f,
+            AccountFlags::Partner | AccountFlags::Administrator | AccountFlags::Developer
+        )
+    });
+
+    if privileged {
+        PARTNER_MAX_REQUESTS_PER_HOUR
+    } else {
+        DEFAULT_MAX_REQUESTS_PER_HOUR
+    }
+}
+
+/// # Token Pair
+///
+/// What a successful login or refresh hands back to the client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+}
+
+/// # Start Session
+///
+/// Records a new session for the device that is logging in and returns its ID.
+pub async fn start_session(
+    state: &APIStateWrapper,
+    req: &HttpRequest,
+    account: &Model,
+    device: Option<String>,
+) -> Result<ID, Errors> {
+    let now_unix = Utc::now().timestamp() as Timestamp;
+
+    match SessionMutationCore::create_session(
+        &state.databases.postgres_conn,
+        session::Model {
+            id: new_id(),
+            account_id: account.id,
+            device,
+            ip: get_client_ip(req),
+            user_agent: get_user_agent(req),
+            created_at: now_unix,
+            last_seen_at: now_unix,
+            revoked: false,
+            revoked_at: None,
+        },
+    )
+    .await
+    {
+        Ok(s) => Ok(s.id),
+        Err(err) => {
+            error!("Error creating session: {:?}", err);
+            Err(Errors::InternalServerError)
+        }
+    }
+}
+
+/// # Issue Token Pair
+///
+/// Mints a new access token and a new refresh token for the account. Both carry the session
+/// ID, which is also the family of the refresh token.
+pub async fn issue_token_pair(
+    state: &APIStateWrapper,
+    account: &Model,
+    session_id: ID,
+) -> Result<TokenPair, Errors> {
+    let now = Utc::now();
+    let now_unix = now.timestamp() as Timestamp;
+
+    let refresh_id = new_id();
+    let refresh_expires_at = (now + Duration::days(REFRESH_TOKEN_TTL_DAYS)).timestamp();
+
+    if let Err(err) = RefreshTokenMutationCore::create_refresh_token(
+        &state.databases.postgres_conn,
+        refresh_token::Model {
+            id: refresh_id,
+            family_id: session_id,
+            account_id: account.id,
+            revoked: false,
+            created_at: now_unix,
+            expires_at: refresh_expires_at,
+            used_at: None,
+        },
+    )
+    .await
+    {
+        error!("Error storing refresh token: {:?}", err);
+        return Err(Errors::InternalServerError);
+    }
+
+    let access_token = jwt::new_token(
+        state.key_ring.signing_key(),
+        JWTClaims {
+            r#type: JWTTokenType::Access,
+            sub: account.id,
+            iss: state.jwt_settings.issuer.clone(),
+            aud: state.jwt_settings.audience.clone(),
+            iat: now_unix,
+            nbf: now_unix,
+            exp: (now + Duration::minutes(ACCESS_TOKEN_TTL_MINUTES)).timestamp(),
+            jti: Some(new_id()),
+            sid: Some(session_id),
+            roles: account.flags.clone(),
+            scopes: None,
+            max_requests_per_hour: max_requests_per_hour_for(account),
+        },
+    )
+    .map_err(|_| Errors::InternalServerError)?;
+
+    let refresh_token = jwt::new_token(
+        state.key_ring.signing_key(),
+        JWTClaims {
+            r#type: JWTTokenType::Refresh,
+            sub: account.id,
+            iss: state.jwt_settings.issuer.clone(),
+            aud: state.jwt_settings.audience.clone(),
+            iat: now_unix,
+            nbf: now_unix,
+            exp: refresh_expires_at,
+            jti: Some(refresh_id),
+            sid: Some(session_id),
+            roles: account.flags.clone(),
+            scopes: None,
+            max_requests_per_hour: max_requests_per_hour_for(account),
+        },
+    )
+    .map_err(|_| Errors::InternalServerError)?;
+
+    Ok(TokenPair {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+    })
+}
+
+/// # OAuth Login
+///
+/// What the browser needs to start logging in with a provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthLogin {
+    pub state: String,
+    pub code_challenge: String,
+    pub expires_in: i64,
+}
+
+/// # Begin OAuth Login
+///
+/// Stores a new login attempt with its PKCE verifier and returns the signed `state` and the
+/// challenge to send to the provider. `link_account_id` is set when an account is linking a
+/// new identity rather than logging in. `redirect_uri` must already be checked against the
+/// allowlist.
+pub async fn begin_oauth_login(
+    state: &APIStateWrapper,
+    provider: &str,
+    redirect_uri: &str,
+    link_account_id: Option<ID>,
+) -> Result<OAuthLogin, Errors> {
+    let now = Utc::now();
+    let now_unix = now.timestamp() as Timestamp;
+    let expires_at = (now + Duration::minutes(OAUTH_STATE_TTL_MINUTES)).timestamp();
+    let code_verifier = random_token(PKCE_CODE_VERIFIER_LEN);
+
+    if let Err(err) =
+        OAuthStateMutationCore::delete_expired_oauth_states(&state.databases.postgres_conn, now_unix).await
+    {
+        error!("Error deleting expired oauth states: {:?}", err);
+    }
+
+    let stored = match OAuthStateMutationCore::create_oauth_state(
+        &state.databases.postgres_conn,
+        oauth_state::Model {
+            id: new_id(),
+            provider: provider.to_string(),
+            code_verifier: code_verifier.clone(),
+            redirect_uri: redirect_uri.to_string(),
+            link_account_id,
+            created_at: now_unix,
+            expires_at,
+            used_at: None,
+        },
+    )
+    .await
+    {
+        Ok(s) => s,
+        Err(err) => {
+            error!("Error storing oauth state: {:?}", err);
+            return Err(Errors::InternalServerError);
+        }
+    };
+
+    let signed_state = jwt::new_token(
+        state.key_ring.signing_key(),
+        OAuthStateClaims {
+            jti: stored.id,
+            provider: provider.to_string(),
+            iss: state.jwt_settings.issuer.clone(),
+            aud: state.jwt_settings.audience.clone(),
+            iat: now_unix,
+            nbf: now_unix,
+            exp: expires_at,
+        },
+    )
+    .map_err(|_| Errors::InternalServerError)?;
+
+    Ok(OAuthLogin {
+        state: signed_state,
+        code_challenge: pkce_challenge(&code_verifier),
+        expires_in: OAUTH_STATE_TTL_MINUTES * 60,
+    })
+}
+
+/// # Finish OAuth Login
+///
+/// Checks the `state` that came back with the code and returns its login attempt, with the
+/// PKCE verifier. Each `state` works once, and only for the provider it was issued for.
+pub async fn finish_oauth_login(
+    state: &APIStateWrapper,
+    provider: &str,
+    signed_state: &str,
+) -> Result<oauth_state::Model, Errors> {
+    let claims = jwt::validate_oauth_state(&state.key_ring, &state.jwt_settings, signed_state)
+        .map_err(|_| Errors::InvalidOAuthState)?;
+
+    if claims.provider != provider {
+        return Err(Errors::InvalidOAuthState);
+    }
+
+    match OAuthStateMutationCore::consume_oauth_state(
+        &state.databases.postgres_conn,
+        claims.jti,
+        provider,
+        Utc::now().timestamp() as Timestamp,
+    )
+    .await
+    {
+        Ok(Some(s)) => Ok(s),
+        Ok(None) => Err(Errors::InvalidOAuthState),
+        Err(err) => {
+            error!("Error consuming oauth state: {:?}", err);
+            Err(Errors::InternalServerError)
+        }
+    }
+}
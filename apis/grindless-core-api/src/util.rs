@@ -1,35 +1,62 @@
-use actix_web::web;
+use actix_web::{HttpMessage, HttpRequest};
+use chrono::Utc;
 use grindless_core::{
-    response::response::{build_err, ResponseBuilderError, ResponseObject},
-    util::jwt::{self, JWTClaims},
+    entities::{
+        account_ops::AccountQueryCore,
+        api_key::API_KEY_PREFIX,
+        api_key_ops::{ApiKeyMutationCore, ApiKeyQueryCore},
+        session_ops::{SessionMutationCore, SessionQueryCore},
+    },
+    response::errors::Errors,
+    util::{
+        jwt::{self, JWTClaims, JWTTokenType},
+        secrets::{secrets_equal, sha256_hex},
+    },
+    Timestamp,
 };
-use jsonwebtoken::Algorithm;
-use serde::Serialize;
-
-pub fn get_claims_from_header<T>(
-    authorization_header: Option<&actix_web::http::header::HeaderValue>,
-    public_pem: Vec<u8>,
-) -> Result<JWTClaims, web::Json<ResponseObject<T>>>
-where
-    T: Serialize,
-{
-    let authorization_header = match authorization_header {
+use log::error;
+
+use crate::{
+    extractors::AuthenticationError,
+    state::APIStateWrapper,
+    limits::hit_account_bucket,
+    tokens::{max_requests_per_hour_for, API_KEY_PREFIX_RANDOM_LEN},
+};
+
+// Don't write last_seen_at or last_used_at on every request
+pub static SESSION_TOUCH_INTERVAL_SECONDS: i64 = 60;
+
+/// # Get Claims From Header
+///
+/// Validates the Bearer credential of the request, either an access token whose session is
+/// still alive or a personal API key. Handlers should take an `AuthenticatedAccount` instead
+/// of calling this directly. The claims are cached on the request, so the credential is
+/// checked (and rate limited) only once however many guards and extractors ask.
+pub async fn get_claims_from_header(
+    req: &HttpRequest,
+    state: &APIStateWrapper,
+) -> Result<JWTClaims, AuthenticationError> {
+    if let Some(claims) = req.extensions().get::<JWTClaims>() {
+        return Ok(claims.clone());
+    }
+
+    let authorization_header = match req.headers().get("Authorization") {
         Some(h) => h,
         None => {
-            return Err(build_err::<T>(ResponseBuilderError {
-                message: "No authorization header".to_string(),
-                errors: vec![],
-            }))
+            return Err(AuthenticationError::new(
+                Errors::Unauthorized,
+                "No authorization header",
+            ))
         }
     };
 
     let authorization_header = match authorization_header.to_str() {
         Ok(a) => a.to_string(),
         Err(_) => {
-            return Err(build_err::<T>(ResponseBuilderError {
-                message: "Error parsing authorization header".to_string(),
-                errors: vec![],
-            }))
+            return Err(AuthenticationError::new(
+                Errors::Unauthorized,
+                "Error parsing authorization header",
+            ))
         }
     };
 
@@ -37,39 +64,232 @@ where
     match parts.next() {
         Some(bearer) => {
             if bearer != "Bearer" {
-                return Err(build_err(ResponseBuilderError {
-                    message: "Invalid authorization header".to_string(),
-                    errors: vec![],
-                }));
+                return Err(AuthenticationError::new(
+                    Errors::Unauthorized,
+                    "Invalid authorization header",
+                ));
             }
         }
         None => {
-            return Err(build_err(ResponseBuilderError {
-                message: "Invalid authorization header".to_string(),
-                errors: vec![],
-            }));
+            return Err(AuthenticationError::new(
+                Errors::Unauthorized,
+                "Invalid authorization header",
+            ));
         }
     }
 
     let token = match parts.next() {
         Some(token) => token.to_string(),
         None => {
-            return Err(build_err(ResponseBuilderError {
-                message: "Invalid authorization header".to_string(),
-                errors: vec![],
-            }));
+            return Err(AuthenticationError::new(
+                Errors::Unauthorized,
+                "Invalid authorization header",
+            ));
         }
     };
 
-    let validate_token = match jwt::validate_token(&public_pem, Algorithm::RS256, &token) {
-        Ok(t) => t,
-        Err(_) => {
-            return Err(build_err(ResponseBuilderError {
-                message: "Invalid token".to_string(),
-                errors: vec![],
-            }))
+    let claims = if token.starts_with(API_KEY_PREFIX) {
+        get_claims_from_api_key(state, &token).await?
+    } else {
+        get_claims_from_access_token(req, state, &token).await?
+    };
+
+    if let Err(exceeded) =
+        hit_account_bucket(&state.limiter, claims.sub, claims.max_requests_per_hour).await
+    {
+        return Err(AuthenticationError::new(
+            Errors::TooManyRequests,
+            &format!(
+                "Rate limit of {} requests per hour reached",
+                exceeded.limit
+            ),
+        ));
+    }
+
+    req.extensions_mut().insert(claims.clone());
+    Ok(claims)
+}
+
+async fn get_claims_from_access_token(
+    req: &HttpRequest,
+    state: &APIStateWrapper,
+    token: &str,
+) -> Result<JWTClaims, AuthenticationError> {
+    // Expired and not-yet-valid tokens can be refreshed, the rest need a new login
+    let validate_token = jwt::validate_token(&state.key_ring, &state.jwt_settings, token)
+        .map_err(|err| AuthenticationError::new(err, "Invalid token"))?;
+
+    // Refresh tokens are only good for /oauth/refresh
+    if validate_token.r#type != JWTTokenType::Access {
+        return Err(AuthenticationError::new(
+            Errors::InvalidToken,
+            "Not an access token",
+        ));
+    }
+
+    // Tokens without a session can't be revoked, so they are not accepted
+    let session_id = match validate_token.sid {
+        Some(sid) => sid,
+        None => {
+            return Err(AuthenticationError::new(
+                Errors::RevokedToken,
+                "Log in again",
+            ))
         }
     };
 
+    let session = match SessionQueryCore::get_session_by_id(&state.databases.postgres_conn, session_id).await {
+        Ok(s) => s,
+        Err(err) => {
+            error!("Error getting session {:?}", err);
+            return Err(AuthenticationError::new(
+                Errors::InternalServerError,
+                "Error getting session",
+            ));
+        }
+    };
+
+    let session = match session {
+        Some(s) if !s.revoked && s.account_id == validate_token.sub => s,
+        _ => {
+            return Err(AuthenticationError::new(
+                Errors::RevokedToken,
+                "Session revoked",
+            ))
+        }
+    };
+
+    let now_unix = Utc::now().timestamp();
+    if now_unix - session.last_seen_at > SESSION_TOUCH_INTERVAL_SECONDS {
+        if let Err(err) = SessionMutationCore::touch_session(
+            &state.databases.postgres_conn,
+            session.id,
+            get_client_ip(req),
+            now_unix,
+        )
+        .await
+        {
+            error!("Error updating session last seen {:?}", err);
+        }
+    }
+
     Ok(validate_token)
 }
+
+/// # Get Claims From API Key
+///
+/// Turns a personal API key into claims, so the rest of the API doesn't need to care how
+/// the request authenticated. API keys never carry roles, and their `scopes` limit what
+/// they can reach.
+async fn get_claims_from_api_key(
+    state: &APIStateWrapper,
+    key: &str,
+) -> Result<JWTClaims, AuthenticationError> {
+    let prefix = match key.get(..API_KEY_PREFIX.len() + API_KEY_PREFIX_RANDOM_LEN) {
+        Some(p) => p,
+        None => {
+            return Err(AuthenticationError::new(
+                Errors::InvalidToken,
+                "Invalid API key",
+            ))
+        }
+    };
+
+    let api_key = match ApiKeyQueryCore::get_api_key_by_prefix(&state.databases.postgres_conn, prefix).await {
+        Ok(k) => k,
+        Err(err) => {
+            error!("Error getting API key {:?}", err);
+            return Err(AuthenticationError::new(
+                Errors::InternalServerError,
+                "Error getting API key",
+            ));
+        }
+    };
+
+    let api_key = match api_key {
+        Some(k) if secrets_equal(&k.key_hash, &sha256_hex(key)) => k,
+        _ => {
+            return Err(AuthenticationError::new(
+                Errors::InvalidToken,
+                "Invalid API key",
+            ))
+        }
+    };
+
+    if api_key.revoked {
+        return Err(AuthenticationError::new(
+            Errors::RevokedToken,
+            "API key revoked",
+        ));
+    }
+
+    let now_unix = Utc::now().timestamp();
+    if api_key.expires_at.map(|e| e <= now_unix).unwrap_or(false) {
+        return Err(AuthenticationError::new(
+            Errors::ExpiredToken,
+            "API key expired",
+        ));
+    }
+
+    // Keys share the rate limit of the account's plan, like its access tokens
+    let account = match AccountQueryCore::get_account_by_id(&state.databases.postgres_conn, api_key.account_id).await {
+        Ok(Some(a)) => a,
+        Ok(None) => {
+            return Err(AuthenticationError::new(
+                Errors::InvalidToken,
+                "Invalid API key",
+            ))
+        }
+        Err(err) => {
+            error!("Error getting account {:?}", err);
+            return Err(AuthenticationError::new(
+                Errors::InternalServerError,
+                "Error getting account",
+            ));
+        }
+    };
+
+    let stale = api_key
+        .last_used_at
+        .map(|l| now_unix - l > SESSION_TOUCH_INTERVAL_SECONDS)
+        .unwrap_or(true);
+    if stale {
+        if let Err(err) = ApiKeyMutationCore::touch_api_key(
+            &state.databases.postgres_conn,
+            api_key.id,
+            now_unix,
+        )
+        .await
+        {
+            error!("Error updating API key last used {:?}", err);
+        }
+    }
+
+    Ok(JWTClaims {
+        r#type: JWTTokenType::Access,
+        sub: api_key.account_id,
+        iss: state.jwt_settings.issuer.clone(),
+        aud: state.jwt_settings.audience.clone(),
+        iat: api_key.created_at,
+        nbf: api_key.created_at,
+        exp: api_key.expires_at.unwrap_or(Timestamp::MAX),
+        jti: Some(api_key.id),
+        sid: None,
+        roles: vec![],
+        scopes: Some(api_key.scopes),
+        max_requests_per_hour: max_requests_per_hour_for(&account),
+    })
+}
+
+pub fn get_client_ip(req: &HttpRequest) -> Option<String> {
+    req.connection_info()
+        .realip_remote_addr()
+        .map(|ip| ip.to_string())
+}
+
+pub fn get_user_agent(req: &HttpRequest) -> Option<String> {
+    req.headers()
+        .get("User-Agent")
+        .and_then(|ua| ua.to_str().ok())
+        .map(|ua| ua.to_string())
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+
+use sea_orm::{sea_query::Expr, *};
+
+use crate::{
+    entities::{
+        billing,
+        ledger_entry_ops::{CreditMovement, LedgerEntryMutationCore},
+        organization_member,
+    },
+    ID, Timestamp,
+};
+
+/// # Billing Error
+///
+/// Why credits could not be charged or refunded.
+#[derive(Debug)]
+pub enum BillingError {
+    /// The balance is lower than the charge
+    InsufficientFunds,
+    /// The member would go over their spending cap, or is not a member
+    OverSpendingCap,
+    /// The payer has no billing
+    NotFound,
+    Database(DbErr),
+}
+
+impl fmt::Display for BillingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BillingError::InsufficientFunds => write!(f, "insufficient credits"),
+            BillingError::OverSpendingCap => write!(f, "spending cap reached"),
+            BillingError::NotFound => write!(f, "billing not found"),
+            BillingError::Database(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for BillingError {}
+
+impl From<DbErr> for BillingError {
+    fn from(err: DbErr) -> Self {
+        BillingError::Database(err)
+    }
+}
+
+/// # Payer
+///
+/// Whose credits pay for a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Payer {
+    Account(ID),
+    /// The pool of the organization, counted against the spending cap of the member
+    Organization { organization_id: ID, account_id: ID },
+}
+
+impl Payer {
+    /// The account behind the request, whoever pays
+    pub fn account_id(&self) -> ID {
+        match self {
+            Payer::Account(account_id) => *account_id,
+            Payer::Organization { account_id, .. } => *account_id,
+        }
+    }
+
+    pub fn organization_id(&self) -> Option<ID> {
+        match self {
+            Payer::Account(_) => None,
+            Payer::Organization { organization_id, .. } => Some(*organization_id),
+        }
+    }
+
+    fn billing_owner(&self) -> Condition {
+        match self {
+            Payer::Account(account_id) => Condition::all().add(billing::Column::AccountId.eq(*account_id)),
+            Payer::Organization { organization_id, .. } => {
+                Condition::all().add(billing::Column::OrganizationId.eq(*organization_id))
+            }
+        }
+    }
+}
+
+/// # Charge
+///
+/// Takes `amount` credits from the payer. The balance and the spending cap are checked by
+/// the updates themselves, in one transaction with the ledger entry, so concurrent requests
+/// can't overspend. Returns the billing after the charge, `NotFound` when the payer has no
+/// billing.
+pub async fn charge(
+    db: &DbConn,
+    payer: Payer,
+    amount: i64,
+    movement: &CreditMovement,
+    now: Timestamp,
+) -> Result<billing::Model, BillingError> {
+    let txn = db.begin().await?;
+
+    if let Payer::Organization { organization_id, account_id } = payer {
+        let member = organization_member::Entity::update_many()
+            .col_expr(
+                organization_member::Column::CreditsSpent,
+                Expr::col(organization_member::Column::CreditsSpent).add(amount),
+            )
+            .col_expr(organization_member::Column::UpdatedAt, Expr::value(now))
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .filter(organization_member::Column::AccountId.eq(account_id))
+            .filter(
+                Condition::any()
+                    .add(organization_member::Column::SpendingCap.is_null())
+                    .add(
+                        Expr::col(organization_member::Column::SpendingCap)
+                            .gte(Expr::col(organization_member::Column::CreditsSpent).add(amount)),
+                    ),
+            )
+            .exec(&txn)
+            .await?;
+        if member.rows_affected != 1 {
+            txn.rollback().await?;
+            return Err(BillingError::OverSpendingCap);
+        }
+    }
+
+    match LedgerEntryMutationCore::move_credits(&txn, payer.billing_owner(), -amount, movement, now).await? {
+        Some(billing) => {
+            txn.commit().await?;
+            Ok(billing)
+        }
+        None => {
+            let exists = billing::Entity::find().filter(payer.billing_owner()).one(&txn).await?.is_some();
+            txn.rollback().await?;
+            Err(if exists { BillingError::InsufficientFunds } else { BillingError::NotFound })
+        }
+    }
+}
+
+/// # Refund
+///
+/// Undoes a charge, for requests that failed after being paid. Gives the credits back and
+/// lowers what the member spent, in one transaction with the ledger entry.
+pub async fn refund(
+    db: &DbConn,
+    payer: Payer,
+    amount: i64,
+    movement: &CreditMovement,
+    now: Timestamp,
+) -> Result<billing::Model, BillingError> {
+    let txn = db.begin().await?;
+
+    if let Payer::Organization { organization_id, account_id } = payer {
+        organization_member::Entity::update_many()
+            .col_expr(
+                organization_member::Column::CreditsSpent,
+                Expr::col(organization_member::Column::CreditsSpent).sub(amount),
+            )
+            .col_expr(organization_member::Column::UpdatedAt, Expr::value(now))
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .filter(organization_member::Column::AccountId.eq(account_id))
+            .filter(organization_member::Column::CreditsSpent.gte(amount))
+            .exec(&txn)
+            .await?;
+    }
+
+    match LedgerEntryMutationCore::move_credits(&txn, payer.billing_owner(), amount, movement, now).await? {
+        Some(billing) => {
+            txn.commit().await?;
+            Ok(billing)
+        }
+        None => {
+            txn.rollback().await?;
+            Err(BillingError::NotFound)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner_sql(payer: Payer) -> String {
+        billing::Entity::find()
+            .filter(payer.billing_owner())
+            .build(DatabaseBackend::Postgres)
+            .to_string()
+    }
+
+    #[test]
+    fn accounts_pay_themselves() {
+        let payer = Payer::Account(7);
+        assert_eq!(payer.account_id(), 7);
+        assert_eq!(payer.organization_id(), None);
+        assert!(owner_sql(payer).ends_with(r#"WHERE "billings"."account_id" = 7"#));
+    }
+
+    #[test]
+    fn organizations_pay_for_their_members() {
+        let payer = Payer::Organization {
+            organization_id: 3,
+            account_id: 7,
+        };
+        assert_eq!(payer.account_id(), 7);
+        assert_eq!(payer.organization_id(), Some(3));
+        assert!(owner_sql(payer).ends_with(r#"WHERE "billings"."organization_id" = 3"#));
+    }
+}
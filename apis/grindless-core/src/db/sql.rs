@@ -1,10 +1,19 @@
 use std::time::Duration;
 
 use log::{debug, info, warn};
-use sea_orm::ConnectionTrait;
+use sea_orm::{
+    sea_query::{Expr, Index, OnConflict, Query, SimpleExpr},
+    ActiveEnum, ColumnTrait, ConditionalStatement, ConnectionTrait, DbErr, QueryFilter,
+    QuerySelect, Set, Statement, TransactionTrait,
+};
 use sea_orm::{ConnectOptions, Database, DatabaseConnection, DbConn, EntityTrait, Schema};
 
-use crate::entities::{account, billing};
+use crate::entities::{
+    account, admin_action, api_key, billing, feature_flag, ledger_entry, magic_link, oauth_state,
+    organization, organization_invitation, organization_member, provider_identity, refresh_token,
+    service_usage, session,
+};
+use crate::util::snowflake::new_id;
 
 pub async fn establish_postgres_connection(
     postgres_url: &String,
@@ -27,6 +36,69 @@ pub async fn establish_postgres_connection(
 
     create_table_for_entity_if_not_exists(&db, account::Entity).await;
     create_table_for_entity_if_not_exists(&db, billing::Entity).await;
+    create_table_for_entity_if_not_exists(&db, refresh_token::Entity).await;
+    create_table_for_entity_if_not_exists(&db, session::Entity).await;
+    create_table_for_entity_if_not_exists(&db, api_key::Entity).await;
+    create_table_for_entity_if_not_exists(&db, provider_identity::Entity).await;
+    create_table_for_entity_if_not_exists(&db, oauth_state::Entity).await;
+    create_table_for_entity_if_not_exists(&db, magic_link::Entity).await;
+    create_table_for_entity_if_not_exists(&db, admin_action::Entity).await;
+    create_table_for_entity_if_not_exists(&db, organization::Entity).await;
+    create_table_for_entity_if_not_exists(&db, organization_member::Entity).await;
+    create_table_for_entity_if_not_exists(&db, organization_invitation::Entity).await;
+    create_table_for_entity_if_not_exists(&db, service_usage::Entity).await;
+    create_table_for_entity_if_not_exists(&db, feature_flag::Entity).await;
+    create_table_for_entity_if_not_exists(&db, ledger_entry::Entity).await;
+    add_column_if_not_exists(&db, "accounts", "locked_fields", "INTEGER[] NOT NULL DEFAULT '{}'").await?;
+    add_column_if_not_exists(&db, "accounts", "preferences", "JSONB NOT NULL DEFAULT '{}'").await?;
+    add_column_if_not_exists(&db, "accounts", "suspended_at", "BIGINT").await?;
+    add_column_if_not_exists(&db, "accounts", "suspension_reason", "TEXT").await?;
+    add_column_if_not_exists(&db, "accounts", "suspended_by", "BIGINT").await?;
+    add_column_if_not_exists(&db, "accounts", "suspended_until", "BIGINT").await?;
+    add_column_if_not_exists(&db, "billings", "organization_id", "BIGINT").await?;
+    drop_not_null(&db, "billings", "account_id").await?;
+
+    let provider_subject_index = Index::create()
+        .if_not_exists()
+        .unique()
+        .name("idx_provider_identities_provider_subject")
+        .table(provider_identity::Entity)
+        .col(provider_identity::Column::Provider)
+        .col(provider_identity::Column::Subject)
+        .to_owned();
+    db.execute(db.get_database_backend().build(&provider_subject_index)).await?;
+
+    let organization_member_index = Index::create()
+        .if_not_exists()
+        .unique()
+        .name("idx_organization_members_organization_account")
+        .table(organization_member::Entity)
+        .col(organization_member::Column::OrganizationId)
+        .col(organization_member::Column::AccountId)
+        .to_owned();
+    db.execute(db.get_database_backend().build(&organization_member_index)).await?;
+
+    // One opening balance per billing, however many instances boot at once
+    let opening_balance_index = Index::create()
+        .if_not_exists()
+        .unique()
+        .name("idx_ledger_entries_opening_balance")
+        .table(ledger_entry::Entity)
+        .col(ledger_entry::Column::BillingId)
+        .and_where(opening_balance_only())
+        .to_owned();
+    db.execute(db.get_database_backend().build(&opening_balance_index)).await?;
+
+    protect_ledger(&db).await?;
+
+    if let Err(e) = migrate_google_ids(&db).await {
+        panic!("Error migrating google ids: {}", e);
+    }
+
+    if let Err(e) = migrate_opening_balances(&db).await {
+        panic!("Error migrating opening balances: {}", e);
+    }
+
     return Ok(db);
 }
 
@@ -44,3 +116,195 @@ where
         Err(e) => panic!("Error: {}", e),
     }
 }
+
+/// # Add Column If Not Exists
+///
+/// Tables are only created when missing, so columns added to an entity later need this to
+/// reach databases created before them. `definition` is the Postgres type and constraints.
+pub async fn add_column_if_not_exists(
+    db: &DbConn,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<(), DbErr> {
+    if column_exists(db, table, column).await? {
+        return Ok(());
+    }
+
+    warn!("Adding column {}.{}", table, column);
+    db.execute_unprepared(&format!(
+        "ALTER TABLE \"{}\" ADD COLUMN IF NOT EXISTS \"{}\" {}",
+        table, column, definition
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// # Drop Not Null
+///
+/// Lets an existing column hold `NULL`, for columns that became optional in their entity.
+/// Does nothing when it already can.
+pub async fn drop_not_null(db: &DbConn, table: &str, column: &str) -> Result<(), DbErr> {
+    if column_nullable(db, table, column).await? {
+        return Ok(());
+    }
+
+    warn!("Dropping not null from {}.{}", table, column);
+    db.execute_unprepared(&format!(
+        "ALTER TABLE \"{}\" ALTER COLUMN \"{}\" DROP NOT NULL",
+        table, column
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// # Column Exists
+///
+/// Whether `table` still has `column`, for migrations that change existing tables.
+pub async fn column_exists(db: &DbConn, table: &str, column: &str) -> Result<bool, DbErr> {
+    let row = db
+        .query_one(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            "SELECT 1 FROM information_schema.columns WHERE table_name = $1 AND column_name = $2",
+            [table.into(), column.into()],
+        ))
+        .await?;
+
+    Ok(row.is_some())
+}
+
+/// # Column Nullable
+///
+/// Whether `column` of `table` can already hold `NULL`. Missing columns count as nullable,
+/// there is nothing to change on them.
+pub async fn column_nullable(db: &DbConn, table: &str, column: &str) -> Result<bool, DbErr> {
+    let row = db
+        .query_one(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            "SELECT 1 FROM information_schema.columns WHERE table_name = $1 AND column_name = $2 AND is_nullable = 'NO'",
+            [table.into(), column.into()],
+        ))
+        .await?;
+
+    Ok(row.is_none())
+}
+
+/// # Protect Ledger
+///
+/// Rejects every `UPDATE`, `DELETE` and `TRUNCATE` of `ledger_entries`, so the ledger stays
+/// append-only whatever code or person runs against the database.
+async fn protect_ledger(db: &DbConn) -> Result<(), DbErr> {
+    let txn = db.begin().await?;
+
+    txn.execute_unprepared(
+        "CREATE OR REPLACE FUNCTION ledger_entries_append_only() RETURNS trigger AS $$ \
+        BEGIN RAISE EXCEPTION 'ledger_entries is append-only'; END; \
+        $$ LANGUAGE plpgsql",
+    )
+    .await?;
+    txn.execute_unprepared("DROP TRIGGER IF EXISTS ledger_entries_append_only ON ledger_entries")
+        .await?;
+    txn.execute_unprepared(
+        "CREATE TRIGGER ledger_entries_append_only \
+        BEFORE UPDATE OR DELETE OR TRUNCATE ON ledger_entries \
+        FOR EACH STATEMENT EXECUTE FUNCTION ledger_entries_append_only()",
+    )
+    .await?;
+
+    txn.commit().await
+}
+
+/// # Migrate Google IDs
+///
+/// Accounts used to keep the Google user id in their own `google_id` column. Moves those
+/// into `provider_identities` and drops the column, once.
+async fn migrate_google_ids(db: &DbConn) -> Result<(), DbErr> {
+    if !column_exists(db, "accounts", "google_id").await? {
+        return Ok(());
+    }
+
+    warn!("Moving accounts.google_id to provider_identities");
+    let txn = db.begin().await?;
+
+    // Whether Google had verified the email was never stored, so it is not trusted
+    txn.execute_unprepared(
+        "INSERT INTO provider_identities \
+            (id, account_id, provider, subject, email, email_verified, created_at, updated_at) \
+        SELECT id, id, 'google', google_id, email, false, created_at, updated_at FROM accounts \
+        ON CONFLICT DO NOTHING",
+    )
+    .await?;
+    txn.execute_unprepared("ALTER TABLE accounts DROP COLUMN google_id").await?;
+
+    txn.commit().await
+}
+
+/// # Opening Balance Only
+///
+/// The predicate of the opening balance index. The reason is written inline, Postgres can
+/// only match an `ON CONFLICT` target to a partial index when it is not a parameter.
+fn opening_balance_only() -> SimpleExpr {
+    Expr::col(ledger_entry::Column::Reason).eq(SimpleExpr::Constant(
+        ledger_entry::LedgerReason::OpeningBalance.to_value().into(),
+    ))
+}
+
+/// # Migrate Opening Balances
+///
+/// Billings older than the ledger have credits no entry accounts for. Writes what they had
+/// as the first entry of every billing that has none yet, so they reconcile. The billings
+/// are locked so no charge changes them in between, and a billing that got its opening
+/// balance from another instance is skipped.
+async fn migrate_opening_balances(db: &DbConn) -> Result<(), DbErr> {
+    let txn = db.begin().await?;
+
+    let billings = billing::Entity::find()
+        .filter(billing::Column::Credits.ne(0))
+        .filter(
+            billing::Column::Id.not_in_subquery(
+                Query::select()
+                    .column(ledger_entry::Column::BillingId)
+                    .from(ledger_entry::Entity)
+                    .to_owned(),
+            ),
+        )
+        .lock_exclusive()
+        .all(&txn)
+        .await?;
+    if billings.is_empty() {
+        return txn.commit().await;
+    }
+
+    let mut written = 0;
+    for b in billings {
+        let entry = ledger_entry::ActiveModel {
+            id: Set(new_id()),
+            billing_id: Set(b.id),
+            account_id: Set(b.account_id),
+            amount: Set(b.credits),
+            balance: Set(b.credits),
+            reason: Set(ledger_entry::LedgerReason::OpeningBalance),
+            service: Set(None),
+            request_id: Set(None),
+            created_at: Set(b.updated_at),
+        };
+
+        let result = ledger_entry::Entity::insert(entry)
+            .on_conflict(
+                OnConflict::column(ledger_entry::Column::BillingId)
+                    .target_and_where(opening_balance_only())
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await?;
+        written += result;
+    }
+    if written > 0 {
+        warn!("Wrote the opening balances of {} billings to the ledger", written);
+    }
+
+    txn.commit().await
+}
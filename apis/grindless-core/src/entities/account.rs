@@ -10,9 +10,6 @@ use crate::{ID, Timestamp};
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: ID,
-    
-    #[sea_orm(indexed)]
-    pub google_id: String,
 
     #[sea_orm(column_type = "Text")]
     pub email: String,
@@ -25,6 +22,13 @@ pub struct Model {
 
     pub flags: Vec<AccountFlags>,
 
+    /// Profile fields the user edited by hand, logins no longer copy them from the provider
+    pub locked_fields: Vec<ProfileField>,
+
+    /// Free-form settings of the client, a JSON object
+    #[sea_orm(column_type = "JsonBinary")]
+    pub preferences: Json,
+
     #[sea_orm(column_type = "BigInteger")]
     pub billing_id: ID,
 
@@ -34,6 +38,18 @@ pub struct Model {
     #[sea_orm(column_type = "BigInteger")]
     pub updated_at: Timestamp,
 
+    // Suspension
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub suspended_at: Option<Timestamp>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub suspension_reason: Option<String>,
+    /// The staff account that suspended it
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub suspended_by: Option<ID>,
+    /// When the suspension lifts by itself, `None` lasts until staff lift it
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub suspended_until: Option<Timestamp>,
+
     // Deletion
     #[sea_orm(column_type = "Boolean")]
     pub deleted: bool,
@@ -67,10 +83,26 @@ pub enum AccountFlags {
     Developer,
 }
 
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, EnumIter, DeriveActiveEnum,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileField {
+    #[sea_orm(num_value = 1)]
+    Name,
+    #[sea_orm(num_value = 2)]
+    Email,
+    #[sea_orm(num_value = 4)]
+    Avatar,
+}
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_one = "crate::entities::billing::Entity")]
     Billing,
+    #[sea_orm(has_many = "crate::entities::provider_identity::Entity")]
+    ProviderIdentities,
 }
 
 impl Related<crate::entities::billing::Entity> for Entity {
@@ -79,4 +111,46 @@ impl Related<crate::entities::billing::Entity> for Entity {
     }
 }
 
+impl Related<crate::entities::provider_identity::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ProviderIdentities.def()
+    }
+}
+
+impl Model {
+    /// # Is Suspended
+    ///
+    /// A suspension whose end time has passed no longer counts, even before the background
+    /// job clears it.
+    pub fn is_suspended(&self, now: Timestamp) -> bool {
+        self.suspended_at.is_some() && self.suspended_until.is_none_or(|until| until > now)
+    }
+}
+
+#[cfg(test)]
+impl Model {
+    /// An active account without flags, for tests to change what they need
+    pub(crate) fn fixture(id: ID) -> Self {
+        Model {
+            id,
+            email: format!("{}@example.com", id),
+            name: "Test".to_string(),
+            avatar: None,
+            flags: vec![],
+            locked_fields: vec![],
+            preferences: Json::Object(Default::default()),
+            billing_id: id,
+            created_at: 0,
+            updated_at: 0,
+            suspended_at: None,
+            suspension_reason: None,
+            suspended_by: None,
+            suspended_until: None,
+            deleted: false,
+            deletion_requested_at: None,
+            deletion_reason: None,
+        }
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -1,48 +1,182 @@
-use sea_orm::*;
-use crate::ID;
+use sea_orm::{
+    prelude::Json,
+    sea_query::{Expr, Func, LikeExpr, Query},
+    *,
+};
+use crate::{ID, Timestamp};
 
-use super::{account::{self, ActiveModel, Model}, billing};
+use super::{
+    account::{self, AccountFlags, ActiveModel, Model, ProfileField},
+    api_key, billing,
+    ledger_entry::{self, LedgerReason},
+    ledger_entry_ops::{CreditMovement, LedgerEntryMutationCore},
+    magic_link, oauth_state, organization_invitation, organization_member,
+    organization_member_ops::OrganizationMemberMutationCore, provider_identity, refresh_token,
+    service_usage, session,
+};
+use crate::util::snowflake::new_id;
+
+/// Name left on accounts once their personal data is purged
+pub static PURGED_ACCOUNT_NAME: &str = "Deleted Account";
 
 pub struct AccountMutationCore;
 pub struct AccountQueryCore;
 
+/// # Account Profile Patch
+///
+/// Profile columns to change, `None` leaves the column as it is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountProfilePatch {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub avatar: Option<Option<String>>,
+    pub locked_fields: Option<Vec<ProfileField>>,
+    pub preferences: Option<Json>,
+}
+
+impl AccountProfilePatch {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// # Account Sort
+///
+/// Columns accounts can be listed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountSort {
+    Id,
+    Email,
+    Name,
+    CreatedAt,
+    UpdatedAt,
+}
+
+impl AccountSort {
+    pub fn from_string(sort: &str) -> Option<Self> {
+        match sort {
+            "id" => Some(AccountSort::Id),
+            "email" => Some(AccountSort::Email),
+            "name" => Some(AccountSort::Name),
+            "created_at" => Some(AccountSort::CreatedAt),
+            "updated_at" => Some(AccountSort::UpdatedAt),
+            _ => None,
+        }
+    }
+
+    fn column(&self) -> account::Column {
+        match self {
+            AccountSort::Id => account::Column::Id,
+            AccountSort::Email => account::Column::Email,
+            AccountSort::Name => account::Column::Name,
+            AccountSort::CreatedAt => account::Column::CreatedAt,
+            AccountSort::UpdatedAt => account::Column::UpdatedAt,
+        }
+    }
+}
+
+/// # Account Search
+///
+/// `query` matches part of the email or name, the exact id, or the exact subject of a
+/// linked identity. Pages start at 0.
+#[derive(Debug, Clone)]
+pub struct AccountSearch {
+    pub query: Option<String>,
+    pub sort: AccountSort,
+    pub descending: bool,
+    pub page: u64,
+    pub per_page: u64,
+}
+
 impl AccountMutationCore {
     async fn set_account_active_model(form_data: &Model) -> ActiveModel {
         ActiveModel {
             id: Set(form_data.id.to_owned()),
-            google_id: Set(form_data.google_id.to_owned()),
             email: Set(form_data.email.to_owned()),
             name: Set(form_data.name.to_owned()),
             avatar: Set(form_data.avatar.to_owned()),
             flags: Set(form_data.flags.to_owned()),
+            locked_fields: Set(form_data.locked_fields.to_owned()),
+            preferences: Set(form_data.preferences.to_owned()),
             billing_id: Set(form_data.billing_id.to_owned()),
             created_at: Set(form_data.created_at.to_owned()),
             updated_at: Set(form_data.updated_at.to_owned()),
+            suspended_at: Set(form_data.suspended_at.to_owned()),
+            suspension_reason: Set(form_data.suspension_reason.to_owned()),
+            suspended_by: Set(form_data.suspended_by.to_owned()),
+            suspended_until: Set(form_data.suspended_until.to_owned()),
             deleted: Set(form_data.deleted.to_owned()),
             deletion_requested_at: Set(form_data.deletion_requested_at.to_owned()),
             deletion_reason: Set(form_data.deletion_reason.to_owned()),
         }
     }
 
-    pub async fn create_account(db: &DbConn, mut form_data: Model, billing: billing::Model) -> Result<Model, DbErr> {
-        // create billing
+    /// # Create Account
+    ///
+    /// Inserts the account, its billing, the ledger entry of its starter credits and the
+    /// identity it was created from in one transaction, so there is never an account without
+    /// a billing or a way to log in to it.
+    pub async fn create_account(
+        db: &DbConn,
+        mut form_data: Model,
+        billing: billing::Model,
+        identity: provider_identity::Model,
+    ) -> Result<Model, DbErr> {
+        form_data.billing_id = billing.id;
+        let txn = db.begin().await?;
+
+        let account = Self::set_account_active_model(&form_data)
+            .await
+            .insert(&txn)
+            .await?;
+
+        // create billing, owned by the new account
         billing::ActiveModel {
             id: Set(billing.id.to_owned()),
-            account_id: Set(billing.account_id.to_owned()),
+            account_id: Set(Some(account.id)),
+            organization_id: Set(None),
             credits: Set(billing.credits.to_owned()),
             total_spent_usd: Set(billing.total_spent_usd.to_owned()),
             created_at: Set(billing.created_at.to_owned()),
             updated_at: Set(billing.updated_at.to_owned()),
         }
-        .insert(db)
+        .insert(&txn)
         .await?;
-    
-        form_data.billing_id = billing.id;
 
-        Self::set_account_active_model(&form_data)
-        .await
-        .insert(db)
-        .await
+        if billing.credits != 0 {
+            LedgerEntryMutationCore::create_ledger_entry(
+                &txn,
+                ledger_entry::Model {
+                    id: new_id(),
+                    billing_id: billing.id,
+                    account_id: Some(account.id),
+                    amount: billing.credits,
+                    balance: billing.credits,
+                    reason: LedgerReason::SignupGrant,
+                    service: None,
+                    request_id: None,
+                    created_at: billing.created_at,
+                },
+            )
+            .await?;
+        }
+
+        provider_identity::ActiveModel {
+            id: Set(identity.id.to_owned()),
+            account_id: Set(account.id),
+            provider: Set(identity.provider.to_owned()),
+            subject: Set(identity.subject.to_owned()),
+            email: Set(identity.email.to_owned()),
+            email_verified: Set(identity.email_verified.to_owned()),
+            created_at: Set(identity.created_at.to_owned()),
+            updated_at: Set(identity.updated_at.to_owned()),
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(account)
     }
 
     pub async fn update_account_by_id(
@@ -62,37 +196,285 @@ impl AccountMutationCore {
             .await
     }
 
-    pub async fn update_account_billing_by_account_id(
+    /// # Patch Account Profile
+    ///
+    /// Writes only the columns set in the patch and bumps `updated_at`, so concurrent
+    /// changes to other columns are not overwritten.
+    pub async fn patch_account_profile(
         db: &DbConn,
-        account_id: ID,
-        form_data: billing::Model,
-    ) -> Result<billing::Model, DbErr> {
-        let _: billing::ActiveModel = billing::Entity::find()
-            .filter(billing::Column::AccountId.eq(account_id))
-            .one(db)
-            .await?
-            .ok_or(DbErr::Custom("Cannot find billing.".to_owned()))
-            .map(Into::into)?;
+        id: ID,
+        patch: AccountProfilePatch,
+        now: Timestamp,
+    ) -> Result<Model, DbErr> {
+        let mut model = ActiveModel {
+            id: Unchanged(id),
+            updated_at: Set(now),
+            ..Default::default()
+        };
 
-        billing::ActiveModel {
-            id: Set(form_data.id.to_owned()),
-            account_id: Set(form_data.account_id.to_owned()),
-            credits: Set(form_data.credits.to_owned()),
-            total_spent_usd: Set(form_data.total_spent_usd.to_owned()),
-            created_at: Set(form_data.created_at.to_owned()),
-            updated_at: Set(form_data.updated_at.to_owned()),
+        if let Some(name) = patch.name {
+            model.name = Set(name);
+        }
+        if let Some(email) = patch.email {
+            model.email = Set(email);
+        }
+        if let Some(avatar) = patch.avatar {
+            model.avatar = Set(avatar);
+        }
+        if let Some(locked_fields) = patch.locked_fields {
+            model.locked_fields = Set(locked_fields);
+        }
+        if let Some(preferences) = patch.preferences {
+            model.preferences = Set(preferences);
+        }
+
+        model.update(db).await
+    }
+
+    /// # Request Account Deletion
+    ///
+    /// Schedules the account for deletion, it is purged once the grace period ends unless
+    /// restored before. Returns false when the account was already scheduled or is gone.
+    pub async fn request_account_deletion(
+        db: &DbConn,
+        id: ID,
+        reason: Option<String>,
+        now: Timestamp,
+    ) -> Result<bool, DbErr> {
+        let result = account::Entity::update_many()
+            .col_expr(account::Column::DeletionRequestedAt, Expr::value(now))
+            .col_expr(account::Column::DeletionReason, Expr::value(reason))
+            .col_expr(account::Column::UpdatedAt, Expr::value(now))
+            .filter(account::Column::Id.eq(id))
+            .filter(account::Column::Deleted.eq(false))
+            .filter(account::Column::DeletionRequestedAt.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    /// # Restore Account
+    ///
+    /// Cancels a scheduled deletion. Returns false when there was none to cancel, which is
+    /// also the case once the account was purged.
+    pub async fn restore_account(db: &DbConn, id: ID, now: Timestamp) -> Result<bool, DbErr> {
+        let result = account::Entity::update_many()
+            .col_expr(account::Column::DeletionRequestedAt, Expr::value(Option::<Timestamp>::None))
+            .col_expr(account::Column::DeletionReason, Expr::value(Option::<String>::None))
+            .col_expr(account::Column::UpdatedAt, Expr::value(now))
+            .filter(account::Column::Id.eq(id))
+            .filter(account::Column::Deleted.eq(false))
+            .filter(account::Column::DeletionRequestedAt.is_not_null())
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    /// # Purge Account
+    ///
+    /// Removes everything personal the account has: billing, identities, sessions, tokens,
+    /// keys, service usage and organization memberships are deleted, and the account row is kept anonymised
+    /// and marked deleted so its id is never reused. Organizations it is the only owner of
+    /// are handed to another member first. Ledger entries stay, as they only hold ids and
+    /// amounts. Runs in one transaction.
+    pub async fn purge_account(db: &DbConn, account: &Model, now: Timestamp) -> Result<(), DbErr> {
+        let txn = db.begin().await?;
+
+        billing::Entity::delete_many()
+            .filter(billing::Column::AccountId.eq(account.id))
+            .exec(&txn)
+            .await?;
+        provider_identity::Entity::delete_many()
+            .filter(provider_identity::Column::AccountId.eq(account.id))
+            .exec(&txn)
+            .await?;
+        refresh_token::Entity::delete_many()
+            .filter(refresh_token::Column::AccountId.eq(account.id))
+            .exec(&txn)
+            .await?;
+        session::Entity::delete_many()
+            .filter(session::Column::AccountId.eq(account.id))
+            .exec(&txn)
+            .await?;
+        api_key::Entity::delete_many()
+            .filter(api_key::Column::AccountId.eq(account.id))
+            .exec(&txn)
+            .await?;
+        oauth_state::Entity::delete_many()
+            .filter(oauth_state::Column::LinkAccountId.eq(account.id))
+            .exec(&txn)
+            .await?;
+        magic_link::Entity::delete_many()
+            .filter(magic_link::Column::Email.eq(account.email.to_lowercase()))
+            .exec(&txn)
+            .await?;
+        service_usage::Entity::delete_many()
+            .filter(service_usage::Column::AccountId.eq(account.id))
+            .exec(&txn)
+            .await?;
+        OrganizationMemberMutationCore::hand_over_ownership(&txn, account.id, now).await?;
+        organization_member::Entity::delete_many()
+            .filter(organization_member::Column::AccountId.eq(account.id))
+            .exec(&txn)
+            .await?;
+        organization_invitation::Entity::delete_many()
+            .filter(organization_invitation::Column::Email.eq(account.email.to_lowercase()))
+            .filter(organization_invitation::Column::AcceptedAt.is_null())
+            .exec(&txn)
+            .await?;
+
+        ActiveModel {
+            id: Unchanged(account.id),
+            email: Set(String::new()),
+            name: Set(PURGED_ACCOUNT_NAME.to_string()),
+            avatar: Set(None),
+            flags: Set(vec![]),
+            locked_fields: Set(vec![]),
+            preferences: Set(Json::Object(Default::default())),
+            updated_at: Set(now),
+            deleted: Set(true),
+            deletion_reason: Set(None),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+
+        txn.commit().await
+    }
+
+    /// # Set Account Flags
+    ///
+    /// Replaces the flags of the account.
+    pub async fn set_account_flags(
+        db: &DbConn,
+        id: ID,
+        flags: Vec<AccountFlags>,
+        now: Timestamp,
+    ) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Unchanged(id),
+            flags: Set(flags),
+            updated_at: Set(now),
+            ..Default::default()
         }
         .update(db)
         .await
     }
+
+    /// # Suspend Account
+    ///
+    /// Blocks the account without touching its data, until `until` or until lifted. Returns
+    /// false when it is already suspended or was deleted.
+    pub async fn suspend_account(
+        db: &DbConn,
+        id: ID,
+        reason: String,
+        suspended_by: ID,
+        until: Option<Timestamp>,
+        now: Timestamp,
+    ) -> Result<bool, DbErr> {
+        let result = account::Entity::update_many()
+            .col_expr(account::Column::SuspendedAt, Expr::value(now))
+            .col_expr(account::Column::SuspensionReason, Expr::value(reason))
+            .col_expr(account::Column::SuspendedBy, Expr::value(suspended_by))
+            .col_expr(account::Column::SuspendedUntil, Expr::value(until))
+            .col_expr(account::Column::UpdatedAt, Expr::value(now))
+            .filter(account::Column::Id.eq(id))
+            .filter(account::Column::Deleted.eq(false))
+            .filter(
+                Condition::any()
+                    .add(account::Column::SuspendedAt.is_null())
+                    .add(account::Column::SuspendedUntil.lte(now)),
+            )
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    /// # Lift Account Suspension
+    ///
+    /// Returns false when the account was not suspended, or its suspension had already run
+    /// out.
+    pub async fn lift_account_suspension(db: &DbConn, id: ID, now: Timestamp) -> Result<bool, DbErr> {
+        let result = Self::clear_suspensions()
+            .col_expr(account::Column::UpdatedAt, Expr::value(now))
+            .filter(account::Column::Id.eq(id))
+            .filter(
+                Condition::any()
+                    .add(account::Column::SuspendedUntil.is_null())
+                    .add(account::Column::SuspendedUntil.gt(now)),
+            )
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    /// # Lift Expired Suspensions
+    ///
+    /// Clears every suspension whose end time has passed. Returns how many were lifted.
+    pub async fn lift_expired_suspensions(db: &DbConn, now: Timestamp) -> Result<u64, DbErr> {
+        let result = Self::clear_suspensions()
+            .col_expr(account::Column::UpdatedAt, Expr::value(now))
+            .filter(account::Column::SuspendedUntil.lte(now))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+
+    fn clear_suspensions() -> UpdateMany<account::Entity> {
+        account::Entity::update_many()
+            .col_expr(account::Column::SuspendedAt, Expr::value(Option::<Timestamp>::None))
+            .col_expr(account::Column::SuspensionReason, Expr::value(Option::<String>::None))
+            .col_expr(account::Column::SuspendedBy, Expr::value(Option::<ID>::None))
+            .col_expr(account::Column::SuspendedUntil, Expr::value(Option::<Timestamp>::None))
+            .filter(account::Column::SuspendedAt.is_not_null())
+    }
+
+    /// # Adjust Account Credits
+    ///
+    /// Adds `amount` to the credits, which may be negative, and writes it to the ledger.
+    /// Returns `None` when the balance would go below zero or the account has no billing.
+    pub async fn adjust_account_credits(
+        db: &DbConn,
+        account_id: ID,
+        amount: i64,
+        movement: &CreditMovement,
+        now: Timestamp,
+    ) -> Result<Option<billing::Model>, DbErr> {
+        let txn = db.begin().await?;
+
+        let billing = LedgerEntryMutationCore::move_credits(
+            &txn,
+            Condition::all().add(billing::Column::AccountId.eq(account_id)),
+            amount,
+            movement,
+            now,
+        )
+        .await?;
+
+        match billing {
+            Some(_) => txn.commit().await?,
+            None => txn.rollback().await?,
+        }
+
+        Ok(billing)
+    }
 }
 
 impl AccountQueryCore {
     /// # Get Account By ID
     ///
-    /// Get the account by its ID.
+    /// Get the account by its ID, unless it was deleted.
     pub async fn get_account_by_id(db: &DbConn, id: ID) -> Result<Option<Model>, DbErr> {
-        let model = account::Entity::find_by_id(id).one(db).await?;
+        let model = account::Entity::find_by_id(id)
+            .filter(account::Column::Deleted.eq(false))
+            .one(db)
+            .await?;
 
         let acc = match model {
             Some(m) => m,
@@ -109,6 +491,7 @@ impl AccountQueryCore {
         let model = account::Entity::find()
             .find_also_related(billing::Entity)
             .filter(account::Column::Id.eq(id))
+            .filter(account::Column::Deleted.eq(false))
             .one(db)
             .await?;
 
@@ -120,12 +503,16 @@ impl AccountQueryCore {
         Ok(Some((m, b)))
     }
 
-    pub async fn get_account_by_google_id(
+    /// # Get Account By Email
+    ///
+    /// Get the account by its email address, unless it was deleted.
+    pub async fn get_account_by_email(
         db: &DbConn,
-        google_id: &str,
+        email: &str,
     ) -> Result<Option<Model>, DbErr> {
         let model = account::Entity::find()
-            .filter(account::Column::GoogleId.eq(google_id))
+            .filter(account::Column::Email.eq(email))
+            .filter(account::Column::Deleted.eq(false))
             .one(db)
             .await?;
 
@@ -135,21 +522,112 @@ impl AccountQueryCore {
         }
     }
 
-    /// # Get Account By Email
+    /// # Get Accounts Due For Purge
     ///
-    /// Get the account by its email address.
-    pub async fn get_account_by_email(
+    /// Accounts whose deletion was requested before `requested_before` and that still have
+    /// to be purged, oldest first.
+    pub async fn get_accounts_due_for_purge(
         db: &DbConn,
-        email: &str,
-    ) -> Result<Option<Model>, DbErr> {
-        let model = account::Entity::find()
-            .filter(account::Column::Email.eq(email))
-            .one(db)
-            .await?;
+        requested_before: Timestamp,
+        limit: u64,
+    ) -> Result<Vec<Model>, DbErr> {
+        account::Entity::find()
+            .filter(account::Column::Deleted.eq(false))
+            .filter(account::Column::DeletionRequestedAt.lt(requested_before))
+            .order_by_asc(account::Column::DeletionRequestedAt)
+            .limit(limit)
+            .all(db)
+            .await
+    }
 
-        match model {
-            Some(m) => Ok(Some(m)),
-            None => Ok(None),
+    /// # Search Accounts
+    ///
+    /// A page of the accounts matching the search, and how many match in total.
+    pub async fn search_accounts(
+        db: &DbConn,
+        search: &AccountSearch,
+    ) -> Result<(Vec<Model>, u64), DbErr> {
+        let mut select = account::Entity::find().filter(account::Column::Deleted.eq(false));
+
+        if let Some(query) = search.query.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+            let escaped = query.to_lowercase().replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            let pattern = format!("%{}%", escaped);
+
+            let mut matches = Condition::any()
+                .add(Expr::expr(Func::lower(Expr::col(account::Column::Email))).like(LikeExpr::new(&pattern).escape('\\')))
+                .add(Expr::expr(Func::lower(Expr::col(account::Column::Name))).like(LikeExpr::new(&pattern).escape('\\')))
+                .add(
+                    account::Column::Id.in_subquery(
+                        Query::select()
+                            .column(provider_identity::Column::AccountId)
+                            .from(provider_identity::Entity)
+                            .and_where(provider_identity::Column::Subject.eq(query))
+                            .to_owned(),
+                    ),
+                );
+            if let Ok(id) = query.parse::<ID>() {
+                matches = matches.add(account::Column::Id.eq(id));
+            }
+
+            select = select.filter(matches);
+        }
+
+        select = match search.descending {
+            true => select.order_by_desc(search.sort.column()),
+            false => select.order_by_asc(search.sort.column()),
+        };
+        // ties keep a stable order across pages
+        if search.sort != AccountSort::Id {
+            select = select.order_by_asc(account::Column::Id);
+        }
+
+        let paginator = select.paginate(db, search.per_page);
+        let total = paginator.num_items().await?;
+        let accounts = paginator.fetch_page(search.page).await?;
+
+        Ok((accounts, total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::organization_member;
+
+    fn account() -> Model {
+        Model {
+            deletion_requested_at: Some(0),
+            ..Model::fixture(7)
         }
     }
+
+    #[actix_web::test]
+    async fn purge_deletes_service_usage_in_the_same_transaction() {
+        let purged = Model {
+            email: String::new(),
+            name: PURGED_ACCOUNT_NAME.to_string(),
+            deleted: true,
+            ..account()
+        };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<organization_member::Model>::new()])
+            .append_query_results([vec![purged]])
+            .append_exec_results((0..10).map(|_| MockExecResult {
+                last_insert_id: 0,
+                rows_affected: 1,
+            }))
+            .into_connection();
+
+        AccountMutationCore::purge_account(&db, &account(), 1).await.unwrap();
+
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 1, "the purge must run in a single transaction");
+
+        let delete_usage = Statement::from_sql_and_values(
+            DatabaseBackend::Postgres,
+            r#"DELETE FROM "service_usages" WHERE "service_usages"."account_id" = $1"#,
+            [account().id.into()],
+        );
+        assert!(format!("{:?}", log[0]).contains(&format!("{:?}", delete_usage)));
+    }
 }
@@ -0,0 +1,42 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::DeriveEntityModel;
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # Admin Action
+///
+/// Audit log of what staff did through the admin API, to an account or to settings like
+/// feature flags. Rows are only ever added, and outlive the accounts they are about.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "admin_actions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    /// Who did it
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub admin_account_id: ID,
+    /// Who it was done to, `None` for changes that are not about one account
+    #[sea_orm(column_type = "BigInteger", nullable, indexed)]
+    pub target_account_id: Option<ID>,
+
+    /// What was done, e.g. `set_flags`, `adjust_credits` or `save_feature_flag`
+    #[sea_orm(column_type = "Text")]
+    pub action: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub reason: Option<String>,
+    /// What changed, depends on the action
+    #[sea_orm(column_type = "JsonBinary")]
+    pub details: Json,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,41 @@
+use sea_orm::*;
+use crate::ID;
+
+use super::admin_action::{self, ActiveModel, Model};
+
+pub struct AdminActionMutationCore;
+pub struct AdminActionQueryCore;
+
+impl AdminActionMutationCore {
+    pub async fn create_admin_action(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            admin_account_id: Set(form_data.admin_account_id.to_owned()),
+            target_account_id: Set(form_data.target_account_id.to_owned()),
+            action: Set(form_data.action.to_owned()),
+            reason: Set(form_data.reason.to_owned()),
+            details: Set(form_data.details.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+}
+
+impl AdminActionQueryCore {
+    /// # Get Admin Actions By Target Account ID
+    ///
+    /// What staff did to the account, newest first.
+    pub async fn get_admin_actions_by_target_account_id(
+        db: &DbConn,
+        target_account_id: ID,
+        limit: u64,
+    ) -> Result<Vec<Model>, DbErr> {
+        admin_action::Entity::find()
+            .filter(admin_action::Column::TargetAccountId.eq(target_account_id))
+            .order_by_desc(admin_action::Column::CreatedAt)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+}
@@ -0,0 +1,69 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{DeriveEntityModel, DeriveRelation};
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+pub static API_KEY_PREFIX: &str = "gl_";
+
+// Scopes an API key can be limited to
+pub static SCOPE_ACCOUNTS_READ: &str = "accounts:read";
+pub static SCOPE_SERVICES_SUMMARIZE: &str = "services:summarize";
+pub static API_KEY_SCOPES: [&str; 2] = [SCOPE_ACCOUNTS_READ, SCOPE_SERVICES_SUMMARIZE];
+
+/// # API Key
+///
+/// A personal key for scripts. Only the SHA-256 of the key is stored; the `prefix` is the
+/// part shown to the user to tell keys apart, and how a key is looked up.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "api_keys")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub account_id: ID,
+
+    #[sea_orm(column_type = "Text")]
+    pub name: String,
+
+    #[sea_orm(column_type = "Text", unique, indexed)]
+    pub prefix: String,
+
+    #[serde(skip_serializing)]
+    #[sea_orm(column_type = "Text")]
+    pub key_hash: String,
+
+    pub scopes: Vec<String>,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub expires_at: Option<Timestamp>,
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub last_used_at: Option<Timestamp>,
+
+    // Revocation
+    #[sea_orm(column_type = "Boolean")]
+    pub revoked: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entities::account::Entity",
+        from = "Column::AccountId",
+        to = "crate::entities::account::Column::Id"
+    )]
+    Account,
+}
+
+impl Related<crate::entities::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
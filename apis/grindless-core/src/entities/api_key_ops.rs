@@ -0,0 +1,112 @@
+use sea_orm::{sea_query::Expr, *};
+use crate::{Timestamp, ID};
+
+use super::api_key::{self, ActiveModel, Model};
+
+pub struct ApiKeyMutationCore;
+pub struct ApiKeyQueryCore;
+
+impl ApiKeyMutationCore {
+    pub async fn create_api_key(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            account_id: Set(form_data.account_id.to_owned()),
+            name: Set(form_data.name.to_owned()),
+            prefix: Set(form_data.prefix.to_owned()),
+            key_hash: Set(form_data.key_hash.to_owned()),
+            scopes: Set(form_data.scopes.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+            expires_at: Set(form_data.expires_at.to_owned()),
+            last_used_at: Set(form_data.last_used_at.to_owned()),
+            revoked: Set(form_data.revoked.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// # Rename API Key
+    ///
+    /// Returns `false` if the account has no such active key.
+    pub async fn rename_api_key(
+        db: &DbConn,
+        account_id: ID,
+        id: ID,
+        name: &str,
+    ) -> Result<bool, DbErr> {
+        let result = api_key::Entity::update_many()
+            .col_expr(api_key::Column::Name, Expr::value(name))
+            .filter(api_key::Column::Id.eq(id))
+            .filter(api_key::Column::AccountId.eq(account_id))
+            .filter(api_key::Column::Revoked.eq(false))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    /// # Revoke API Key
+    ///
+    /// Returns `false` if the account has no such active key.
+    pub async fn revoke_api_key(db: &DbConn, account_id: ID, id: ID) -> Result<bool, DbErr> {
+        let result = api_key::Entity::update_many()
+            .col_expr(api_key::Column::Revoked, Expr::value(true))
+            .filter(api_key::Column::Id.eq(id))
+            .filter(api_key::Column::AccountId.eq(account_id))
+            .filter(api_key::Column::Revoked.eq(false))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+
+    pub async fn touch_api_key(db: &DbConn, id: ID, last_used_at: Timestamp) -> Result<(), DbErr> {
+        api_key::Entity::update_many()
+            .col_expr(api_key::Column::LastUsedAt, Expr::value(last_used_at))
+            .filter(api_key::Column::Id.eq(id))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl ApiKeyQueryCore {
+    /// # Get API Key By Prefix
+    ///
+    /// Get the key by its public prefix, revoked or not.
+    pub async fn get_api_key_by_prefix(db: &DbConn, prefix: &str) -> Result<Option<Model>, DbErr> {
+        api_key::Entity::find()
+            .filter(api_key::Column::Prefix.eq(prefix))
+            .one(db)
+            .await
+    }
+
+    /// # Get API Keys By Account ID
+    ///
+    /// Get every key of the account that has not been revoked, newest first.
+    pub async fn get_api_keys_by_account_id(
+        db: &DbConn,
+        account_id: ID,
+    ) -> Result<Vec<Model>, DbErr> {
+        api_key::Entity::find()
+            .filter(api_key::Column::AccountId.eq(account_id))
+            .filter(api_key::Column::Revoked.eq(false))
+            .order_by_desc(api_key::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    /// # Get All API Keys By Account ID
+    ///
+    /// Get every key of the account, revoked ones included, newest first.
+    pub async fn get_all_api_keys_by_account_id(
+        db: &DbConn,
+        account_id: ID,
+    ) -> Result<Vec<Model>, DbErr> {
+        api_key::Entity::find()
+            .filter(api_key::Column::AccountId.eq(account_id))
+            .order_by_desc(api_key::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+}
@@ -4,6 +4,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::{ID, Timestamp};
 
+/// # Billing
+///
+/// A credit balance. Belongs to either one account or one organization, whose members
+/// spend from it.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, DeriveEntityModel)]
 #[serde(rename_all = "snake_case")]
 #[sea_orm(table_name = "billings")]
@@ -11,8 +15,12 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: ID,
 
-    #[sea_orm(column_type = "BigInteger")]
-    pub account_id: ID,
+    /// The account that owns the credits, unset for organization billing
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub account_id: Option<ID>,
+    /// The organization that owns the credits, unset for account billing
+    #[sea_orm(column_type = "BigInteger", nullable, indexed)]
+    pub organization_id: Option<ID>,
 
     #[sea_orm(column_type = "BigInteger")]
     pub credits: i64,
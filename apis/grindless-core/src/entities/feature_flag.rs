@@ -0,0 +1,49 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::DeriveEntityModel;
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+use super::account::AccountFlags;
+
+/// # Feature Flag
+///
+/// Turns a feature on for some accounts only, so it can ship before everyone sees it. See
+/// `feature_flags::is_enabled` for who gets it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "feature_flags")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    /// What the code and the client check for, e.g. `summarize_v2`
+    #[sea_orm(column_type = "Text", unique, indexed)]
+    pub key: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description: Option<String>,
+
+    /// Off turns the feature off for everyone, whatever the targeting says
+    #[sea_orm(column_type = "Boolean")]
+    pub enabled: bool,
+
+    // Targeting
+    /// Accounts with any of these flags get the feature
+    pub account_flags: Vec<AccountFlags>,
+    /// These accounts get the feature
+    pub account_ids: Vec<ID>,
+    /// Share of the other accounts that get the feature, from 0 to 100
+    #[sea_orm(column_type = "Integer")]
+    pub rollout_percentage: i32,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger")]
+    pub updated_at: Timestamp,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
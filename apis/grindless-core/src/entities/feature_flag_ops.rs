@@ -0,0 +1,82 @@
+use sea_orm::{sea_query::OnConflict, *};
+
+use super::feature_flag::{self, ActiveModel, Model};
+
+pub struct FeatureFlagMutationCore;
+pub struct FeatureFlagQueryCore;
+
+impl FeatureFlagMutationCore {
+    /// # Save Feature Flag
+    ///
+    /// Creates the flag, or replaces the flag with the same key keeping its id and
+    /// `created_at`. A single upsert, so concurrent saves of one key don't collide.
+    pub async fn save_feature_flag(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        let model = ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            key: Set(form_data.key.to_owned()),
+            description: Set(form_data.description.to_owned()),
+            enabled: Set(form_data.enabled.to_owned()),
+            account_flags: Set(form_data.account_flags.to_owned()),
+            account_ids: Set(form_data.account_ids.to_owned()),
+            rollout_percentage: Set(form_data.rollout_percentage.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+            updated_at: Set(form_data.updated_at.to_owned()),
+        };
+
+        feature_flag::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(feature_flag::Column::Key)
+                    .update_columns([
+                        feature_flag::Column::Description,
+                        feature_flag::Column::Enabled,
+                        feature_flag::Column::AccountFlags,
+                        feature_flag::Column::AccountIds,
+                        feature_flag::Column::RolloutPercentage,
+                        feature_flag::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec_with_returning(db)
+            .await
+    }
+
+    /// # Delete Feature Flag
+    ///
+    /// Returns false when there was no flag with the key. The feature is off for everyone
+    /// once its flag is gone.
+    pub async fn delete_feature_flag(db: &DbConn, key: &str) -> Result<bool, DbErr> {
+        let result = feature_flag::Entity::delete_many()
+            .filter(feature_flag::Column::Key.eq(key))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+}
+
+impl FeatureFlagQueryCore {
+    pub async fn get_feature_flag_by_key(db: &DbConn, key: &str) -> Result<Option<Model>, DbErr> {
+        feature_flag::Entity::find()
+            .filter(feature_flag::Column::Key.eq(key))
+            .one(db)
+            .await
+    }
+
+    pub async fn get_all_feature_flags(db: &DbConn) -> Result<Vec<Model>, DbErr> {
+        feature_flag::Entity::find()
+            .order_by_asc(feature_flag::Column::Key)
+            .all(db)
+            .await
+    }
+
+    /// # Get Enabled Feature Flags
+    ///
+    /// The flags not switched off, the only ones that can be on for anyone.
+    pub async fn get_enabled_feature_flags(db: &DbConn) -> Result<Vec<Model>, DbErr> {
+        feature_flag::Entity::find()
+            .filter(feature_flag::Column::Enabled.eq(true))
+            .order_by_asc(feature_flag::Column::Key)
+            .all(db)
+            .await
+    }
+}
@@ -0,0 +1,77 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{DeriveActiveEnum, DeriveEntityModel};
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # Ledger Entry
+///
+/// One change to a credit balance. Rows are only ever added, in the same transaction as the
+/// change, so the entries of a billing always add up to its credits. A trigger rejects any
+/// update or delete.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "ledger_entries")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub billing_id: ID,
+    /// Who caused it: the account that spent or got the credits, or the admin who
+    /// adjusted them
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub account_id: Option<ID>,
+
+    /// Credits added, negative when taken away
+    #[sea_orm(column_type = "BigInteger")]
+    pub amount: i64,
+    /// The credits right after this entry
+    #[sea_orm(column_type = "BigInteger")]
+    pub balance: i64,
+
+    pub reason: LedgerReason,
+    /// The service charged or refunded, e.g. `summarize`
+    #[sea_orm(column_type = "Text", nullable)]
+    pub service: Option<String>,
+    /// Ties entries of the same request together, like a charge and its refund
+    #[sea_orm(column_type = "BigInteger", nullable, indexed)]
+    pub request_id: Option<ID>,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+/// # Ledger Reason
+///
+/// Why a balance changed.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, EnumIter, DeriveActiveEnum,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerReason {
+    /// The starter credits of a new account
+    #[sea_orm(num_value = 1)]
+    SignupGrant,
+    #[sea_orm(num_value = 2)]
+    ServiceCharge,
+    /// A charge given back, for a request that failed
+    #[sea_orm(num_value = 3)]
+    Refund,
+    /// Credits granted or taken away by staff
+    #[sea_orm(num_value = 5)]
+    AdminAdjustment,
+    /// Credits moved between an account and an organization
+    #[sea_orm(num_value = 6)]
+    Transfer,
+    /// The balance billings had when the ledger was introduced
+    #[sea_orm(num_value = 7)]
+    OpeningBalance,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
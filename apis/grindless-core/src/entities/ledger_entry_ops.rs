@@ -0,0 +1,170 @@
+use sea_orm::{sea_query::Expr, *};
+use crate::{util::snowflake::new_id, ID, Timestamp};
+
+use super::{
+    billing,
+    ledger_entry::{self, ActiveModel, LedgerReason, Model},
+};
+
+pub struct LedgerEntryMutationCore;
+pub struct LedgerEntryQueryCore;
+
+/// # Credit Movement
+///
+/// What gets written to the ledger along with a balance change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreditMovement {
+    pub reason: LedgerReason,
+    pub account_id: Option<ID>,
+    pub service: Option<String>,
+    pub request_id: Option<ID>,
+}
+
+impl CreditMovement {
+    pub fn new(reason: LedgerReason, account_id: ID) -> Self {
+        CreditMovement {
+            reason,
+            account_id: Some(account_id),
+            service: None,
+            request_id: None,
+        }
+    }
+
+    /// A charge or refund of a service request
+    pub fn service(reason: LedgerReason, account_id: ID, service: &str, request_id: ID) -> Self {
+        CreditMovement {
+            reason,
+            account_id: Some(account_id),
+            service: Some(service.to_string()),
+            request_id: Some(request_id),
+        }
+    }
+}
+
+/// # Ledger Mismatch
+///
+/// A billing whose credits don't add up to its ledger.
+#[derive(Debug, Clone, PartialEq, Eq, FromQueryResult)]
+pub struct LedgerMismatch {
+    pub billing_id: ID,
+    pub credits: i64,
+    pub ledger_total: i64,
+}
+
+impl LedgerEntryMutationCore {
+    pub async fn create_ledger_entry<C: ConnectionTrait>(db: &C, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            billing_id: Set(form_data.billing_id.to_owned()),
+            account_id: Set(form_data.account_id.to_owned()),
+            amount: Set(form_data.amount.to_owned()),
+            balance: Set(form_data.balance.to_owned()),
+            reason: Set(form_data.reason.to_owned()),
+            service: Set(form_data.service.to_owned()),
+            request_id: Set(form_data.request_id.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// # Move Credits
+    ///
+    /// Adds `amount` to the billing matching `owner`, and writes it to the ledger with the
+    /// resulting balance. Credits are only taken away when there are enough, `None` is
+    /// returned otherwise and nothing is written. Run it inside a transaction, so the
+    /// balance and the ledger can't disagree.
+    pub async fn move_credits<C: ConnectionTrait>(
+        db: &C,
+        owner: Condition,
+        amount: i64,
+        movement: &CreditMovement,
+        now: Timestamp,
+    ) -> Result<Option<billing::Model>, DbErr> {
+        let mut update = billing::Entity::update_many()
+            .col_expr(billing::Column::Credits, Expr::col(billing::Column::Credits).add(amount))
+            .col_expr(billing::Column::UpdatedAt, Expr::value(now))
+            .filter(owner);
+        if amount < 0 {
+            let needed = amount
+                .checked_neg()
+                .ok_or(DbErr::Custom(format!("Can't take {} credits away", amount)))?;
+            update = update.filter(billing::Column::Credits.gte(needed));
+        }
+
+        let billing = match update.exec_with_returning(db).await?.into_iter().next() {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+
+        Self::create_ledger_entry(
+            db,
+            Model {
+                id: new_id(),
+                billing_id: billing.id,
+                account_id: movement.account_id,
+                amount,
+                balance: billing.credits,
+                reason: movement.reason,
+                service: movement.service.clone(),
+                request_id: movement.request_id,
+                created_at: now,
+            },
+        )
+        .await?;
+
+        Ok(Some(billing))
+    }
+}
+
+impl LedgerEntryQueryCore {
+    /// # Get Ledger Entries By Billing ID
+    ///
+    /// The latest changes to the balance, newest first.
+    pub async fn get_ledger_entries_by_billing_id(
+        db: &DbConn,
+        billing_id: ID,
+        limit: Option<u64>,
+    ) -> Result<Vec<Model>, DbErr> {
+        ledger_entry::Entity::find()
+            .filter(ledger_entry::Column::BillingId.eq(billing_id))
+            .order_by_desc(ledger_entry::Column::CreatedAt)
+            .order_by_desc(ledger_entry::Column::Id)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// # Get Ledger Total
+    ///
+    /// What the ledger says the balance of the billing is.
+    pub async fn get_ledger_total(db: &DbConn, billing_id: ID) -> Result<i64, DbErr> {
+        let total: Option<i64> = ledger_entry::Entity::find()
+            .select_only()
+            .column_as(Expr::cust("COALESCE(SUM(amount), 0)::BIGINT"), "total")
+            .filter(ledger_entry::Column::BillingId.eq(billing_id))
+            .into_tuple()
+            .one(db)
+            .await?;
+
+        Ok(total.unwrap_or(0))
+    }
+
+    /// # Get Ledger Mismatches
+    ///
+    /// Billings whose credits are not the sum of their ledger, which means a balance was
+    /// changed without going through `move_credits`. Empty when everything reconciles.
+    pub async fn get_ledger_mismatches(db: &DbConn, limit: u64) -> Result<Vec<LedgerMismatch>, DbErr> {
+        LedgerMismatch::find_by_statement(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            "SELECT b.id AS billing_id, b.credits, COALESCE(SUM(l.amount), 0)::BIGINT AS ledger_total \
+            FROM billings b LEFT JOIN ledger_entries l ON l.billing_id = b.id \
+            GROUP BY b.id, b.credits \
+            HAVING b.credits <> COALESCE(SUM(l.amount), 0) \
+            ORDER BY b.id LIMIT $1",
+            [(limit as i64).into()],
+        ))
+        .all(db)
+        .await
+    }
+}
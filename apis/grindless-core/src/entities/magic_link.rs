@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::DeriveEntityModel;
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # Magic Link
+///
+/// A single-use login link mailed to `email`. Only the SHA-256 of the token is stored, the
+/// token itself is only ever in the mail.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "magic_links")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "Text", indexed)]
+    pub email: String,
+    #[sea_orm(column_type = "Text", unique, indexed)]
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger")]
+    pub expires_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub used_at: Option<Timestamp>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,78 @@
+use sea_orm::{sea_query::Expr, *};
+use crate::Timestamp;
+
+use super::magic_link::{self, ActiveModel, Model};
+
+pub struct MagicLinkMutationCore;
+pub struct MagicLinkQueryCore;
+
+impl MagicLinkMutationCore {
+    pub async fn create_magic_link(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            email: Set(form_data.email.to_owned()),
+            token_hash: Set(form_data.token_hash.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+            expires_at: Set(form_data.expires_at.to_owned()),
+            used_at: Set(form_data.used_at.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// # Consume Magic Link
+    ///
+    /// Marks the link as used, only if it is unused and not expired, in a single statement.
+    /// Returns `None` otherwise, so a link logs in exactly once.
+    pub async fn consume_magic_link(
+        db: &DbConn,
+        token_hash: &str,
+        now: Timestamp,
+    ) -> Result<Option<Model>, DbErr> {
+        let result = magic_link::Entity::update_many()
+            .col_expr(magic_link::Column::UsedAt, Expr::value(now))
+            .filter(magic_link::Column::TokenHash.eq(token_hash))
+            .filter(magic_link::Column::UsedAt.is_null())
+            .filter(magic_link::Column::ExpiresAt.gt(now))
+            .exec(db)
+            .await?;
+
+        if result.rows_affected != 1 {
+            return Ok(None);
+        }
+
+        magic_link::Entity::find()
+            .filter(magic_link::Column::TokenHash.eq(token_hash))
+            .one(db)
+            .await
+    }
+
+    /// # Delete Expired Magic Links
+    ///
+    /// Links that were never used, or used already.
+    pub async fn delete_expired_magic_links(db: &DbConn, now: Timestamp) -> Result<u64, DbErr> {
+        let result = magic_link::Entity::delete_many()
+            .filter(magic_link::Column::ExpiresAt.lte(now))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+}
+
+impl MagicLinkQueryCore {
+    /// # Count Magic Links Since
+    ///
+    /// How many links were sent to `email` since `since`, to keep the inbox from being flooded.
+    pub async fn count_magic_links_since(
+        db: &DbConn,
+        email: &str,
+        since: Timestamp,
+    ) -> Result<u64, DbErr> {
+        magic_link::Entity::find()
+            .filter(magic_link::Column::Email.eq(email))
+            .filter(magic_link::Column::CreatedAt.gte(since))
+            .count(db)
+            .await
+    }
+}
@@ -1,3 +1,29 @@
 pub mod account;
 pub mod account_ops;
+pub mod admin_action;
+pub mod admin_action_ops;
+pub mod api_key;
+pub mod api_key_ops;
 pub mod billing;
+pub mod feature_flag;
+pub mod feature_flag_ops;
+pub mod ledger_entry;
+pub mod ledger_entry_ops;
+pub mod magic_link;
+pub mod magic_link_ops;
+pub mod oauth_state;
+pub mod oauth_state_ops;
+pub mod organization;
+pub mod organization_invitation;
+pub mod organization_invitation_ops;
+pub mod organization_member;
+pub mod organization_member_ops;
+pub mod organization_ops;
+pub mod provider_identity;
+pub mod provider_identity_ops;
+pub mod refresh_token;
+pub mod refresh_token_ops;
+pub mod service_usage;
+pub mod service_usage_ops;
+pub mod session;
+pub mod session_ops;
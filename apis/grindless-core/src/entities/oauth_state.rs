@@ -0,0 +1,41 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::DeriveEntityModel;
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # OAuth State
+///
+/// A login started with an OAuth provider and not finished yet. The `id` is the `jti` of the
+/// signed `state` handed to the browser, the PKCE verifier never leaves the server. When
+/// `link_account_id` is set the identity gets linked to that account instead of logging in.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "oauth_states")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "Text")]
+    pub provider: String,
+    #[sea_orm(column_type = "Text")]
+    #[serde(skip_serializing)]
+    pub code_verifier: String,
+    #[sea_orm(column_type = "Text")]
+    pub redirect_uri: String,
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub link_account_id: Option<ID>,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub expires_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub used_at: Option<Timestamp>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,61 @@
+use sea_orm::{sea_query::Expr, *};
+use crate::{Timestamp, ID};
+
+use super::oauth_state::{self, ActiveModel, Model};
+
+pub struct OAuthStateMutationCore;
+
+impl OAuthStateMutationCore {
+    pub async fn create_oauth_state(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            provider: Set(form_data.provider.to_owned()),
+            code_verifier: Set(form_data.code_verifier.to_owned()),
+            redirect_uri: Set(form_data.redirect_uri.to_owned()),
+            link_account_id: Set(form_data.link_account_id.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+            expires_at: Set(form_data.expires_at.to_owned()),
+            used_at: Set(form_data.used_at.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// # Consume OAuth State
+    ///
+    /// Marks the login attempt as used, only if it is for `provider`, unused and not expired,
+    /// in a single statement. Returns `None` otherwise, so a `state` works exactly once.
+    pub async fn consume_oauth_state(
+        db: &DbConn,
+        id: ID,
+        provider: &str,
+        now: Timestamp,
+    ) -> Result<Option<Model>, DbErr> {
+        let result = oauth_state::Entity::update_many()
+            .col_expr(oauth_state::Column::UsedAt, Expr::value(now))
+            .filter(oauth_state::Column::Id.eq(id))
+            .filter(oauth_state::Column::Provider.eq(provider))
+            .filter(oauth_state::Column::UsedAt.is_null())
+            .filter(oauth_state::Column::ExpiresAt.gt(now))
+            .exec(db)
+            .await?;
+
+        if result.rows_affected != 1 {
+            return Ok(None);
+        }
+
+        oauth_state::Entity::find_by_id(id).one(db).await
+    }
+
+    /// # Delete Expired OAuth States
+    ///
+    /// Logins that were never finished.
+    pub async fn delete_expired_oauth_states(db: &DbConn, now: Timestamp) -> Result<u64, DbErr> {
+        let result = oauth_state::Entity::delete_many()
+            .filter(oauth_state::Column::ExpiresAt.lte(now))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected)
+    }
+}
@@ -0,0 +1,46 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{DeriveEntityModel, DeriveRelation};
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # Organization
+///
+/// A team of accounts sharing one credit pool, the billing row `billing_id` points to.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "organizations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "Text")]
+    pub name: String,
+
+    #[sea_orm(column_type = "BigInteger")]
+    pub billing_id: ID,
+
+    /// The account that created it, its first owner
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_by: ID,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger")]
+    pub updated_at: Timestamp,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "crate::entities::organization_member::Entity")]
+    Members,
+}
+
+impl Related<crate::entities::organization_member::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Members.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,47 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::DeriveEntityModel;
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+use super::organization_member::OrganizationRole;
+
+/// # Organization Invitation
+///
+/// An invitation mailed to `email` to join an organization with `role`. Only the SHA-256 of
+/// the token is stored, and only the account using that email can accept it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "organization_invitations")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub organization_id: ID,
+
+    #[sea_orm(column_type = "Text")]
+    pub email: String,
+    pub role: OrganizationRole,
+    #[sea_orm(column_type = "Text", unique, indexed)]
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+
+    #[sea_orm(column_type = "BigInteger")]
+    pub invited_by: ID,
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub accepted_by: Option<ID>,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger")]
+    pub expires_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub accepted_at: Option<Timestamp>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,151 @@
+use sea_orm::{sea_query::Expr, *};
+use crate::{ID, Timestamp};
+
+use super::{
+    organization,
+    organization_invitation::{self, ActiveModel, Model},
+    organization_member,
+};
+
+pub struct OrganizationInvitationMutationCore;
+pub struct OrganizationInvitationQueryCore;
+
+/// # Invitation Acceptance
+///
+/// How accepting an invitation went.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvitationAcceptance {
+    /// Joined, with the new membership
+    Accepted(organization_member::Model),
+    /// The invitation was accepted, revoked or expired in the meantime
+    Unavailable,
+    /// The organization already has `max_members`
+    OrganizationFull,
+}
+
+impl OrganizationInvitationMutationCore {
+    pub async fn create_invitation(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            organization_id: Set(form_data.organization_id.to_owned()),
+            email: Set(form_data.email.to_owned()),
+            role: Set(form_data.role.to_owned()),
+            token_hash: Set(form_data.token_hash.to_owned()),
+            invited_by: Set(form_data.invited_by.to_owned()),
+            accepted_by: Set(form_data.accepted_by.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+            expires_at: Set(form_data.expires_at.to_owned()),
+            accepted_at: Set(form_data.accepted_at.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// # Accept Invitation
+    ///
+    /// Marks the invitation accepted and adds the account to the organization with the
+    /// invited role, in one transaction. The organization row stays locked until then, so
+    /// invitations accepted at the same time can't take it past `max_members`.
+    pub async fn accept_invitation(
+        db: &DbConn,
+        invitation: &Model,
+        member_id: ID,
+        account_id: ID,
+        max_members: u64,
+        now: Timestamp,
+    ) -> Result<InvitationAcceptance, DbErr> {
+        let txn = db.begin().await?;
+
+        organization::Entity::find_by_id(invitation.organization_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+        let members = organization_member::Entity::find()
+            .filter(organization_member::Column::OrganizationId.eq(invitation.organization_id))
+            .count(&txn)
+            .await?;
+        if members >= max_members {
+            txn.rollback().await?;
+            return Ok(InvitationAcceptance::OrganizationFull);
+        }
+
+        let result = organization_invitation::Entity::update_many()
+            .col_expr(organization_invitation::Column::AcceptedAt, Expr::value(now))
+            .col_expr(organization_invitation::Column::AcceptedBy, Expr::value(account_id))
+            .filter(organization_invitation::Column::Id.eq(invitation.id))
+            .filter(organization_invitation::Column::AcceptedAt.is_null())
+            .filter(organization_invitation::Column::ExpiresAt.gt(now))
+            .exec(&txn)
+            .await?;
+        if result.rows_affected != 1 {
+            txn.rollback().await?;
+            return Ok(InvitationAcceptance::Unavailable);
+        }
+
+        let member = organization_member::ActiveModel {
+            id: Set(member_id),
+            organization_id: Set(invitation.organization_id),
+            account_id: Set(account_id),
+            role: Set(invitation.role),
+            spending_cap: Set(None),
+            credits_spent: Set(0),
+            created_at: Set(now),
+            updated_at: Set(now),
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(InvitationAcceptance::Accepted(member))
+    }
+
+    /// # Revoke Invitation
+    ///
+    /// Deletes an invitation that was not accepted yet. Returns false when there was none.
+    pub async fn revoke_invitation(db: &DbConn, organization_id: ID, id: ID) -> Result<bool, DbErr> {
+        let result = organization_invitation::Entity::delete_many()
+            .filter(organization_invitation::Column::Id.eq(id))
+            .filter(organization_invitation::Column::OrganizationId.eq(organization_id))
+            .filter(organization_invitation::Column::AcceptedAt.is_null())
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+}
+
+impl OrganizationInvitationQueryCore {
+    /// # Get Pending Invitation By Token Hash
+    ///
+    /// The invitation, if it was not accepted and has not expired.
+    pub async fn get_pending_invitation_by_token_hash(
+        db: &DbConn,
+        token_hash: &str,
+        now: Timestamp,
+    ) -> Result<Option<Model>, DbErr> {
+        organization_invitation::Entity::find()
+            .filter(organization_invitation::Column::TokenHash.eq(token_hash))
+            .filter(organization_invitation::Column::AcceptedAt.is_null())
+            .filter(organization_invitation::Column::ExpiresAt.gt(now))
+            .one(db)
+            .await
+    }
+
+    /// # Get Pending Invitations By Organization ID
+    ///
+    /// Invitations of the organization still waiting to be accepted, newest first.
+    pub async fn get_pending_invitations_by_organization_id(
+        db: &DbConn,
+        organization_id: ID,
+        now: Timestamp,
+    ) -> Result<Vec<Model>, DbErr> {
+        organization_invitation::Entity::find()
+            .filter(organization_invitation::Column::OrganizationId.eq(organization_id))
+            .filter(organization_invitation::Column::AcceptedAt.is_null())
+            .filter(organization_invitation::Column::ExpiresAt.gt(now))
+            .order_by_desc(organization_invitation::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+}
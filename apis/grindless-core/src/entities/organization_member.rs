@@ -0,0 +1,100 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{DeriveActiveEnum, DeriveEntityModel, DeriveRelation};
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # Organization Member
+///
+/// An account in an organization. `organization_id` and `account_id` together are unique.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "organization_members")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub organization_id: ID,
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub account_id: ID,
+
+    pub role: OrganizationRole,
+
+    /// Most credits the member may spend from the pool, no limit when unset
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub spending_cap: Option<i64>,
+    /// Credits spent from the pool since the member joined or the cap was last reset
+    #[sea_orm(column_type = "BigInteger")]
+    pub credits_spent: i64,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger")]
+    pub updated_at: Timestamp,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entities::organization::Entity",
+        from = "Column::OrganizationId",
+        to = "crate::entities::organization::Column::Id"
+    )]
+    Organization,
+    #[sea_orm(
+        belongs_to = "crate::entities::account::Entity",
+        from = "Column::AccountId",
+        to = "crate::entities::account::Column::Id"
+    )]
+    Account,
+}
+
+impl Related<crate::entities::organization::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Organization.def()
+    }
+}
+
+impl Related<crate::entities::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+/// # Organization Role
+///
+/// Owners can do everything, admins manage the members and the invitations, members only
+/// spend from the pool.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, EnumIter, DeriveActiveEnum,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizationRole {
+    #[sea_orm(num_value = 1)]
+    Member,
+    #[sea_orm(num_value = 2)]
+    Admin,
+    #[sea_orm(num_value = 4)]
+    Owner,
+}
+
+impl OrganizationRole {
+    pub fn from_string(role: &str) -> Option<Self> {
+        match role {
+            "member" => Some(OrganizationRole::Member),
+            "admin" => Some(OrganizationRole::Admin),
+            "owner" => Some(OrganizationRole::Owner),
+            _ => None,
+        }
+    }
+
+    /// Whether the role may invite, remove and cap members
+    pub fn can_manage_members(&self) -> bool {
+        *self >= OrganizationRole::Admin
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
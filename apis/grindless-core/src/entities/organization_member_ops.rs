@@ -0,0 +1,209 @@
+use sea_orm::{sea_query::Expr, *};
+use crate::{ID, Timestamp};
+
+use super::{
+    account,
+    organization_member::{self, Model, OrganizationRole},
+};
+
+pub struct OrganizationMemberMutationCore;
+pub struct OrganizationMemberQueryCore;
+
+/// # Member Change
+///
+/// How changing the role of a member, or removing them, went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberChange {
+    Changed,
+    NotMember,
+    /// The member is the only owner, and the organization would be left without one
+    LastOwner,
+}
+
+impl OrganizationMemberMutationCore {
+    /// Locks the owners of the organization until the transaction ends, so two owners can't
+    /// both step down at the same time, each counting the other
+    async fn lock_owners<C: ConnectionTrait>(db: &C, organization_id: ID) -> Result<Vec<Model>, DbErr> {
+        organization_member::Entity::find()
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .filter(organization_member::Column::Role.eq(OrganizationRole::Owner))
+            .lock_exclusive()
+            .all(db)
+            .await
+    }
+
+    /// Whether the account is the only owner left in `owners`
+    fn is_last_owner(owners: &[Model], account_id: ID) -> bool {
+        owners.len() < 2 && owners.iter().any(|o| o.account_id == account_id)
+    }
+
+    /// # Set Member Role
+    ///
+    /// Keeps at least one owner: the check and the update are in one transaction.
+    pub async fn set_member_role(
+        db: &DbConn,
+        organization_id: ID,
+        account_id: ID,
+        role: OrganizationRole,
+        now: Timestamp,
+    ) -> Result<MemberChange, DbErr> {
+        let txn = db.begin().await?;
+
+        if role != OrganizationRole::Owner {
+            let owners = Self::lock_owners(&txn, organization_id).await?;
+            if Self::is_last_owner(&owners, account_id) {
+                txn.rollback().await?;
+                return Ok(MemberChange::LastOwner);
+            }
+        }
+
+        let result = organization_member::Entity::update_many()
+            .col_expr(organization_member::Column::Role, Expr::value(role))
+            .col_expr(organization_member::Column::UpdatedAt, Expr::value(now))
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .filter(organization_member::Column::AccountId.eq(account_id))
+            .exec(&txn)
+            .await?;
+        if result.rows_affected != 1 {
+            txn.rollback().await?;
+            return Ok(MemberChange::NotMember);
+        }
+
+        txn.commit().await?;
+
+        Ok(MemberChange::Changed)
+    }
+
+    /// # Hand Over Ownership
+    ///
+    /// For an account about to leave every organization: where it is the only owner, the
+    /// highest ranked member left, oldest first, becomes owner. Organizations with no one
+    /// else in them are left as they are. Run it inside the transaction that removes the
+    /// account.
+    pub async fn hand_over_ownership<C: ConnectionTrait>(db: &C, account_id: ID, now: Timestamp) -> Result<(), DbErr> {
+        let owned = organization_member::Entity::find()
+            .filter(organization_member::Column::AccountId.eq(account_id))
+            .filter(organization_member::Column::Role.eq(OrganizationRole::Owner))
+            .all(db)
+            .await?;
+
+        for membership in owned {
+            let owners = Self::lock_owners(db, membership.organization_id).await?;
+            if !Self::is_last_owner(&owners, account_id) {
+                continue;
+            }
+
+            let successor = organization_member::Entity::find()
+                .filter(organization_member::Column::OrganizationId.eq(membership.organization_id))
+                .filter(organization_member::Column::AccountId.ne(account_id))
+                .order_by_desc(organization_member::Column::Role)
+                .order_by_asc(organization_member::Column::CreatedAt)
+                .order_by_asc(organization_member::Column::Id)
+                .lock_exclusive()
+                .one(db)
+                .await?;
+
+            if let Some(successor) = successor {
+                organization_member::Entity::update_many()
+                    .col_expr(organization_member::Column::Role, Expr::value(OrganizationRole::Owner))
+                    .col_expr(organization_member::Column::UpdatedAt, Expr::value(now))
+                    .filter(organization_member::Column::Id.eq(successor.id))
+                    .exec(db)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// # Set Member Spending Cap
+    ///
+    /// `None` lifts the cap. With `reset_spent` what the member spent so far stops counting
+    /// against it.
+    pub async fn set_member_spending_cap(
+        db: &DbConn,
+        organization_id: ID,
+        account_id: ID,
+        spending_cap: Option<i64>,
+        reset_spent: bool,
+        now: Timestamp,
+    ) -> Result<Option<Model>, DbErr> {
+        let mut update = organization_member::Entity::update_many()
+            .col_expr(organization_member::Column::SpendingCap, Expr::value(spending_cap))
+            .col_expr(organization_member::Column::UpdatedAt, Expr::value(now));
+        if reset_spent {
+            update = update.col_expr(organization_member::Column::CreditsSpent, Expr::value(0i64));
+        }
+
+        let updated = update
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .filter(organization_member::Column::AccountId.eq(account_id))
+            .exec_with_returning(db)
+            .await?;
+
+        Ok(updated.into_iter().next())
+    }
+
+    /// # Remove Member
+    ///
+    /// Keeps at least one owner: the check and the delete are in one transaction.
+    pub async fn remove_member(db: &DbConn, organization_id: ID, account_id: ID) -> Result<MemberChange, DbErr> {
+        let txn = db.begin().await?;
+
+        let owners = Self::lock_owners(&txn, organization_id).await?;
+        if Self::is_last_owner(&owners, account_id) {
+            txn.rollback().await?;
+            return Ok(MemberChange::LastOwner);
+        }
+
+        let result = organization_member::Entity::delete_many()
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .filter(organization_member::Column::AccountId.eq(account_id))
+            .exec(&txn)
+            .await?;
+        if result.rows_affected != 1 {
+            txn.rollback().await?;
+            return Ok(MemberChange::NotMember);
+        }
+
+        txn.commit().await?;
+
+        Ok(MemberChange::Changed)
+    }
+}
+
+impl OrganizationMemberQueryCore {
+    pub async fn get_member(
+        db: &DbConn,
+        organization_id: ID,
+        account_id: ID,
+    ) -> Result<Option<Model>, DbErr> {
+        organization_member::Entity::find()
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .filter(organization_member::Column::AccountId.eq(account_id))
+            .one(db)
+            .await
+    }
+
+    /// # Get Members With Accounts
+    ///
+    /// Every member of the organization with their account, oldest first.
+    pub async fn get_members_with_accounts(
+        db: &DbConn,
+        organization_id: ID,
+    ) -> Result<Vec<(Model, Option<account::Model>)>, DbErr> {
+        organization_member::Entity::find()
+            .find_also_related(account::Entity)
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .order_by_asc(organization_member::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    pub async fn count_members(db: &DbConn, organization_id: ID) -> Result<u64, DbErr> {
+        organization_member::Entity::find()
+            .filter(organization_member::Column::OrganizationId.eq(organization_id))
+            .count(db)
+            .await
+    }
+}
@@ -0,0 +1,153 @@
+use sea_orm::*;
+use crate::{ID, Timestamp};
+
+use super::{
+    billing,
+    ledger_entry_ops::{CreditMovement, LedgerEntryMutationCore},
+    organization::{self, ActiveModel, Model},
+    organization_member,
+};
+
+pub struct OrganizationMutationCore;
+pub struct OrganizationQueryCore;
+
+impl OrganizationMutationCore {
+    /// # Create Organization
+    ///
+    /// Inserts the organization, its billing and its first owner in one transaction.
+    pub async fn create_organization(
+        db: &DbConn,
+        form_data: Model,
+        billing: billing::Model,
+        owner: organization_member::Model,
+    ) -> Result<Model, DbErr> {
+        let txn = db.begin().await?;
+
+        let organization = ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            name: Set(form_data.name.to_owned()),
+            billing_id: Set(billing.id.to_owned()),
+            created_by: Set(form_data.created_by.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+            updated_at: Set(form_data.updated_at.to_owned()),
+        }
+        .insert(&txn)
+        .await?;
+
+        billing::ActiveModel {
+            id: Set(billing.id.to_owned()),
+            account_id: Set(None),
+            organization_id: Set(Some(organization.id)),
+            credits: Set(billing.credits.to_owned()),
+            total_spent_usd: Set(billing.total_spent_usd.to_owned()),
+            created_at: Set(billing.created_at.to_owned()),
+            updated_at: Set(billing.updated_at.to_owned()),
+        }
+        .insert(&txn)
+        .await?;
+
+        organization_member::ActiveModel {
+            id: Set(owner.id.to_owned()),
+            organization_id: Set(organization.id),
+            account_id: Set(owner.account_id.to_owned()),
+            role: Set(owner.role.to_owned()),
+            spending_cap: Set(owner.spending_cap.to_owned()),
+            credits_spent: Set(owner.credits_spent.to_owned()),
+            created_at: Set(owner.created_at.to_owned()),
+            updated_at: Set(owner.updated_at.to_owned()),
+        }
+        .insert(&txn)
+        .await?;
+
+        txn.commit().await?;
+
+        Ok(organization)
+    }
+
+    /// # Transfer Credits To Organization
+    ///
+    /// Moves credits from the account's own billing to the pool, in one transaction. Both
+    /// ledger entries share `movement`. Returns `None` when the account doesn't have enough.
+    pub async fn transfer_credits_to_organization(
+        db: &DbConn,
+        account_id: ID,
+        organization_id: ID,
+        amount: i64,
+        movement: &CreditMovement,
+        now: Timestamp,
+    ) -> Result<Option<billing::Model>, DbErr> {
+        let txn = db.begin().await?;
+
+        let taken = LedgerEntryMutationCore::move_credits(
+            &txn,
+            Condition::all().add(billing::Column::AccountId.eq(account_id)),
+            -amount,
+            movement,
+            now,
+        )
+        .await?;
+        if taken.is_none() {
+            txn.rollback().await?;
+            return Ok(None);
+        }
+
+        let pool = LedgerEntryMutationCore::move_credits(
+            &txn,
+            Condition::all().add(billing::Column::OrganizationId.eq(organization_id)),
+            amount,
+            movement,
+            now,
+        )
+        .await?;
+        let pool = match pool {
+            Some(p) => p,
+            None => {
+                txn.rollback().await?;
+                return Ok(None);
+            }
+        };
+
+        txn.commit().await?;
+
+        Ok(Some(pool))
+    }
+}
+
+impl OrganizationQueryCore {
+    pub async fn get_organization_by_id(db: &DbConn, id: ID) -> Result<Option<Model>, DbErr> {
+        organization::Entity::find_by_id(id).one(db).await
+    }
+
+    /// # Get Organizations By Account ID
+    ///
+    /// Every organization the account is in, with its membership, oldest membership first.
+    pub async fn get_organizations_by_account_id(
+        db: &DbConn,
+        account_id: ID,
+    ) -> Result<Vec<(organization_member::Model, Model)>, DbErr> {
+        let memberships = organization_member::Entity::find()
+            .find_also_related(organization::Entity)
+            .filter(organization_member::Column::AccountId.eq(account_id))
+            .order_by_asc(organization_member::Column::CreatedAt)
+            .all(db)
+            .await?;
+
+        Ok(memberships
+            .into_iter()
+            .filter_map(|(member, organization)| organization.map(|o| (member, o)))
+            .collect())
+    }
+
+    /// # Get Organization Billing
+    ///
+    /// The credit pool of the organization.
+    pub async fn get_organization_billing(
+        db: &DbConn,
+        organization_id: ID,
+    ) -> Result<Option<billing::Model>, DbErr> {
+        billing::Entity::find()
+            .filter(billing::Column::OrganizationId.eq(organization_id))
+            .one(db)
+            .await
+    }
+}
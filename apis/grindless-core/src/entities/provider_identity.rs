@@ -0,0 +1,55 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{DeriveEntityModel, DeriveRelation};
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # Provider Identity
+///
+/// An external account (Google, GitHub, an OIDC issuer...) that can be used to log in to an
+/// account. `provider` and `subject` together are unique, `subject` being the provider's own
+/// stable user id.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "provider_identities")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub account_id: ID,
+
+    #[sea_orm(column_type = "Text")]
+    pub provider: String,
+    #[sea_orm(column_type = "Text")]
+    pub subject: String,
+
+    #[sea_orm(column_type = "Text", nullable)]
+    pub email: Option<String>,
+    #[sea_orm(column_type = "Boolean")]
+    pub email_verified: bool,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger")]
+    pub updated_at: Timestamp,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entities::account::Entity",
+        from = "Column::AccountId",
+        to = "crate::entities::account::Column::Id"
+    )]
+    Account,
+}
+
+impl Related<crate::entities::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
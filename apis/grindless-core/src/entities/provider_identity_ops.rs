@@ -0,0 +1,186 @@
+use sea_orm::{
+    sea_query::{Expr, Func},
+    *,
+};
+use crate::{Timestamp, ID};
+
+use super::{
+    account,
+    provider_identity::{self, ActiveModel, Model},
+};
+
+pub struct ProviderIdentityMutationCore;
+pub struct ProviderIdentityQueryCore;
+
+/// # Unlink
+///
+/// How unlinking an identity from an account went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unlink {
+    Unlinked,
+    NotFound,
+    /// The identity is the only one of the account, it would have no way to log in left
+    LastIdentity,
+}
+
+impl ProviderIdentityMutationCore {
+    pub async fn create_provider_identity(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            account_id: Set(form_data.account_id.to_owned()),
+            provider: Set(form_data.provider.to_owned()),
+            subject: Set(form_data.subject.to_owned()),
+            email: Set(form_data.email.to_owned()),
+            email_verified: Set(form_data.email_verified.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+            updated_at: Set(form_data.updated_at.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// # Unlink Provider Identity
+    ///
+    /// Removes the identity from the account, unless it is the last one. The identities of
+    /// the account stay locked from the check to the delete, so two unlinks can't each count
+    /// the other and leave the account with none.
+    pub async fn unlink_provider_identity(db: &DbConn, account_id: ID, id: ID) -> Result<Unlink, DbErr> {
+        let txn = db.begin().await?;
+
+        let identities = provider_identity::Entity::find()
+            .filter(provider_identity::Column::AccountId.eq(account_id))
+            .lock_exclusive()
+            .all(&txn)
+            .await?;
+        if !identities.iter().any(|i| i.id == id) {
+            txn.rollback().await?;
+            return Ok(Unlink::NotFound);
+        }
+        if identities.len() < 2 {
+            txn.rollback().await?;
+            return Ok(Unlink::LastIdentity);
+        }
+
+        provider_identity::Entity::delete_by_id(id).exec(&txn).await?;
+        txn.commit().await?;
+
+        Ok(Unlink::Unlinked)
+    }
+
+    /// # Update Provider Identity Email
+    ///
+    /// Keeps the email the provider reports for the identity up to date.
+    pub async fn update_provider_identity_email(
+        db: &DbConn,
+        id: ID,
+        email: Option<String>,
+        email_verified: bool,
+        updated_at: Timestamp,
+    ) -> Result<(), DbErr> {
+        provider_identity::Entity::update_many()
+            .col_expr(provider_identity::Column::Email, Expr::value(email))
+            .col_expr(provider_identity::Column::EmailVerified, Expr::value(email_verified))
+            .col_expr(provider_identity::Column::UpdatedAt, Expr::value(updated_at))
+            .filter(provider_identity::Column::Id.eq(id))
+            .exec(db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+impl ProviderIdentityQueryCore {
+    /// # Get Provider Identity
+    ///
+    /// Get the identity a provider user id belongs to, if it was ever used to log in.
+    pub async fn get_provider_identity(
+        db: &DbConn,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<Model>, DbErr> {
+        provider_identity::Entity::find()
+            .filter(provider_identity::Column::Provider.eq(provider))
+            .filter(provider_identity::Column::Subject.eq(subject))
+            .one(db)
+            .await
+    }
+
+    /// # Get Account By Provider Identity
+    ///
+    /// Get the identity together with the account it logs in to.
+    pub async fn get_account_by_provider_identity(
+        db: &DbConn,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<(Model, account::Model)>, DbErr> {
+        let model = provider_identity::Entity::find()
+            .find_also_related(account::Entity)
+            .filter(provider_identity::Column::Provider.eq(provider))
+            .filter(provider_identity::Column::Subject.eq(subject))
+            .one(db)
+            .await?;
+
+        match model {
+            Some((identity, Some(account))) => Ok(Some((identity, account))),
+            _ => Ok(None),
+        }
+    }
+
+    /// # Get Account By Verified Email
+    ///
+    /// Get the account that has an identity whose provider verified `email`, ignoring case.
+    /// Emails nobody verified are never matched, anyone can type any address into a profile.
+    pub async fn get_account_by_verified_email(
+        db: &DbConn,
+        email: &str,
+    ) -> Result<Option<account::Model>, DbErr> {
+        let model = provider_identity::Entity::find()
+            .find_also_related(account::Entity)
+            .filter(provider_identity::Column::EmailVerified.eq(true))
+            .filter(
+                Expr::expr(Func::lower(Expr::col((
+                    provider_identity::Entity,
+                    provider_identity::Column::Email,
+                ))))
+                .eq(email.to_lowercase()),
+            )
+            .filter(account::Column::Deleted.eq(false))
+            .order_by_asc(provider_identity::Column::CreatedAt)
+            .one(db)
+            .await?;
+
+        Ok(model.and_then(|(_, account)| account))
+    }
+
+    /// # Has Verified Email
+    ///
+    /// Whether one of the account's identities had `email` verified by its provider, ignoring
+    /// case. Magic link identities count, the link was opened from that inbox.
+    pub async fn has_verified_email(db: &DbConn, account_id: ID, email: &str) -> Result<bool, DbErr> {
+        let model = provider_identity::Entity::find()
+            .filter(provider_identity::Column::AccountId.eq(account_id))
+            .filter(provider_identity::Column::EmailVerified.eq(true))
+            .filter(
+                Expr::expr(Func::lower(Expr::col((
+                    provider_identity::Entity,
+                    provider_identity::Column::Email,
+                ))))
+                .eq(email.to_lowercase()),
+            )
+            .one(db)
+            .await?;
+
+        Ok(model.is_some())
+    }
+
+    pub async fn get_provider_identities_by_account_id(
+        db: &DbConn,
+        account_id: ID,
+    ) -> Result<Vec<Model>, DbErr> {
+        provider_identity::Entity::find()
+            .filter(provider_identity::Column::AccountId.eq(account_id))
+            .order_by_asc(provider_identity::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+}
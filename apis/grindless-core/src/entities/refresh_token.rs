@@ -0,0 +1,53 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{DeriveEntityModel, DeriveRelation};
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # Refresh Token
+///
+/// A refresh token that has been handed out. The `id` is the `jti` of the token, and every
+/// token minted by rotating another one shares its `family_id`, so a reused token can take
+/// down the whole family.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "refresh_tokens")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub family_id: ID,
+
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub account_id: ID,
+
+    #[sea_orm(column_type = "Boolean")]
+    pub revoked: bool,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger")]
+    pub expires_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub used_at: Option<Timestamp>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entities::account::Entity",
+        from = "Column::AccountId",
+        to = "crate::entities::account::Column::Id"
+    )]
+    Account,
+}
+
+impl Related<crate::entities::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
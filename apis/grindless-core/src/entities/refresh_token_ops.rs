@@ -0,0 +1,53 @@
+use sea_orm::{sea_query::Expr, *};
+use crate::{Timestamp, ID};
+
+use super::refresh_token::{self, ActiveModel, Model};
+
+pub struct RefreshTokenMutationCore;
+pub struct RefreshTokenQueryCore;
+
+impl RefreshTokenMutationCore {
+    pub async fn create_refresh_token(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            family_id: Set(form_data.family_id.to_owned()),
+            account_id: Set(form_data.account_id.to_owned()),
+            revoked: Set(form_data.revoked.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+            expires_at: Set(form_data.expires_at.to_owned()),
+            used_at: Set(form_data.used_at.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// # Mark Refresh Token Used
+    ///
+    /// Marks the token as used only if nobody has used or revoked it before, in a single
+    /// statement. Returns `false` when the token was already spent, which callers must treat
+    /// as reuse.
+    pub async fn mark_refresh_token_used(
+        db: &DbConn,
+        id: ID,
+        used_at: Timestamp,
+    ) -> Result<bool, DbErr> {
+        let result = refresh_token::Entity::update_many()
+            .col_expr(refresh_token::Column::UsedAt, Expr::value(used_at))
+            .filter(refresh_token::Column::Id.eq(id))
+            .filter(refresh_token::Column::UsedAt.is_null())
+            .filter(refresh_token::Column::Revoked.eq(false))
+            .exec(db)
+            .await?;
+
+        Ok(result.rows_affected == 1)
+    }
+}
+
+impl RefreshTokenQueryCore {
+    /// # Get Refresh Token By ID
+    ///
+    /// Get the refresh token by its `jti`.
+    pub async fn get_refresh_token_by_id(db: &DbConn, id: ID) -> Result<Option<Model>, DbErr> {
+        refresh_token::Entity::find_by_id(id).one(db).await
+    }
+}
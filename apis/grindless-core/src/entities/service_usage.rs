@@ -0,0 +1,37 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::DeriveEntityModel;
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # Service Usage
+///
+/// One paid request to a service: who made it, and the organization that paid for it
+/// when it wasn't paid from the account's own credits.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "service_usages")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub account_id: ID,
+    #[sea_orm(column_type = "BigInteger", nullable, indexed)]
+    pub organization_id: Option<ID>,
+
+    /// The service used, e.g. `summarize`
+    #[sea_orm(column_type = "Text")]
+    pub service: String,
+    #[sea_orm(column_type = "BigInteger")]
+    pub credits: i64,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,35 @@
+use sea_orm::*;
+use crate::ID;
+
+use super::service_usage::{self, ActiveModel, Model};
+
+pub struct ServiceUsageMutationCore;
+pub struct ServiceUsageQueryCore;
+
+impl ServiceUsageMutationCore {
+    pub async fn create_service_usage(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            account_id: Set(form_data.account_id.to_owned()),
+            organization_id: Set(form_data.organization_id.to_owned()),
+            service: Set(form_data.service.to_owned()),
+            credits: Set(form_data.credits.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+}
+
+impl ServiceUsageQueryCore {
+    /// # Get Service Usages By Account ID
+    ///
+    /// Every paid request the account made, whoever paid for it, newest first.
+    pub async fn get_service_usages_by_account_id(db: &DbConn, account_id: ID) -> Result<Vec<Model>, DbErr> {
+        service_usage::Entity::find()
+            .filter(service_usage::Column::AccountId.eq(account_id))
+            .order_by_desc(service_usage::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+}
@@ -0,0 +1,57 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{DeriveEntityModel, DeriveRelation};
+use serde::{Deserialize, Serialize};
+
+use crate::{ID, Timestamp};
+
+/// # Session
+///
+/// A logged in device. The `id` is shared with the refresh token family started at login and
+/// travels in the `sid` claim of every access token, so revoking the session kills them all.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, DeriveEntityModel)]
+#[serde(rename_all = "snake_case")]
+#[sea_orm(table_name = "sessions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: ID,
+
+    #[sea_orm(column_type = "BigInteger", indexed)]
+    pub account_id: ID,
+
+    #[sea_orm(column_type = "Text", nullable)]
+    pub device: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub ip: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub user_agent: Option<String>,
+
+    // Timestamps
+    #[sea_orm(column_type = "BigInteger")]
+    pub created_at: Timestamp,
+    #[sea_orm(column_type = "BigInteger")]
+    pub last_seen_at: Timestamp,
+
+    // Revocation
+    #[sea_orm(column_type = "Boolean")]
+    pub revoked: bool,
+    #[sea_orm(column_type = "BigInteger", nullable)]
+    pub revoked_at: Option<Timestamp>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "crate::entities::account::Entity",
+        from = "Column::AccountId",
+        to = "crate::entities::account::Column::Id"
+    )]
+    Account,
+}
+
+impl Related<crate::entities::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,147 @@
+use sea_orm::{sea_query::Expr, *};
+use crate::{Timestamp, ID};
+
+use super::{
+    refresh_token,
+    session::{self, ActiveModel, Model},
+};
+
+pub struct SessionMutationCore;
+pub struct SessionQueryCore;
+
+impl SessionMutationCore {
+    pub async fn create_session(db: &DbConn, form_data: Model) -> Result<Model, DbErr> {
+        ActiveModel {
+            id: Set(form_data.id.to_owned()),
+            account_id: Set(form_data.account_id.to_owned()),
+            device: Set(form_data.device.to_owned()),
+            ip: Set(form_data.ip.to_owned()),
+            user_agent: Set(form_data.user_agent.to_owned()),
+            created_at: Set(form_data.created_at.to_owned()),
+            last_seen_at: Set(form_data.last_seen_at.to_owned()),
+            revoked: Set(form_data.revoked.to_owned()),
+            revoked_at: Set(form_data.revoked_at.to_owned()),
+        }
+        .insert(db)
+        .await
+    }
+
+    /// # Touch Session
+    ///
+    /// Records that the session was just used, and from where.
+    pub async fn touch_session(
+        db: &DbConn,
+        id: ID,
+        ip: Option<String>,
+        last_seen_at: Timestamp,
+    ) -> Result<(), DbErr> {
+        let mut update = session::Entity::update_many()
+            .col_expr(session::Column::LastSeenAt, Expr::value(last_seen_at))
+            .filter(session::Column::Id.eq(id));
+
+        if let Some(ip) = ip {
+            update = update.col_expr(session::Column::Ip, Expr::value(ip));
+        }
+
+        update.exec(db).await?;
+        Ok(())
+    }
+
+    /// # Revoke Session
+    ///
+    /// Revokes one session of the account together with its refresh tokens, in one transaction.
+    /// Returns `false` if the account has no such active session.
+    pub async fn revoke_session(
+        db: &DbConn,
+        account_id: ID,
+        id: ID,
+        revoked_at: Timestamp,
+    ) -> Result<bool, DbErr> {
+        let txn = db.begin().await?;
+
+        let result = session::Entity::update_many()
+            .col_expr(session::Column::Revoked, Expr::value(true))
+            .col_expr(session::Column::RevokedAt, Expr::value(revoked_at))
+            .filter(session::Column::Id.eq(id))
+            .filter(session::Column::AccountId.eq(account_id))
+            .filter(session::Column::Revoked.eq(false))
+            .exec(&txn)
+            .await?;
+
+        refresh_token::Entity::update_many()
+            .col_expr(refresh_token::Column::Revoked, Expr::value(true))
+            .filter(refresh_token::Column::FamilyId.eq(id))
+            .filter(refresh_token::Column::AccountId.eq(account_id))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(result.rows_affected == 1)
+    }
+
+    /// # Revoke All Sessions
+    ///
+    /// Logs the account out everywhere. Returns how many sessions were revoked.
+    pub async fn revoke_all_sessions(
+        db: &DbConn,
+        account_id: ID,
+        revoked_at: Timestamp,
+    ) -> Result<u64, DbErr> {
+        let txn = db.begin().await?;
+
+        let result = session::Entity::update_many()
+            .col_expr(session::Column::Revoked, Expr::value(true))
+            .col_expr(session::Column::RevokedAt, Expr::value(revoked_at))
+            .filter(session::Column::AccountId.eq(account_id))
+            .filter(session::Column::Revoked.eq(false))
+            .exec(&txn)
+            .await?;
+
+        refresh_token::Entity::update_many()
+            .col_expr(refresh_token::Column::Revoked, Expr::value(true))
+            .filter(refresh_token::Column::AccountId.eq(account_id))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+        Ok(result.rows_affected)
+    }
+}
+
+impl SessionQueryCore {
+    /// # Get Session By ID
+    ///
+    /// Get the session by its ID, revoked or not.
+    pub async fn get_session_by_id(db: &DbConn, id: ID) -> Result<Option<Model>, DbErr> {
+        session::Entity::find_by_id(id).one(db).await
+    }
+
+    /// # Get Active Sessions By Account ID
+    ///
+    /// Get every session of the account that has not been revoked, most recently used first.
+    pub async fn get_active_sessions_by_account_id(
+        db: &DbConn,
+        account_id: ID,
+    ) -> Result<Vec<Model>, DbErr> {
+        session::Entity::find()
+            .filter(session::Column::AccountId.eq(account_id))
+            .filter(session::Column::Revoked.eq(false))
+            .order_by_desc(session::Column::LastSeenAt)
+            .all(db)
+            .await
+    }
+
+    /// # Get Sessions By Account ID
+    ///
+    /// Get every session of the account, revoked ones included, newest first.
+    pub async fn get_sessions_by_account_id(
+        db: &DbConn,
+        account_id: ID,
+    ) -> Result<Vec<Model>, DbErr> {
+        session::Entity::find()
+            .filter(session::Column::AccountId.eq(account_id))
+            .order_by_desc(session::Column::CreatedAt)
+            .all(db)
+            .await
+    }
+}
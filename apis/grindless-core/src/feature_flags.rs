@@ -0,0 +1,139 @@
+use ring::digest::{digest, SHA256};
+use sea_orm::{DbConn, DbErr};
+
+use crate::{
+    entities::{account, feature_flag, feature_flag_ops::FeatureFlagQueryCore},
+    ID,
+};
+
+/// # Rollout Bucket
+///
+/// Where the account falls in a percentage rollout of the flag, from 0 to 99. Hashing the
+/// key with the id keeps an account in the same bucket as the rollout grows, while
+/// different flags reach different accounts first.
+pub fn rollout_bucket(key: &str, account_id: ID) -> u8 {
+    let hash = digest(&SHA256, format!("{}:{}", key, account_id).as_bytes());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&hash.as_ref()[..8]);
+
+    (u64::from_be_bytes(prefix) % 100) as u8
+}
+
+/// # Is Enabled
+///
+/// Whether the account gets the feature: the flag must be on, and the account listed by
+/// id, have one of the targeted account flags, or fall inside the rollout.
+pub fn is_enabled(flag: &feature_flag::Model, account: &account::Model) -> bool {
+    if !flag.enabled {
+        return false;
+    }
+
+    flag.account_ids.contains(&account.id)
+        || account.flags.iter().any(|f| flag.account_flags.contains(f))
+        || (rollout_bucket(&flag.key, account.id) as i32) < flag.rollout_percentage
+}
+
+/// # Is Feature Enabled
+///
+/// Looks the flag up by key. Features without a flag are off.
+pub async fn is_feature_enabled(db: &DbConn, key: &str, account: &account::Model) -> Result<bool, DbErr> {
+    Ok(FeatureFlagQueryCore::get_feature_flag_by_key(db, key)
+        .await?
+        .is_some_and(|flag| is_enabled(&flag, account)))
+}
+
+/// # Enabled Features
+///
+/// Keys of every feature the account gets, sorted.
+pub async fn enabled_features(db: &DbConn, account: &account::Model) -> Result<Vec<String>, DbErr> {
+    Ok(FeatureFlagQueryCore::get_enabled_feature_flags(db)
+        .await?
+        .into_iter()
+        .filter(|flag| is_enabled(flag, account))
+        .map(|flag| flag.key)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::account::AccountFlags;
+
+    fn account(id: ID, flags: Vec<AccountFlags>) -> account::Model {
+        account::Model {
+            flags,
+            ..account::Model::fixture(id)
+        }
+    }
+
+    fn flag(rollout_percentage: i32) -> feature_flag::Model {
+        feature_flag::Model {
+            id: 1,
+            key: "new_editor".to_string(),
+            description: None,
+            enabled: true,
+            account_flags: vec![],
+            account_ids: vec![],
+            rollout_percentage,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn rollout_bucket_is_stable_and_in_range() {
+        for account_id in 0..1000 {
+            let bucket = rollout_bucket("new_editor", account_id);
+            assert!(bucket < 100);
+            assert_eq!(bucket, rollout_bucket("new_editor", account_id));
+        }
+    }
+
+    #[test]
+    fn rollout_bucket_depends_on_the_key() {
+        let differs = (0..100).filter(|id| rollout_bucket("a", *id) != rollout_bucket("b", *id)).count();
+        assert!(differs > 50);
+    }
+
+    #[test]
+    fn rollout_buckets_are_spread_out() {
+        let inside = (0..10_000).filter(|id| rollout_bucket("new_editor", *id) < 30).count();
+        assert!((2_500..3_500).contains(&inside), "{} accounts in a 30% rollout", inside);
+    }
+
+    #[test]
+    fn disabled_flags_reach_nobody() {
+        let mut flag = flag(100);
+        flag.enabled = false;
+        flag.account_ids = vec![7];
+        flag.account_flags = vec![AccountFlags::Beta];
+        assert!(!is_enabled(&flag, &account(7, vec![AccountFlags::Beta])));
+    }
+
+    #[test]
+    fn rollout_covers_everyone_at_100_and_nobody_at_0() {
+        for id in 0..100 {
+            assert!(is_enabled(&flag(100), &account(id, vec![])));
+            assert!(!is_enabled(&flag(0), &account(id, vec![])));
+        }
+    }
+
+    #[test]
+    fn rollout_grows_without_dropping_accounts() {
+        for id in 0..1000 {
+            if is_enabled(&flag(10), &account(id, vec![])) {
+                assert!(is_enabled(&flag(50), &account(id, vec![])));
+            }
+        }
+    }
+
+    #[test]
+    fn targeted_accounts_and_flags_are_enabled() {
+        let mut flag = flag(0);
+        flag.account_ids = vec![7];
+        flag.account_flags = vec![AccountFlags::Beta];
+        assert!(is_enabled(&flag, &account(7, vec![])));
+        assert!(is_enabled(&flag, &account(8, vec![AccountFlags::Verified, AccountFlags::Beta])));
+        assert!(!is_enabled(&flag, &account(8, vec![AccountFlags::Verified])));
+    }
+}
@@ -1,5 +1,8 @@
+pub mod billing;
 pub mod db;
 pub mod entities;
+pub mod feature_flags;
+pub mod permissions;
 pub mod response;
 pub mod util;
 
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+
+use crate::entities::account::AccountFlags;
+
+/// # Permission
+///
+/// Something an account is allowed to do beyond using its own account. Permissions are
+/// never stored, they follow from the account flags through `permissions_for`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    ViewAccounts,
+    ManageAccountFlags,
+    SuspendAccounts,
+    RestoreAccounts,
+    GrantCredits,
+    ExportAccountData,
+    ManageFeatureFlags,
+    UseBetaFeatures,
+}
+
+/// # Permissions For Flag
+///
+/// What a single account flag grants.
+pub fn permissions_for_flag(flag: &AccountFlags) -> &'static [Permission] {
+    match flag {
+        AccountFlags::Administrator => &[
+            Permission::ViewAccounts,
+            Permission::ManageAccountFlags,
+            Permission::SuspendAccounts,
+            Permission::RestoreAccounts,
+            Permission::GrantCredits,
+            Permission::ExportAccountData,
+            Permission::ManageFeatureFlags,
+            Permission::UseBetaFeatures,
+        ],
+        AccountFlags::Support => &[
+            Permission::ViewAccounts,
+            Permission::RestoreAccounts,
+            Permission::GrantCredits,
+            Permission::ExportAccountData,
+        ],
+        AccountFlags::Moderator => &[Permission::ViewAccounts, Permission::SuspendAccounts],
+        AccountFlags::Developer => &[Permission::ManageFeatureFlags, Permission::UseBetaFeatures],
+        AccountFlags::Beta => &[Permission::UseBetaFeatures],
+        AccountFlags::Verified | AccountFlags::Partner => &[],
+    }
+}
+
+/// # Permissions For
+///
+/// Every permission granted by a set of account flags, without duplicates.
+pub fn permissions_for(flags: &[AccountFlags]) -> Vec<Permission> {
+    let mut permissions: Vec<Permission> = Vec::new();
+    for flag in flags {
+        for permission in permissions_for_flag(flag) {
+            if !permissions.contains(permission) {
+                permissions.push(*permission);
+            }
+        }
+    }
+
+    permissions
+}
+
+pub fn has_permission(flags: &[AccountFlags], permission: Permission) -> bool {
+    flags
+        .iter()
+        .any(|flag| permissions_for_flag(flag).contains(&permission))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::Iterable;
+
+    #[test]
+    fn no_flags_grant_nothing() {
+        assert!(permissions_for(&[]).is_empty());
+        assert!(permissions_for(&[AccountFlags::Verified, AccountFlags::Partner]).is_empty());
+        assert!(!has_permission(&[], Permission::ViewAccounts));
+    }
+
+    #[test]
+    fn administrators_have_every_permission() {
+        let permissions = permissions_for(&[AccountFlags::Administrator]);
+        for flag in AccountFlags::iter() {
+            for permission in permissions_for_flag(&flag) {
+                assert!(permissions.contains(permission), "{:?} is missing {:?}", flag, permission);
+            }
+        }
+    }
+
+    #[test]
+    fn flags_add_up_without_duplicates() {
+        let permissions = permissions_for(&[AccountFlags::Moderator, AccountFlags::Support, AccountFlags::Beta]);
+        assert_eq!(
+            permissions,
+            vec![
+                Permission::ViewAccounts,
+                Permission::SuspendAccounts,
+                Permission::RestoreAccounts,
+                Permission::GrantCredits,
+                Permission::ExportAccountData,
+                Permission::UseBetaFeatures,
+            ]
+        );
+    }
+
+    #[test]
+    fn has_permission_matches_permissions_for() {
+        for flag in AccountFlags::iter() {
+            let flags = [flag];
+            let granted = permissions_for(&flags);
+            for permission in permissions_for(&[AccountFlags::Administrator]) {
+                assert_eq!(has_permission(&flags, permission), granted.contains(&permission));
+            }
+        }
+    }
+
+    #[test]
+    fn support_cannot_change_flags_or_suspend() {
+        assert!(!has_permission(&[AccountFlags::Support], Permission::ManageAccountFlags));
+        assert!(!has_permission(&[AccountFlags::Support], Permission::SuspendAccounts));
+        assert!(has_permission(&[AccountFlags::Moderator], Permission::SuspendAccounts));
+    }
+}
@@ -15,7 +15,21 @@ pub enum Errors {
     GatewayTimeout,
 
     InvalidToken,
+    MalformedToken,
+    InvalidTokenSignature,
+    InvalidTokenIssuer,
+    InvalidTokenAudience,
+    TokenNotYetValid,
     ExpiredToken,
+    RevokedToken,
+
+    AccountDeleted,
+    AccountPendingDeletion,
+    AccountSuspended,
+    InvalidOAuthState,
+
+    InsufficientCredits,
+    SpendingCapReached,
 }
 
 impl Errors {
@@ -32,7 +46,19 @@ impl Errors {
             Errors::ServiceUnavailable => "Service Unavailable".to_string(),
             Errors::GatewayTimeout => "Gateway Timeout".to_string(),
             Errors::InvalidToken => "Invalid Token".to_string(),
+            Errors::MalformedToken => "Malformed Token".to_string(),
+            Errors::InvalidTokenSignature => "Invalid Token Signature".to_string(),
+            Errors::InvalidTokenIssuer => "Invalid Token Issuer".to_string(),
+            Errors::InvalidTokenAudience => "Invalid Token Audience".to_string(),
+            Errors::TokenNotYetValid => "Token Not Yet Valid".to_string(),
             Errors::ExpiredToken => "Expired Token".to_string(),
+            Errors::RevokedToken => "Revoked Token".to_string(),
+            Errors::AccountDeleted => "Account Deleted".to_string(),
+            Errors::AccountPendingDeletion => "Account Pending Deletion".to_string(),
+            Errors::AccountSuspended => "Account Suspended".to_string(),
+            Errors::InvalidOAuthState => "Invalid OAuth State".to_string(),
+            Errors::InsufficientCredits => "Insufficient Credits".to_string(),
+            Errors::SpendingCapReached => "Spending Cap Reached".to_string(),
         }
     }
 }
\ No newline at end of file
@@ -35,6 +35,9 @@ pub struct ResponseObject<T> {
 pub struct ResponseObjectError {
     pub error_id: Errors,
     pub message: Option<String>,
+    /// The input field the error is about, for validation errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
 }
 
 /// # Responder impl for Response from actix_web
@@ -1,8 +1,10 @@
-use jsonwebtoken::{encode, Algorithm, DecodingKey, EncodingKey, Header};
+use jsonwebtoken::{decode_header, encode, errors::ErrorKind, Header, Validation};
 use log::debug;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
-use crate::{response::errors::Errors, Timestamp, ID};
+use crate::{entities::account::AccountFlags, response::errors::Errors, Timestamp, ID};
+
+use super::keyring::{KeyRing, SigningKey};
 
 /// # Tokens
 ///
@@ -12,12 +14,50 @@ use crate::{response::errors::Errors, Timestamp, ID};
 pub struct JWTClaims {
     pub r#type: JWTTokenType,
     pub sub: ID,         // Account Id
-    pub provider_id: String, // Provider Id
-    pub iss: Option<ID>, // Application ID
+    pub iss: String,     // Issuer, the API that minted the token
+    pub aud: String,     // Audience, who the token is meant for
+    pub iat: Timestamp,  // Issued at
+    pub nbf: Timestamp,  // Not valid before
     pub exp: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<ID>, // Token ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<ID>, // Session ID
+    #[serde(default)]
+    pub roles: Vec<AccountFlags>, // Account flags at the time the token was issued
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<String>>, // Only set for API keys, `None` means full access
     pub max_requests_per_hour: u64,
 }
 
+/// # OAuth State Claims
+///
+/// The `state` of an OAuth login, signed so only logins started by `begin` are accepted.
+/// `jti` points at the stored login attempt, which holds the PKCE verifier.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub struct OAuthStateClaims {
+    pub jti: ID,
+    pub provider: String,
+    pub iss: String,
+    pub aud: String,
+    pub iat: Timestamp,
+    pub nbf: Timestamp,
+    pub exp: Timestamp,
+}
+
+/// # JWT Settings
+///
+/// What every token is issued with and checked against.
+#[derive(Debug, Clone)]
+pub struct JWTSettings {
+    pub issuer: String,
+    pub audience: String,
+    /// Clock skew tolerated on `exp`, `nbf` and `iat`, in seconds
+    pub leeway: u64,
+}
+
+pub static DEFAULT_LEEWAY_SECONDS: u64 = 30;
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "snake_case")]
@@ -37,60 +77,109 @@ impl std::fmt::Display for JWTTokenType {
 
 /// # New Token
 ///
-/// This function generates a new token
-pub fn new_token(
-    key_pem: &[u8],
-    alg: Algorithm,
-    claims: JWTClaims,
+/// This function generates a new token, signed with `key` and tagged with its `kid`
+pub fn new_token<T: Serialize>(
+    key: &SigningKey,
+    claims: T,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    let header = Header::new(alg);
-    let token = encode(&header, &claims, &EncodingKey::from_rsa_pem(key_pem)?)?;
+    let mut header = Header::new(key.algorithm);
+    header.kid = Some(key.kid.clone());
+
+    let token = encode(&header, &claims, &key.encoding_key)?;
     Ok(token)
 }
 
 /// # Validate Token
 ///
-/// This function validates a token
+/// This function validates an access or refresh token, see [`decode_token`].
 pub fn validate_token(
-    public_pem: &[u8],
-    algorithm: Algorithm,
+    key_ring: &KeyRing,
+    settings: &JWTSettings,
     token: &str,
 ) -> Result<JWTClaims, Errors> {
-    // Set the validation parameters
-    let mut validation = jsonwebtoken::Validation::new(algorithm);
+    let claims = decode_token::<JWTClaims>(key_ring, settings, token)?;
+    check_issued_at(claims.iat, settings)?;
 
-    // Set the required claims
-    validation.set_required_spec_claims(&["sub", "exp", "iss", "aud"]);
+    Ok(claims)
+}
 
-    debug!("🔑 Token Validation: {:?}", validation);
-    debug!("🔑 Token: {}", token);
+/// # Validate OAuth State
+///
+/// Same checks as [`validate_token`], for the `state` of an OAuth login.
+pub fn validate_oauth_state(
+    key_ring: &KeyRing,
+    settings: &JWTSettings,
+    state: &str,
+) -> Result<OAuthStateClaims, Errors> {
+    let claims = decode_token::<OAuthStateClaims>(key_ring, settings, state)?;
+    check_issued_at(claims.iat, settings)?;
 
-    let key = match DecodingKey::from_rsa_pem(public_pem) {
-        Ok(key) => key,
-        Err(_) => {
-            return Err(Errors::InvalidToken);
-        }
+    Ok(claims)
+}
+
+/// # Decode Token
+///
+/// This function validates a token against the key named by its `kid`. Tokens without a
+/// `kid` were signed before the key ring existed and are checked with the signing key.
+/// Each failure maps to its own error, so clients can tell "refresh" from "log in again".
+fn decode_token<T: DeserializeOwned + std::fmt::Debug>(
+    key_ring: &KeyRing,
+    settings: &JWTSettings,
+    token: &str,
+) -> Result<T, Errors> {
+    let header = decode_header(token).map_err(|_| Errors::MalformedToken)?;
+    let key = match &header.kid {
+        Some(kid) => key_ring.get(kid).ok_or(Errors::InvalidTokenSignature)?,
+        None => key_ring.signing_key(),
     };
 
+    // Never let the token pick its own algorithm
+    if header.alg != key.algorithm {
+        return Err(Errors::InvalidTokenSignature);
+    }
+
+    // Set the validation parameters
+    let mut validation = Validation::new(key.algorithm);
+    // `sub` is left out on purpose: ours is a numeric account ID, and jsonwebtoken only
+    // recognises string subjects, so it would always count it as missing
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud"]);
+    validation.set_issuer(&[&settings.issuer]);
+    validation.set_audience(&[&settings.audience]);
+    validation.validate_exp = true;
+    validation.validate_nbf = true;
+    validation.leeway = settings.leeway;
+
+    debug!("🔑 Token Validation: {:?}", validation);
+
     // Decode the token
-    let token_data = match jsonwebtoken::decode::<JWTClaims>(
-        token,
-        &key,
-        &jsonwebtoken::Validation::new(algorithm),
-    ) {
+    let token_data = match jsonwebtoken::decode::<T>(token, &key.decoding_key, &validation) {
         Ok(data) => data,
-        Err(e) => match *e.kind() {
-            _ => {
-                return Err(Errors::InvalidToken);
-            }
-        },
+        Err(e) => {
+            let err = match e.kind() {
+                ErrorKind::InvalidSignature | ErrorKind::InvalidAlgorithm => Errors::InvalidTokenSignature,
+                ErrorKind::ExpiredSignature => Errors::ExpiredToken,
+                ErrorKind::ImmatureSignature => Errors::TokenNotYetValid,
+                ErrorKind::InvalidAudience => Errors::InvalidTokenAudience,
+                ErrorKind::InvalidIssuer => Errors::InvalidTokenIssuer,
+                _ => Errors::MalformedToken,
+            };
+
+            debug!("❌ Token rejected: {:?}", e);
+            return Err(err);
+        }
     };
 
     debug!("🔑 Token Data: {:?}", token_data);
-    if token_data.claims.exp < chrono::Utc::now().timestamp() {
-        debug!("❌ Token expired");
-        return Err(Errors::ExpiredToken);
-    }
 
     Ok(token_data.claims)
-}
\ No newline at end of file
+}
+
+/// jsonwebtoken doesn't look at iat, a token from the future is not valid yet
+fn check_issued_at(iat: Timestamp, settings: &JWTSettings) -> Result<(), Errors> {
+    if iat > chrono::Utc::now().timestamp() + settings.leeway as i64 {
+        debug!("❌ Token issued in the future");
+        return Err(Errors::TokenNotYetValid);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,382 @@
+use std::{fs, path::Path};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use log::info;
+use rsa::pkcs8::EncodePrivateKey;
+use ring::{
+    rand::SystemRandom,
+    signature::{
+        EcdsaKeyPair, Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents,
+        ECDSA_P256_SHA256_FIXED_SIGNING,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// # Signing Key
+///
+/// One private key of the key ring, with everything derived from it: the algorithm it signs
+/// with, the key to verify tokens and its public JWK.
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub encoding_key: EncodingKey,
+    pub decoding_key: DecodingKey,
+    pub jwk: Jwk,
+}
+
+/// # JWK
+///
+/// Public half of a signing key, as published in `/.well-known/jwks.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Jwk {
+    pub kty: String,
+    pub kid: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
+}
+
+impl SigningKey {
+    /// # From Private PEM
+    ///
+    /// Builds a signing key from a PKCS#8 (`PRIVATE KEY`) or PKCS#1 (`RSA PRIVATE KEY`) PEM.
+    /// The algorithm follows the key type: RSA signs RS256, P-256 signs ES256 and Ed25519
+    /// signs EdDSA.
+    pub fn from_private_pem(kid: &str, key_pem: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let parsed = pem::parse(key_pem)?;
+        let der = parsed.contents();
+
+        let rsa = match parsed.tag() {
+            "RSA PRIVATE KEY" => RsaKeyPair::from_der(der).ok(),
+            "PRIVATE KEY" => RsaKeyPair::from_pkcs8(der).ok(),
+            "EC PRIVATE KEY" => {
+                return Err(format!(
+                    "key {}: SEC1 EC keys are not supported, convert it with `openssl pkcs8 -topk8 -nocrypt`",
+                    kid
+                )
+                .into())
+            }
+            tag => return Err(format!("key {}: unsupported PEM block {}", kid, tag).into()),
+        };
+
+        if let Some(rsa) = rsa {
+            let components = RsaPublicKeyComponents::<Vec<u8>>::from(rsa.public());
+            let n = URL_SAFE_NO_PAD.encode(&components.n);
+            let e = URL_SAFE_NO_PAD.encode(&components.e);
+
+            return Ok(SigningKey {
+                kid: kid.to_string(),
+                algorithm: Algorithm::RS256,
+                encoding_key: EncodingKey::from_rsa_pem(key_pem)?,
+                decoding_key: DecodingKey::from_rsa_components(&n, &e)?,
+                jwk: Jwk {
+                    kty: "RSA".to_string(),
+                    kid: kid.to_string(),
+                    alg: "RS256".to_string(),
+                    key_use: "sig".to_string(),
+                    n: Some(n),
+                    e: Some(e),
+                    crv: None,
+                    x: None,
+                    y: None,
+                },
+            });
+        }
+
+        if let Ok(ec) = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, der, &SystemRandom::new()) {
+            // Uncompressed point: 0x04 || x || y
+            let point = ec.public_key().as_ref();
+            let x = URL_SAFE_NO_PAD.encode(&point[1..33]);
+            let y = URL_SAFE_NO_PAD.encode(&point[33..65]);
+
+            return Ok(SigningKey {
+                kid: kid.to_string(),
+                algorithm: Algorithm::ES256,
+                encoding_key: EncodingKey::from_ec_pem(key_pem)?,
+                decoding_key: DecodingKey::from_ec_components(&x, &y)?,
+                jwk: Jwk {
+                    kty: "EC".to_string(),
+                    kid: kid.to_string(),
+                    alg: "ES256".to_string(),
+                    key_use: "sig".to_string(),
+                    n: None,
+                    e: None,
+                    crv: Some("P-256".to_string()),
+                    x: Some(x),
+                    y: Some(y),
+                },
+            });
+        }
+
+        if let Ok(ed) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der) {
+            let x = URL_SAFE_NO_PAD.encode(ed.public_key().as_ref());
+
+            return Ok(SigningKey {
+                kid: kid.to_string(),
+                algorithm: Algorithm::EdDSA,
+                encoding_key: EncodingKey::from_ed_pem(key_pem)?,
+                decoding_key: DecodingKey::from_ed_components(&x)?,
+                jwk: Jwk {
+                    kty: "OKP".to_string(),
+                    kid: kid.to_string(),
+                    alg: "EdDSA".to_string(),
+                    key_use: "sig".to_string(),
+                    n: None,
+                    e: None,
+                    crv: Some("Ed25519".to_string()),
+                    x: Some(x),
+                    y: None,
+                },
+            });
+        }
+
+        Err(format!("key {}: not an RSA, P-256 or Ed25519 private key", kid).into())
+    }
+}
+
+/// # Key Ring
+///
+/// Every key tokens may be signed with. Only one of them signs new tokens, the rest stay
+/// around so tokens signed before a rotation keep validating until they expire.
+pub struct KeyRing {
+    keys: Vec<SigningKey>,
+    signing_kid: String,
+}
+
+impl KeyRing {
+    pub fn new(keys: Vec<SigningKey>, signing_kid: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        if !keys.iter().any(|k| k.kid == signing_kid) {
+            return Err(format!("signing key {} is not in the key ring", signing_kid).into());
+        }
+
+        Ok(KeyRing {
+            keys,
+            signing_kid: signing_kid.to_string(),
+        })
+    }
+
+    /// # Load
+    ///
+    /// Builds the key ring from every `<kid>.pem` private key in `dir` plus an optional
+    /// inline key, e.g. one passed through an env var. Without an explicit `signing_kid` the
+    /// inline key signs, or else the last kid in lexicographic order, so naming keys by date
+    /// makes the newest one active.
+    pub fn load(
+        dir: Option<&Path>,
+        inline_key: Option<(&str, &[u8])>,
+        signing_kid: Option<&str>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut keys = match dir {
+            Some(dir) => Self::read_dir(dir)?,
+            None => Vec::new(),
+        };
+
+        let inline_kid = match inline_key {
+            Some((kid, key_pem)) => {
+                if keys.iter().any(|k| k.kid == kid) {
+                    return Err(format!("key {} is both inline and in the keys dir", kid).into());
+                }
+
+                let key = SigningKey::from_private_pem(kid, key_pem)?;
+                info!("Loaded inline token key {} ({:?})", key.kid, key.algorithm);
+                keys.push(key);
+                Some(kid.to_string())
+            }
+            None => None,
+        };
+
+        let signing_kid = match (signing_kid, inline_kid) {
+            (Some(kid), _) => kid.to_string(),
+            (None, Some(kid)) => kid,
+            (None, None) => match keys.last() {
+                Some(k) => k.kid.clone(),
+                None => return Err("no token keys configured".into()),
+            },
+        };
+
+        Self::new(keys, &signing_kid)
+    }
+
+    fn read_dir(dir: &Path) -> Result<Vec<SigningKey>, Box<dyn std::error::Error>> {
+        let mut paths = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().map(|ext| ext == "pem").unwrap_or(false))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let mut keys = Vec::new();
+        for path in paths {
+            let kid = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(kid) => kid.to_string(),
+                None => continue,
+            };
+
+            let key = SigningKey::from_private_pem(&kid, &fs::read(&path)?)?;
+            info!("Loaded token key {} ({:?})", key.kid, key.algorithm);
+            keys.push(key);
+        }
+
+        Ok(keys)
+    }
+
+    /// # Signing Key
+    ///
+    /// The key new tokens are signed with.
+    pub fn signing_key(&self) -> &SigningKey {
+        self.keys
+            .iter()
+            .find(|k| k.kid == self.signing_kid)
+            .expect("signing key is checked when the key ring is built")
+    }
+
+    pub fn get(&self, kid: &str) -> Option<&SigningKey> {
+        self.keys.iter().find(|k| k.kid == kid)
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().map(|k| k.jwk.clone()).collect(),
+        }
+    }
+}
+
+/// # Generate Private Key PEM
+///
+/// New PKCS8 private key for `algorithm`, in the same format [`SigningKey::from_private_pem`]
+/// reads. RS256 keys are 4096 bits, like the ones `openssl genpkey` made before.
+pub fn generate_private_key_pem(algorithm: Algorithm) -> Result<String, Box<dyn std::error::Error>> {
+    let rng = SystemRandom::new();
+
+    let der = match algorithm {
+        Algorithm::RS256 => {
+            let key = rsa::RsaPrivateKey::new(&mut rand::rngs::OsRng, 4096)?;
+            key.to_pkcs8_der()?.as_bytes().to_vec()
+        }
+        Algorithm::ES256 => EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .map_err(|_| "could not generate ES256 key")?
+            .as_ref()
+            .to_vec(),
+        Algorithm::EdDSA => Ed25519KeyPair::generate_pkcs8(&rng)
+            .map_err(|_| "could not generate EdDSA key")?
+            .as_ref()
+            .to_vec(),
+        other => return Err(format!("unsupported algorithm {:?}, use RS256, ES256 or EdDSA", other).into()),
+    };
+
+    Ok(pem::encode(&pem::Pem::new("PRIVATE KEY", der)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, encode, Header, Validation};
+    use std::collections::HashMap;
+
+    fn key(kid: &str, algorithm: Algorithm) -> SigningKey {
+        let pem = generate_private_key_pem(algorithm).unwrap();
+        SigningKey::from_private_pem(kid, pem.as_bytes()).unwrap()
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("grindless-keyring-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn signs_with_the_algorithm_of_the_key() {
+        let es = key("es", Algorithm::ES256);
+        assert_eq!(es.algorithm, Algorithm::ES256);
+        assert_eq!(es.jwk.kty, "EC");
+        assert_eq!(es.jwk.crv.as_deref(), Some("P-256"));
+
+        let ed = key("ed", Algorithm::EdDSA);
+        assert_eq!(ed.algorithm, Algorithm::EdDSA);
+        assert_eq!(ed.jwk.kty, "OKP");
+        assert!(ed.jwk.n.is_none());
+    }
+
+    #[test]
+    fn tokens_verify_with_the_derived_key() {
+        for algorithm in [Algorithm::ES256, Algorithm::EdDSA] {
+            let key = key("k", algorithm);
+            let claims = HashMap::from([("sub", "1")]);
+            let token = encode(&Header::new(key.algorithm), &claims, &key.encoding_key).unwrap();
+
+            let mut validation = Validation::new(key.algorithm);
+            validation.required_spec_claims.clear();
+            validation.validate_exp = false;
+            let decoded = decode::<HashMap<String, String>>(&token, &key.decoding_key, &validation).unwrap();
+            assert_eq!(decoded.claims["sub"], "1");
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_pem_blocks() {
+        let pem = pem::encode(&pem::Pem::new("CERTIFICATE", vec![1, 2, 3]));
+        assert!(SigningKey::from_private_pem("k", pem.as_bytes()).is_err());
+        assert!(SigningKey::from_private_pem("k", b"not a pem").is_err());
+    }
+
+    #[test]
+    fn signing_kid_must_be_in_the_ring() {
+        assert!(KeyRing::new(vec![key("a", Algorithm::EdDSA)], "b").is_err());
+
+        let ring = KeyRing::new(vec![key("a", Algorithm::EdDSA), key("b", Algorithm::EdDSA)], "a").unwrap();
+        assert_eq!(ring.signing_key().kid, "a");
+        assert!(ring.get("b").is_some());
+        assert!(ring.get("c").is_none());
+        assert_eq!(ring.jwks().keys.len(), 2);
+    }
+
+    #[test]
+    fn load_picks_the_last_kid_of_the_dir() {
+        let dir = temp_dir("last");
+        for kid in ["2024-01", "2024-03", "2024-02"] {
+            fs::write(dir.join(format!("{}.pem", kid)), generate_private_key_pem(Algorithm::EdDSA).unwrap()).unwrap();
+        }
+        fs::write(dir.join("notes.txt"), "not a key").unwrap();
+
+        let ring = KeyRing::load(Some(&dir), None, None).unwrap();
+        assert_eq!(ring.signing_key().kid, "2024-03");
+        assert_eq!(ring.jwks().keys.len(), 3);
+
+        let ring = KeyRing::load(Some(&dir), None, Some("2024-01")).unwrap();
+        assert_eq!(ring.signing_key().kid, "2024-01");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_prefers_the_inline_key() {
+        let dir = temp_dir("inline");
+        fs::write(dir.join("old.pem"), generate_private_key_pem(Algorithm::EdDSA).unwrap()).unwrap();
+        let inline = generate_private_key_pem(Algorithm::ES256).unwrap();
+
+        let ring = KeyRing::load(Some(&dir), Some(("inline", inline.as_bytes())), None).unwrap();
+        assert_eq!(ring.signing_key().kid, "inline");
+        assert!(ring.get("old").is_some());
+
+        assert!(KeyRing::load(Some(&dir), Some(("old", inline.as_bytes())), None).is_err());
+        assert!(KeyRing::load(None, None, None).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,5 @@
 pub mod log;
 pub mod jwt;
-
-pub fn random_int() -> u32 {
-    rand::random::<u32>()
-} 
\ No newline at end of file
+pub mod keyring;
+pub mod secrets;
+pub mod snowflake;
@@ -0,0 +1,43 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::{distributions::Alphanumeric, Rng};
+use ring::{
+    constant_time,
+    digest::{digest, SHA256},
+};
+
+/// # Random Token
+///
+/// A random alphanumeric string, for secrets handed to users.
+pub fn random_token(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// # SHA-256 Hex
+///
+/// Hex digest of a secret, which is what gets stored instead of the secret itself.
+pub fn sha256_hex(input: &str) -> String {
+    digest(&SHA256, input.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// # Secrets Equal
+///
+/// Compares two secrets, or their digests, in constant time, so a mismatch doesn't tell
+/// how much of it was right.
+pub fn secrets_equal(a: &str, b: &str) -> bool {
+    constant_time::verify_slices_are_equal(a.as_bytes(), b.as_bytes()).is_ok()
+}
+
+/// # PKCE Challenge
+///
+/// The `S256` code challenge of a PKCE code verifier (RFC 7636).
+pub fn pkce_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(digest(&SHA256, code_verifier.as_bytes()).as_ref())
+}
@@ -0,0 +1,214 @@
+use std::{
+    fmt,
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Timestamp, ID};
+
+/// 2024-01-01T00:00:00Z, where the timestamps in IDs count from
+pub const ID_EPOCH: Timestamp = 1_704_067_200;
+
+const WORKER_ID_BITS: u32 = 8;
+const SEQUENCE_BITS: u32 = 12;
+
+/// Workers are numbered from 0 up to this
+pub const MAX_WORKER_ID: u16 = (1 << WORKER_ID_BITS) - 1;
+const MAX_SEQUENCE: i64 = (1 << SEQUENCE_BITS) - 1;
+
+static GENERATOR: OnceLock<IdGenerator> = OnceLock::new();
+
+#[derive(Debug)]
+pub enum IdGeneratorError {
+    InvalidWorkerId(u16),
+    AlreadyInitialized,
+}
+
+impl fmt::Display for IdGeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdGeneratorError::InvalidWorkerId(id) => {
+                write!(f, "worker id {} is not between 0 and {}", id, MAX_WORKER_ID)
+            }
+            IdGeneratorError::AlreadyInitialized => write!(f, "the id generator was already set up"),
+        }
+    }
+}
+
+impl std::error::Error for IdGeneratorError {}
+
+/// # ID Generator
+///
+/// Snowflake-style IDs: 33 bits of seconds since `ID_EPOCH`, 8 bits of worker id and 12 bits
+/// of sequence. That is 53 bits, so IDs stay exact as JavaScript numbers in the frontend.
+/// IDs from one worker always grow, and two workers never make the same one, so every
+/// running instance needs its own worker id.
+pub struct IdGenerator {
+    worker_id: i64,
+    /// Second and sequence of the last ID
+    last: Mutex<(i64, i64)>,
+}
+
+impl IdGenerator {
+    pub fn new(worker_id: u16) -> Result<Self, IdGeneratorError> {
+        if worker_id > MAX_WORKER_ID {
+            return Err(IdGeneratorError::InvalidWorkerId(worker_id));
+        }
+
+        Ok(IdGenerator {
+            worker_id: worker_id as i64,
+            last: Mutex::new((0, 0)),
+        })
+    }
+
+    pub fn next_id(&self) -> ID {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default()
+            - ID_EPOCH;
+
+        self.next_id_at(now)
+    }
+
+    /// The ID for `now`, in seconds since `ID_EPOCH`
+    fn next_id_at(&self, now: i64) -> ID {
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let (last_second, last_sequence) = *last;
+
+        // If the clock went back, keep counting from the last second instead of handing
+        // out IDs that were already used. A full second borrows the next one.
+        let (second, sequence) = if now > last_second {
+            (now, 0)
+        } else if last_sequence < MAX_SEQUENCE {
+            (last_second, last_sequence + 1)
+        } else {
+            (last_second + 1, 0)
+        };
+        *last = (second, sequence);
+
+        (second << (WORKER_ID_BITS + SEQUENCE_BITS)) | (self.worker_id << SEQUENCE_BITS) | sequence
+    }
+}
+
+/// # Init ID Generator
+///
+/// Sets the worker id for `new_id`, once, before any ID is made.
+pub fn init_id_generator(worker_id: u16) -> Result<(), IdGeneratorError> {
+    GENERATOR
+        .set(IdGenerator::new(worker_id)?)
+        .map_err(|_| IdGeneratorError::AlreadyInitialized)
+}
+
+/// # New ID
+///
+/// A new unique ID for a row. Uses worker 0 if `init_id_generator` was never called.
+pub fn new_id() -> ID {
+    GENERATOR
+        .get_or_init(|| IdGenerator::new(0).expect("worker 0 is always valid"))
+        .next_id()
+}
+
+/// # ID Created At
+///
+/// When the ID was made, in Unix seconds. `None` for IDs made before this generator, which
+/// were random and fit in 32 bits.
+pub fn id_created_at(id: ID) -> Option<Timestamp> {
+    match id > u32::MAX as ID {
+        true => Some((id >> (WORKER_ID_BITS + SEQUENCE_BITS)) + ID_EPOCH),
+        false => None,
+    }
+}
+
+/// # ID Worker
+///
+/// Which worker made the ID.
+pub fn id_worker(id: ID) -> u16 {
+    ((id >> SEQUENCE_BITS) & MAX_WORKER_ID as i64) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2026-01-01T00:00:00Z
+    const SECOND: i64 = 1_767_225_600 - ID_EPOCH;
+
+    #[test]
+    fn ids_grow_within_a_second() {
+        let generator = IdGenerator::new(3).unwrap();
+        let ids = (0..100).map(|_| generator.next_id_at(SECOND)).collect::<Vec<ID>>();
+
+        assert!(ids.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        assert!(ids.iter().all(|id| id_created_at(*id) == Some(SECOND + ID_EPOCH)));
+        assert!(ids.iter().all(|id| id_worker(*id) == 3));
+    }
+
+    #[test]
+    fn sequence_rolls_over_into_the_next_second() {
+        let generator = IdGenerator::new(0).unwrap();
+        let ids = (0..=MAX_SEQUENCE + 1).map(|_| generator.next_id_at(SECOND)).collect::<Vec<ID>>();
+
+        assert_eq!(ids[MAX_SEQUENCE as usize] & MAX_SEQUENCE, MAX_SEQUENCE);
+        let borrowed = ids[MAX_SEQUENCE as usize + 1];
+        assert_eq!(borrowed & MAX_SEQUENCE, 0);
+        assert_eq!(id_created_at(borrowed), Some(SECOND + 1 + ID_EPOCH));
+        assert!(ids.windows(2).all(|pair| pair[1] > pair[0]));
+
+        // The borrowed second keeps counting when the clock catches up
+        let next = generator.next_id_at(SECOND + 1);
+        assert_eq!(next, borrowed + 1);
+    }
+
+    #[test]
+    fn ids_keep_growing_when_the_clock_goes_back() {
+        let generator = IdGenerator::new(0).unwrap();
+        let first = generator.next_id_at(SECOND);
+        let second = generator.next_id_at(SECOND - 10);
+        assert!(second > first);
+        assert_eq!(id_created_at(second), Some(SECOND + ID_EPOCH));
+    }
+
+    #[test]
+    fn workers_never_collide() {
+        let a = IdGenerator::new(1).unwrap();
+        let b = IdGenerator::new(2).unwrap();
+        assert_ne!(a.next_id_at(SECOND), b.next_id_at(SECOND));
+        assert_eq!(id_worker(IdGenerator::new(MAX_WORKER_ID).unwrap().next_id_at(SECOND)), MAX_WORKER_ID);
+    }
+
+    #[test]
+    fn new_id_round_trips_its_time() {
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as Timestamp;
+        let id = new_id();
+        let after = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as Timestamp;
+
+        let created_at = id_created_at(id).unwrap();
+        assert!(before <= created_at && created_at <= after + 1);
+        assert!(new_id() > id);
+    }
+
+    #[test]
+    fn ids_are_exact_in_javascript() {
+        let generator = IdGenerator::new(MAX_WORKER_ID).unwrap();
+        // 2100-01-01T00:00:00Z
+        let id = generator.next_id_at(4_102_444_800 - ID_EPOCH);
+        assert!(id < 1 << 53);
+    }
+
+    #[test]
+    fn worker_ids_out_of_range_are_refused() {
+        assert!(IdGenerator::new(MAX_WORKER_ID).is_ok());
+        assert!(matches!(
+            IdGenerator::new(MAX_WORKER_ID + 1),
+            Err(IdGeneratorError::InvalidWorkerId(256))
+        ));
+        assert!(IdGenerator::new(u16::MAX).is_err());
+    }
+
+    #[test]
+    fn old_random_ids_have_no_time() {
+        assert_eq!(id_created_at(123_456), None);
+        assert_eq!(id_created_at(u32::MAX as ID), None);
+    }
+}
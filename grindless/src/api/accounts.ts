@@ -9,4 +9,138 @@ export async function get_my_info<T>(access_token: String): Promise<AxiosRespons
             "Authorization": `Bearer ${access_token}`
         }
     })
-}
\ No newline at end of file
+}
+
+export async function get_my_identities<T>(access_token: String): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "GET",
+        url: `${api_uri}/api/accounts/@me/identities`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
+
+export async function begin_link_identity<T>(access_token: String, provider: string, redirect_uri: string): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("redirect_uri", redirect_uri);
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/accounts/@me/identities/${provider}/begin`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function link_identity<T>(access_token: String, provider: string, code: string, state: string): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("code", code);
+    data.append("state", state);
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/accounts/@me/identities/${provider}`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function unlink_identity<T>(access_token: String, identity_id: number): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "DELETE",
+        url: `${api_uri}/api/accounts/@me/identities/${identity_id}`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
+
+
+export async function update_locked_fields<T>(access_token: String, fields: string[]): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("fields", fields.join(","));
+
+    return instance({
+        method: "PUT",
+        url: `${api_uri}/api/accounts/@me/locked-fields`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+
+export async function delete_me<T>(access_token: String, reason: string): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("reason", reason);
+
+    return instance({
+        method: "DELETE",
+        url: `${api_uri}/api/accounts/@me`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function restore_me<T>(access_token: String): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/accounts/@me/restore`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
+
+
+export interface PatchMe {
+    name?: string,
+    avatar?: string | null,
+    preferences?: Record<string, unknown>,
+}
+
+export async function patch_me<T>(access_token: String, patch: PatchMe): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "PATCH",
+        url: `${api_uri}/api/accounts/@me`,
+        data: patch,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/json",
+        }
+    })
+}
+
+
+export async function export_me(access_token: String): Promise<AxiosResponse<Blob, any>> {
+    return instance({
+        method: "GET",
+        url: `${api_uri}/api/accounts/@me/export`,
+        responseType: "blob",
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
+
+export async function get_my_ledger<T>(access_token: String): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "GET",
+        url: `${api_uri}/api/accounts/@me/ledger`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
@@ -20,16 +20,88 @@ export const instance = Axios.create({
     }
 });
 
-export async function access_google_oauth<T>(code: string): Promise<AxiosResponse<T, any>> {
+export interface TokenPair {
+    access_token: string,
+    refresh_token: string,
+    token_type: string,
+    expires_in: number,
+}
+
+export interface OAuthLogin {
+    provider: string,
+    url: string,
+    state: string,
+    expires_in: number,
+}
+
+export async function begin_oauth_login<T>(provider: string, redirect_uri: string): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("redirect_uri", redirect_uri);
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/oauth/${provider}/begin`,
+        data,
+        headers: {
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function access_oauth<T>(provider: string, code: string, state: string): Promise<AxiosResponse<T, any>> {
     let data = new URLSearchParams();
     data.append("code", code);
+    data.append("state", state);
 
     return instance({
         method: "POST",
-        url: `${api_uri}/api/oauth/access/google`,
+        url: `${api_uri}/api/oauth/access/${provider}`,
         data,
         headers: {
             "Content-Type": "application/x-www-form-urlencoded",
         }
     })
-}
\ No newline at end of file
+}
+
+export async function refresh_access_token<T>(refresh_token: string): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("refresh_token", refresh_token);
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/oauth/refresh`,
+        data,
+        headers: {
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function request_magic_link<T>(email: string, redirect_uri: string): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("email", email);
+    data.append("redirect_uri", redirect_uri);
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/oauth/magic-link`,
+        data,
+        headers: {
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function verify_magic_link<T>(token: string): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("token", token);
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/oauth/magic-link/verify`,
+        data,
+        headers: {
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
@@ -6,8 +6,14 @@ export interface Account {
     name: string,
     avatar: string | undefined | null,
     flags: number[],
+    locked_fields: ("name" | "email" | "avatar")[],
+    preferences: Record<string, unknown>,
     created_at: number,
     updated_at: number,
+    suspended_at: number | undefined | null,
+    suspension_reason: string | undefined | null,
+    suspended_by: number | undefined | null,
+    suspended_until: number | undefined | null,
     deleted: boolean,
     deletion_requested_at: number | undefined | null,
     deletion_reason: string | undefined | null,
@@ -1,8 +1,30 @@
 export interface Billing {
     id: number;
-    account_id: number;
+    account_id: number | null;
+    organization_id: number | null;
     credits: number;
     total_spent_usd: number;
     created_at: number;
     updated_at: number;
-}
\ No newline at end of file
+}
+
+export type LedgerReason =
+    | "signup_grant"
+    | "service_charge"
+    | "refund"
+    | "purchase"
+    | "admin_adjustment"
+    | "transfer"
+    | "opening_balance";
+
+export interface LedgerEntry {
+    id: number;
+    billing_id: number;
+    account_id: number | null;
+    amount: number;
+    balance: number;
+    reason: LedgerReason;
+    service: string | null;
+    request_id: number | null;
+    created_at: number;
+}
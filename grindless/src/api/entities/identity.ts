@@ -0,0 +1,10 @@
+export interface ProviderIdentity {
+    id: number,
+    account_id: number,
+    provider: string,
+    subject: string,
+    email: string | undefined | null,
+    email_verified: boolean,
+    created_at: number,
+    updated_at: number,
+}
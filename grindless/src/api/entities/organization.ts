@@ -0,0 +1,33 @@
+export type OrganizationRole = "member" | "admin" | "owner";
+
+export interface Organization {
+    id: number,
+    name: string,
+    billing_id: number,
+    created_by: number,
+    created_at: number,
+    updated_at: number,
+}
+
+export interface OrganizationMember {
+    id: number,
+    organization_id: number,
+    account_id: number,
+    role: OrganizationRole,
+    spending_cap: number | null,
+    credits_spent: number,
+    created_at: number,
+    updated_at: number,
+}
+
+export interface OrganizationInvitation {
+    id: number,
+    organization_id: number,
+    email: string,
+    role: OrganizationRole,
+    invited_by: number,
+    accepted_by: number | null,
+    created_at: number,
+    expires_at: number,
+    accepted_at: number | null,
+}
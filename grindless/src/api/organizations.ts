@@ -0,0 +1,155 @@
+import { AxiosResponse } from "axios";
+import { api_uri, instance } from "./api";
+import { OrganizationRole } from "./entities/organization";
+
+export async function get_my_organizations<T>(access_token: String): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "GET",
+        url: `${api_uri}/api/organizations`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
+
+export async function create_organization<T>(access_token: String, name: string): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("name", name);
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/organizations`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function get_organization<T>(access_token: String, organization_id: number): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "GET",
+        url: `${api_uri}/api/organizations/${organization_id}`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
+
+export async function transfer_credits<T>(access_token: String, organization_id: number, amount: number): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("amount", amount.toString());
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/organizations/${organization_id}/credits`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function get_organization_ledger<T>(access_token: String, organization_id: number): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "GET",
+        url: `${api_uri}/api/organizations/${organization_id}/ledger`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
+
+export async function get_invitations<T>(access_token: String, organization_id: number): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "GET",
+        url: `${api_uri}/api/organizations/${organization_id}/invitations`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
+
+export async function invite_member<T>(access_token: String, organization_id: number, email: string, role: OrganizationRole): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("email", email);
+    data.append("role", role);
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/organizations/${organization_id}/invitations`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function revoke_invitation<T>(access_token: String, organization_id: number, invitation_id: number): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "DELETE",
+        url: `${api_uri}/api/organizations/${organization_id}/invitations/${invitation_id}`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
+
+export async function accept_invitation<T>(access_token: String, token: string): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("token", token);
+
+    return instance({
+        method: "POST",
+        url: `${api_uri}/api/organizations/invitations/accept`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function set_member_role<T>(access_token: String, organization_id: number, account_id: number, role: OrganizationRole): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("role", role);
+
+    return instance({
+        method: "PUT",
+        url: `${api_uri}/api/organizations/${organization_id}/members/${account_id}/role`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function set_spending_cap<T>(access_token: String, organization_id: number, account_id: number, spending_cap: number | null, reset_spent: boolean): Promise<AxiosResponse<T, any>> {
+    let data = new URLSearchParams();
+    data.append("spending_cap", spending_cap === null ? "" : spending_cap.toString());
+    data.append("reset_spent", reset_spent.toString());
+
+    return instance({
+        method: "PUT",
+        url: `${api_uri}/api/organizations/${organization_id}/members/${account_id}/spending-cap`,
+        data,
+        headers: {
+            "Authorization": `Bearer ${access_token}`,
+            "Content-Type": "application/x-www-form-urlencoded",
+        }
+    })
+}
+
+export async function remove_member<T>(access_token: String, organization_id: number, account_id: number): Promise<AxiosResponse<T, any>> {
+    return instance({
+        method: "DELETE",
+        url: `${api_uri}/api/organizations/${organization_id}/members/${account_id}`,
+        headers: {
+            "Authorization": `Bearer ${access_token}`
+        }
+    })
+}
@@ -8,9 +8,10 @@ export interface SummaryResponse {
     operation_cost_in_credits: number,
     operation_cost_in_usd: number,
     remaining_credits: number,
+    organization_id: number | null,
 }
 
-export async function summary_api(access_token: String, kind: String, text: String): Promise<AxiosResponse<Response<SummaryResponse>, any>> {
+export async function summary_api(access_token: String, kind: String, text: String, organization_id?: number): Promise<AxiosResponse<Response<SummaryResponse>, any>> {
     return instance({
         method: "POST",
         url: `${api_uri}/api/services/summarize`,
@@ -21,6 +22,7 @@ export async function summary_api(access_token: String, kind: String, text: Stri
         data: {
             kind,
             text,
+            ...(organization_id !== undefined && { organization_id }),
         }
     })
 }
\ No newline at end of file
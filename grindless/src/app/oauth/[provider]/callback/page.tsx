@@ -0,0 +1,64 @@
+"use client"
+import { access_oauth, Response, TokenPair } from "@/api/api";
+import { link_identity } from "@/api/accounts";
+import { DarkModeLoading } from "@/components/dashboard/dark-mode-loading";
+import { useParams, useSearchParams } from "next/navigation"
+import { useEffect } from "react";
+
+export default function OAuthCallback() {
+    const { provider } = useParams<{ provider: string }>();
+    const searchParams = useSearchParams();
+    const code = searchParams.get("code");
+    const state = searchParams.get("state");
+
+    useEffect(() => {
+        async function handleOAuth() {
+            const expected_state = sessionStorage.getItem("oauth_state");
+            const linking = sessionStorage.getItem("oauth_link") === "true";
+            sessionStorage.removeItem("oauth_state");
+            sessionStorage.removeItem("oauth_link");
+
+            if (!state || state !== expected_state) {
+                console.error(`${provider} OAuth state mismatch`);
+                window.location.href = "/";
+                return;
+            }
+
+            const access_token = localStorage.getItem("access_token");
+            if (linking && code && access_token) {
+                try {
+                    await link_identity(access_token, provider, code, state);
+                } catch (error) {
+                    console.error(`${provider} link Grindless API Error`, error);
+                }
+
+                window.location.href = "/launchpad";
+                return;
+            }
+
+            if (code && code.length > 0) {
+                try {
+                    let res = await access_oauth(provider, code, state);
+                    let data: Response<TokenPair> = res.data as Response<TokenPair>;
+
+                    if(!(data.success && data.message && data.data)) return; // invalid response
+                    if(data.data.access_token.length > 0) {
+                        localStorage.setItem("access_token", data.data.access_token);
+                        localStorage.setItem("refresh_token", data.data.refresh_token);
+                    }
+                } catch (error) {
+                    console.error(`${provider} OAuth Grindless API Error`, error);
+                    window.location.href = "/";
+                }
+            }
+            
+            window.location.href = "/launchpad";
+        }
+
+        handleOAuth()
+    }, []);
+
+    return (
+        <DarkModeLoading />
+    )
+}
\ No newline at end of file
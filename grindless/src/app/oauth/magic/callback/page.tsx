@@ -0,0 +1,42 @@
+"use client"
+import { Response, TokenPair, verify_magic_link } from "@/api/api";
+import { DarkModeLoading } from "@/components/dashboard/dark-mode-loading";
+import { useSearchParams } from "next/navigation"
+import { useEffect } from "react";
+
+export default function MagicLinkCallback() {
+    const searchParams = useSearchParams();
+    const token = searchParams.get("token");
+
+    useEffect(() => {
+        async function handleMagicLink() {
+            if (token && token.length > 0) {
+                try {
+                    let res = await verify_magic_link(token);
+                    let data: Response<TokenPair> = res.data as Response<TokenPair>;
+
+                    if(!(data.success && data.message && data.data)) {
+                        window.location.href = "/";
+                        return;
+                    }
+                    if(data.data.access_token.length > 0) {
+                        localStorage.setItem("access_token", data.data.access_token);
+                        localStorage.setItem("refresh_token", data.data.refresh_token);
+                    }
+                } catch (error) {
+                    console.error("Magic link Grindless API Error", error);
+                    window.location.href = "/";
+                    return;
+                }
+            }
+
+            window.location.href = "/launchpad";
+        }
+
+        handleMagicLink()
+    }, []);
+
+    return (
+        <DarkModeLoading />
+    )
+}
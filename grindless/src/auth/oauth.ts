@@ -0,0 +1,28 @@
+import { begin_oauth_login, OAuthLogin, Response } from "@/api/api";
+import { begin_link_identity } from "@/api/accounts";
+
+// Send the user to the provider. With `link` the identity they come back with is linked
+// to the logged in account instead of logging in
+export async function StartOAuth(provider: string, link: boolean = false) {
+    const access_token = localStorage.getItem("access_token");
+    if (link && !access_token) return;
+
+    // Wherever the app runs (production, a preview, localhost), as long as the API allows it
+    const redirect_uri = `${window.location.origin}/oauth/${provider}/callback`;
+
+    let res = link
+        ? await begin_link_identity(access_token!, provider, redirect_uri)
+        : await begin_oauth_login(provider, redirect_uri);
+    let data: Response<OAuthLogin> = res.data as Response<OAuthLogin>;
+    if (!data.data) return;
+
+    // Checked by the callback, so only logins started here are finished
+    sessionStorage.setItem("oauth_state", data.data.state);
+    if (link) {
+        sessionStorage.setItem("oauth_link", "true");
+    } else {
+        sessionStorage.removeItem("oauth_link");
+    }
+
+    window.location.href = data.data.url;
+}
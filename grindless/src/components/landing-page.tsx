@@ -1,20 +1,39 @@
 'use client'
 
-import { GetOAuthGoogleURL } from "@/auth/google"
+import { request_magic_link } from "@/api/api"
+import { StartOAuth } from "@/auth/oauth"
 import { Button } from "@/components/ui/button"
+import { Input } from "@/components/ui/input"
 import useOAuthStore from "@/stores/oauth"
 import { MenuIcon, XIcon } from "lucide-react"
 import Link from "next/link"
 import { useEffect, useState } from "react"
+import { GitHubIcon } from "./icons/github"
 import { GoogleIcon } from "./icons/google"
 
 export default function LandingPage() {
     const [isMenuOpen, setIsMenuOpen] = useState(false)
+    const [email, setEmail] = useState("")
+    const [magicLinkSent, setMagicLinkSent] = useState(false)
     let oauthStore = useOAuthStore();
 
-    function handleGoogleSignIn() {
-        const google_oauth_url = GetOAuthGoogleURL({ date: new Date().toISOString() });
-        window.location.href = google_oauth_url;
+    async function handleSignIn(provider: string) {
+        try {
+            await StartOAuth(provider);
+        } catch (error) {
+            console.error(`${provider} OAuth Grindless API Error`, error);
+        }
+    }
+
+    async function handleMagicLink() {
+        if (email.length === 0) return;
+
+        try {
+            await request_magic_link(email, `${window.location.origin}/oauth/magic/callback`);
+            setMagicLinkSent(true);
+        } catch (error) {
+            console.error("Magic link Grindless API Error", error);
+        }
     }
 
     return (
@@ -45,11 +64,17 @@ export default function LandingPage() {
                         </>}
                         {!oauthStore.authenticated && <>
                             <Button className="flex flex-row items-center justify-center space-x-2 text-white" variant="ghost" size="sm" onClick={() => {
-                                handleGoogleSignIn();
+                                handleSignIn("google");
                             }}>
                                 <GoogleIcon className="h-6 w-6" />
                                 <span>Sign in With Google</span>
                             </Button>
+                            <Button className="flex flex-row items-center justify-center space-x-2 text-white" variant="ghost" size="sm" onClick={() => {
+                                handleSignIn("github");
+                            }}>
+                                <GitHubIcon className="h-6 w-6" />
+                                <span>Sign in With GitHub</span>
+                            </Button>
                         </>}
                     </nav>
                     <Button variant="ghost" size="icon" className="md:hidden z-50" onClick={() => setIsMenuOpen(!isMenuOpen)}>
@@ -74,8 +99,12 @@ export default function LandingPage() {
                             {!oauthStore.authenticated && <>
                                 <Link href="#login" className="text-2xl font-medium" onClick={() => {
                                     setIsMenuOpen(false);
-                                    handleGoogleSignIn();
+                                    handleSignIn("google");
                                 }}>Sign in With Google</Link>
+                                <Link href="#login" className="text-2xl font-medium" onClick={() => {
+                                    setIsMenuOpen(false);
+                                    handleSignIn("github");
+                                }}>Sign in With GitHub</Link>
                             </>}
                         </nav>
                     </div>
@@ -97,11 +126,28 @@ export default function LandingPage() {
                                     return;
                                 }
 
-                                handleGoogleSignIn();
+                                handleSignIn("google");
                             }} className="w-full sm:w-auto bg-gradient-to-r from-lime-400 to-lime-700 text-white hover:from-lime-500 hover:to-lime-800 font-medium text-lg px-12 py-3 rounded-lg shadow-lg hover:shadow-xl transition-all duration-200">
                                 {oauthStore.authenticated ? "Dashboard" : "Get Started"}
                             </Button>
                         </div>
+                        {!oauthStore.authenticated && (
+                            <form className="flex flex-col sm:flex-row justify-center items-center gap-2 mt-6 max-w-md mx-auto" onSubmit={(e) => {
+                                e.preventDefault();
+                                handleMagicLink();
+                            }}>
+                                {magicLinkSent ? (
+                                    <p className="text-gray-300">Check your inbox for a login link.</p>
+                                ) : (
+                                    <>
+                                        <Input type="email" placeholder="you@work.com" value={email} onChange={(e) => setEmail(e.target.value)} className="bg-gray-900 border-gray-700 text-white" />
+                                        <Button type="submit" variant="ghost" className="text-white">
+                                            Email me a link
+                                        </Button>
+                                    </>
+                                )}
+                            </form>
+                        )}
                     </div>
                 </main>
             </div>
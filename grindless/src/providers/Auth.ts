@@ -1,7 +1,7 @@
 "use client"
 
 import { get_my_info } from "@/api/accounts";
-import { Response } from "@/api/api";
+import { refresh_access_token, Response, TokenPair } from "@/api/api";
 import { Account } from "@/api/entities/account";
 import { Billing } from "@/api/entities/billing";
 import useOAuthStore from "@/stores/oauth";
@@ -12,16 +12,19 @@ export default function AuthProvider({ children }: {
 }) {
     async function fetch_my_account(access_token: string): Promise<{
         account: Account,
-        billing: Billing
+        billing: Billing,
+        features: string[]
     }> {
         let res = await get_my_info(access_token || "");
 
         let data: Response<{
             account: Account,
-            billing: Billing
+            billing: Billing,
+            features: string[]
         }> = res.data as Response<{
             account: Account,
-            billing: Billing
+            billing: Billing,
+            features: string[]
         }>;
 
         if(!(data.success && data.message && data.data && data.data.account && data.data.billing)) return Promise.reject("Invalid response");
@@ -34,8 +37,9 @@ export default function AuthProvider({ children }: {
             let user = JSON.stringify(data.data.account);
             localStorage.setItem("@me", user);
             localStorage.setItem("@me.billing", JSON.stringify(data.data.billing));
+            localStorage.setItem("@me.features", JSON.stringify(data.data.features || []));
             localStorage.setItem("@me.last_updated", Date.now().toString());
-            useOAuthStore.setState({ authenticated: true, profile: data.data.account, billing: data.data.billing, access_token });
+            useOAuthStore.setState({ authenticated: true, profile: data.data.account, billing: data.data.billing, features: data.data.features || [], access_token });
             return Promise.resolve(data.data);
         }
 
@@ -53,20 +57,38 @@ export default function AuthProvider({ children }: {
         // Profile exists and was updated within 5 minutes
         if (billing_obj && profile_obj && last_updated_obj && (Date.now() - last_updated_obj) < 300000) {
             console.log("Using profile cache");
-            useOAuthStore.setState({ authenticated: true, checked: true, profile: profile_obj, billing: billing_obj, access_token });
+            useOAuthStore.setState({ authenticated: true, checked: true, profile: profile_obj, billing: billing_obj, features: GetCacheFeatures(), access_token });
             return;
         }
 
         if (access_token && access_token.length > 0) {
-            try {
-                fetch_my_account(access_token);
-            } catch (error) {
+            fetch_my_account(access_token).catch(async () => {
+                // Access tokens are short lived, try to swap the refresh token before giving up
+                let refresh_token = localStorage.getItem("refresh_token");
+                if (refresh_token && refresh_token.length > 0) {
+                    try {
+                        let res = await refresh_access_token(refresh_token);
+                        let data: Response<TokenPair> = res.data as Response<TokenPair>;
+
+                        if (data.success == "ok" && data.data) {
+                            localStorage.setItem("access_token", data.data.access_token);
+                            localStorage.setItem("refresh_token", data.data.refresh_token);
+                            await fetch_my_account(data.data.access_token);
+                            return;
+                        }
+                    } catch (error) {
+                        console.error("Grindless API Error refreshing token", error);
+                    }
+                }
+
                 localStorage.removeItem("access_token");
+                localStorage.removeItem("refresh_token");
                 localStorage.removeItem("@me");
                 localStorage.removeItem("@me.billing");
+                localStorage.removeItem("@me.features");
                 localStorage.removeItem("@me.last_updated");
                 useOAuthStore.setState({ checked: true });
-            }
+            });
         }
 
         useOAuthStore.setState({ checked: true });
@@ -85,6 +107,16 @@ export function GetCacheBilling(): Billing | null {
     return billing ? JSON.parse(billing) : null;
 }
 
+export function GetCacheFeatures(): string[] {
+    let features = localStorage.getItem("@me.features");
+    return features ? JSON.parse(features) : [];
+}
+
+// Whether a feature flag is on for the logged in account, features ship dark until it is
+export function useFeature(key: string): boolean {
+    return useOAuthStore((state) => state.features.includes(key));
+}
+
 export function UpdateCacheBilling(billing: Billing) {
     localStorage.setItem("@me.billing", JSON.stringify(billing));
     useOAuthStore.setState({ billing });
@@ -92,9 +124,11 @@ export function UpdateCacheBilling(billing: Billing) {
 
 export function Logout() {
     localStorage.removeItem("access_token");
+    localStorage.removeItem("refresh_token");
     localStorage.removeItem("@me");
     localStorage.removeItem("@me.billing");
+    localStorage.removeItem("@me.features");
     localStorage.removeItem("@me.last_updated");
-    useOAuthStore.setState({ authenticated: false, profile: null, billing: null, access_token: "" });
+    useOAuthStore.setState({ authenticated: false, profile: null, billing: null, features: [], access_token: "" });
     window.location.href = "/";
 }
\ No newline at end of file
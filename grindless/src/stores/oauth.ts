@@ -8,6 +8,8 @@ export interface OAuthStore {
     access_token: string | null,
     profile: Account | undefined | null,
     billing: Billing | undefined | null,
+    // Keys of the feature flags turned on for the account
+    features: string[],
 }
 
 export const useOAuthStore = create<OAuthStore>((set) => ({
@@ -16,6 +18,7 @@ export const useOAuthStore = create<OAuthStore>((set) => ({
     access_token: null,
     profile: null,
     billing: null,
+    features: [],
 }));
 
 export default useOAuthStore;
\ No newline at end of file